@@ -1,10 +1,30 @@
 use super::token_response::TokenResponse;
 use crate::config::Config;
+use crate::http::SendWithRetry;
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 use spinners::{Spinner, Spinners};
 use std::time::{Duration, Instant};
 
+/// Added to the polling interval each time the server answers `slow_down`.
+const SLOW_DOWN_INCREMENT: Duration = Duration::from_secs(5);
+/// Consecutive failed polls tolerated before login gives up.
+const MAX_POLL_FAILURES: u32 = 3;
+
+/// Polling interval after a response carrying `error`; per RFC 8628 a
+/// `slow_down` permanently increases it.
+fn next_interval(interval: Duration, error: Option<&str>) -> Duration {
+    match error {
+        Some("slow_down") => interval + SLOW_DOWN_INCREMENT,
+        _ => interval,
+    }
+}
+
+/// Errors that end the device flow; anything else means keep polling.
+fn is_terminal(error: &str) -> bool {
+    !matches!(error, "authorization_pending" | "slow_down")
+}
+
 #[derive(Serialize, Deserialize)]
 struct DeviceAuthResponse {
     device_code: String,
@@ -15,25 +35,20 @@ struct DeviceAuthResponse {
     interval: usize,
 }
 
-pub fn login(config: &Config) -> Result<TokenResponse, Box<dyn std::error::Error>> {
+pub fn login(config: &Config, scope: &str) -> Result<TokenResponse, Box<dyn std::error::Error>> {
+    let auth = config.auth()?;
     let client = Client::new();
     let resp = client
-        .post(&format!("https://{}/oauth/device/code", config.domain))
+        .post(format!("https://{}/oauth/device/code", auth.domain))
         .form(&[
-            ("client_id", config.client_id.as_str()),
-            ("audience", config.audience.as_str()),
-            ("scope", "openid profile email offline_access"),
+            ("client_id", auth.client_id),
+            ("audience", auth.audience),
+            ("scope", scope),
         ])
-        .send();
+        .send_logged();
 
-    let response = match resp {
-        Ok(resp) => resp,
-        Err(e) => return Err(e.into()),
-    };
-    let device_auth_response: DeviceAuthResponse = match response.json::<DeviceAuthResponse>() {
-        Ok(resp) => resp,
-        Err(e) => return Err(e.into()),
-    };
+    let response = resp?;
+    let device_auth_response: DeviceAuthResponse = response.json::<DeviceAuthResponse>()?;
 
     println!(
         "Go to {} and enter the code: {}",
@@ -42,12 +57,14 @@ pub fn login(config: &Config) -> Result<TokenResponse, Box<dyn std::error::Error
 
     _ = open::that(device_auth_response.verification_uri_complete);
 
-    let token_endpoint = format!("https://{}/oauth/token", config.domain);
+    let token_endpoint = format!("https://{}/oauth/token", auth.domain);
 
     let start_instant = Instant::now();
     let expiry_duration = Duration::from_secs(device_auth_response.expires_in as u64);
 
     let mut sp = Spinner::new(Spinners::Dots9, "Polling for token".into());
+    let mut interval = Duration::from_secs(device_auth_response.interval as u64);
+    let mut failures = 0;
 
     loop {
         if Instant::now() >= start_instant + expiry_duration {
@@ -63,26 +80,62 @@ pub fn login(config: &Config) -> Result<TokenResponse, Box<dyn std::error::Error
             .form(&[
                 ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
                 ("device_code", &device_auth_response.device_code),
-                ("client_id", config.client_id.as_str()),
+                ("client_id", auth.client_id),
             ])
-            .send()
+            .send_logged()
             .and_then(|res| res.json::<TokenResponse>());
 
         match resp_result {
             Ok(resp) => {
+                failures = 0;
                 if resp.access_token.is_some() {
                     sp.stop();
                     return Ok(resp);
                 }
+                if let Some(error) = resp.error.as_deref().filter(|e| is_terminal(e)) {
+                    sp.stop();
+                    return Err(format!("Login failed: {}", error).into());
+                }
+                interval = next_interval(interval, resp.error.as_deref());
             }
+            // a network blip shouldn't abort a login the user is halfway through
+            Err(_) if failures < MAX_POLL_FAILURES => failures += 1,
             Err(e) => {
                 sp.stop();
                 return Err(Box::new(e));
             }
         }
 
-        std::thread::sleep(std::time::Duration::from_secs(
-            device_auth_response.interval as u64,
-        ));
+        std::thread::sleep(interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_interval() {
+        let interval = Duration::from_secs(5);
+        assert_eq!(next_interval(interval, None), interval);
+        assert_eq!(
+            next_interval(interval, Some("authorization_pending")),
+            interval
+        );
+        let slowed = next_interval(interval, Some("slow_down"));
+        assert_eq!(slowed, Duration::from_secs(10));
+        // each slow_down adds another increment
+        assert_eq!(
+            next_interval(slowed, Some("slow_down")),
+            Duration::from_secs(15)
+        );
+    }
+
+    #[test]
+    fn test_is_terminal() {
+        assert!(!is_terminal("authorization_pending"));
+        assert!(!is_terminal("slow_down"));
+        assert!(is_terminal("expired_token"));
+        assert!(is_terminal("access_denied"));
     }
 }
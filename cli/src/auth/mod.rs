@@ -1,7 +1,9 @@
 pub mod get_token;
 pub mod login;
 pub mod token_response;
+pub mod userinfo;
 
 pub use get_token::*;
 pub use login::*;
 pub use token_response::*;
+pub use userinfo::*;
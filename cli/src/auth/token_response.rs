@@ -7,4 +7,7 @@ pub struct TokenResponse {
     pub refresh_token: Option<String>,
     pub expires_in: Option<usize>,
     pub scope: Option<String>,
+    /// Set instead of the token while polling, e.g. `authorization_pending`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
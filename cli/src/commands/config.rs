@@ -0,0 +1,49 @@
+use super::CommandContext;
+use crate::config::set_config_value;
+use clap::Subcommand;
+use cred_store::CredStore;
+
+fn parse_config_key(s: &str) -> Result<String, String> {
+    crate::config::config_var(s).map(|_| s.to_string())
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Print the configuration in effect
+    Show,
+    /// Save a value to ~/.todo/config.json; environment variables override it
+    Set {
+        #[arg(value_parser = parse_config_key)]
+        key: String,
+        value: String,
+    },
+}
+
+/// The client id isn't a secret, but there is no need to print all of it.
+fn mask(value: &str) -> String {
+    let visible: String = value.chars().take(4).collect();
+    format!("{}****", visible)
+}
+
+pub fn config<T: CredStore>(command: &ConfigCommand, context: &mut CommandContext<T>) {
+    match command {
+        ConfigCommand::Show => {
+            println!("profile: {}", context.profile);
+            for (key, value) in context.config.entries() {
+                let value = match value {
+                    Some(value) if key == "client_id" => mask(value),
+                    Some(value) => value.to_string(),
+                    None => "(not set)".to_string(),
+                };
+                println!("{}: {}", key, value);
+            }
+        }
+        ConfigCommand::Set { key, value } => match set_config_value(key, value) {
+            Ok(()) => println!("Saved {}.", key),
+            Err(e) => {
+                eprintln!("Couldn't save configuration: {}", e);
+                std::process::exit(1);
+            }
+        },
+    }
+}
@@ -5,4 +5,8 @@ use cred_store::CredStore;
 pub struct CommandContext<'a, T: CredStore> {
     pub config: &'a Config,
     pub cred_store: &'a mut T,
+    /// Active profile; selects the credentials file and config overrides.
+    pub profile: String,
+    /// Todo server URL, from `--server-url` or the config.
+    pub server_url: String,
 }
@@ -0,0 +1,8 @@
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+pub struct LoginCommand {
+    /// Space separated scopes to request instead of the configured `SCOPES`
+    #[arg(long)]
+    pub scope: Option<String>,
+}
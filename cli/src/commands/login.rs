@@ -1,18 +1,8 @@
 use cred_store::CredStore;
 
+use super::login_options::LoginCommand;
 use super::CommandContext;
 use crate::auth;
-use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct UserInfo {
-    external_id: String,
-    email: String,
-    name: String,
-    id: String,
-    tenant_id: String,
-}
 
 fn save_tokens<T: CredStore>(
     access_token: &str,
@@ -27,35 +17,27 @@ fn save_tokens<T: CredStore>(
         .save()
 }
 
-fn get_userinfo(url: &str, access_token: &str) -> Result<UserInfo, Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let resp = client
-        .get(format!("{}/userinfo", url))
-        .bearer_auth(access_token)
-        .send()?;
-
-    let userinfo: UserInfo = match resp.json::<UserInfo>() {
-        Ok(resp) => resp,
-        Err(e) => return Err(e.into()),
-    };
-
-    Ok(userinfo)
-}
-
-pub fn login<T: CredStore>(context: &mut CommandContext<T>) {
-    match auth::login(context.config) {
+pub fn login<T: CredStore>(command: &LoginCommand, context: &mut CommandContext<T>) {
+    let scope = command.scope.as_deref().unwrap_or(&context.config.scopes);
+    match auth::login(context.config, scope) {
         Ok(resp) => {
             let access_token = resp.access_token.clone().unwrap();
             let refresh_token = resp.refresh_token.clone().unwrap();
-            println!();
-            println!("Access Token: {}", access_token);
             if save_tokens(&access_token, &refresh_token, context).is_err() {
                 eprintln!("Couldn't configure credentials.");
                 std::process::exit(1);
             }
-            match get_userinfo(&context.config.todo_url, &access_token) {
+            match auth::get_userinfo(&context.server_url, &access_token) {
                 Ok(userinfo) => {
-                    println!("User Info: {:?}", userinfo);
+                    auth::cache_userinfo(context.cred_store, &userinfo, &access_token);
+                    if context.cred_store.save().is_err() {
+                        eprintln!("Couldn't cache user info.");
+                    }
+                    println!();
+                    println!("Logged in as {} ({}).", userinfo.name, userinfo.email);
+                    if let Some(expiry) = auth::token_expiry(&access_token) {
+                        println!("Access token expires at {}.", expiry.to_rfc2822());
+                    }
                 }
                 Err(e) => {
                     eprintln!("Couldn't get user info: {}", e);
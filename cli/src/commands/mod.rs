@@ -1,8 +1,13 @@
 #[path = "command-executor.rs"]
 mod command_executor;
+mod config;
 mod context;
 mod login;
+#[path = "login-options.rs"]
+mod login_options;
 mod logout;
+mod profile;
+mod status;
 mod todo;
 #[path = "todos-add.rs"]
 mod todos_add;
@@ -12,25 +17,53 @@ mod todos_add_options;
 mod todos_complete;
 #[path = "todos-delete.rs"]
 mod todos_delete;
+#[path = "todos-export.rs"]
+mod todos_export;
+#[path = "todos-export-options.rs"]
+mod todos_export_options;
+#[path = "todos-import.rs"]
+mod todos_import;
+#[path = "todos-import-options.rs"]
+mod todos_import_options;
 #[path = "todos-list.rs"]
 mod todos_list;
+#[path = "todos-list-options.rs"]
+mod todos_list_options;
 #[path = "todos-options.rs"]
 mod todos_options;
+#[path = "todos-reminders.rs"]
+mod todos_reminders;
 #[path = "todos-view.rs"]
 mod todos_view;
+mod tui;
+#[path = "tui-app.rs"]
+mod tui_app;
+mod whoami;
 
 use command_executor::CommandExecutor;
+use config::{config, ConfigCommand};
 pub use context::CommandContext;
 use login::login;
+use login_options::LoginCommand;
 use logout::logout;
+use profile::{profile, ProfileCommand};
+use status::status;
 use todo::*;
 use todos_add::todos_add;
 use todos_add_options::TodoAddCommand;
 use todos_complete::todos_complete;
 use todos_delete::todos_delete;
+use todos_export::todos_export;
+use todos_export_options::TodoExportCommand;
+use todos_import::todos_import;
+use todos_import_options::TodoImportCommand;
 use todos_list::todos_list;
+use todos_list_options::TodoListCommand;
 use todos_options::*;
+use todos_reminders::todos_reminders;
 use todos_view::todos_view;
+use tui::tui;
+use whoami::whoami;
 
 use crate::auth::get_token;
 use clap::{Parser, Subcommand};
@@ -38,25 +71,66 @@ use cred_store::CredStore;
 
 #[derive(Parser)]
 #[clap(author, version, about = "A command line tool for managing todos")]
-struct Cli {
+pub struct Cli {
+    /// Account to use; each profile keeps its own credentials
+    #[clap(long, global = true, value_parser = parse_profile)]
+    pub profile: Option<String>,
+    /// Todo server to talk to instead of the configured `TODO_URL`
+    #[clap(long, global = true)]
+    pub server_url: Option<String>,
+    /// Log more: -v for info, -vv for requests with status and timing, -vvv
+    /// for everything
+    #[clap(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Log nothing, not even warnings
+    #[clap(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
     #[clap(subcommand)]
     command: Command,
 }
 
+fn parse_profile(s: &str) -> Result<String, String> {
+    crate::profile::validate_profile(s).map(|_| s.to_string())
+}
+
 #[derive(Subcommand)]
 enum Command {
-    Login,
+    /// Show or change the CLI configuration
+    #[clap(subcommand)]
+    Config(ConfigCommand),
+    Login(LoginCommand),
     Logout,
+    /// Manage profiles for multiple accounts
+    #[clap(subcommand)]
+    Profile(ProfileCommand),
+    /// Show whether the stored credentials are still valid
+    Status,
     #[clap(subcommand)]
     Todos(TodosCommand),
+    /// Browse and edit todos in a full-screen terminal UI
+    Tui,
+    /// Show the logged in user
+    Whoami {
+        /// Fetch the user info from the server instead of the cache
+        #[arg(long)]
+        refresh: bool,
+    },
 }
 
 impl<T: CredStore> CommandExecutor<T> for Command {
     fn execute(&self, context: &mut CommandContext<T>) {
         match self {
-            Command::Login => login(context),
+            Command::Config(config_command) => config(config_command, context),
+            Command::Login(login_command) => login(login_command, context),
             Command::Logout => logout(context),
+            Command::Profile(profile_command) => profile(profile_command, context),
+            Command::Status => status(context),
             Command::Todos(todos_command) => todos_command.execute(context),
+            Command::Tui => {
+                let access_token = access_token(context);
+                tui(&context.server_url, &access_token)
+            }
+            Command::Whoami { refresh } => whoami(*refresh, context),
         }
     }
 }
@@ -64,46 +138,69 @@ impl<T: CredStore> CommandExecutor<T> for Command {
 #[derive(Subcommand)]
 enum TodosCommand {
     View(TodosSelectOptions),
-    List,
+    List(TodoListCommand),
     Add(TodoAddCommand),
     Complete(TodosSelectOptions),
     Delete(TodosSelectOptions),
+    /// Write all todos as a JSON array
+    Export(TodoExportCommand),
+    /// Add todos from a JSON array written by `export`
+    Import(TodoImportCommand),
+    /// Show open todos that are overdue or due within a day
+    Reminders,
 }
 
-impl<T: CredStore> CommandExecutor<T> for TodosCommand {
-    fn execute(&self, context: &mut CommandContext<T>) {
-        let access_token = match get_token(context) {
-            Ok(token) => match token {
-                Some(token) => token,
-                None => {
-                    eprintln!("You must login first.");
-                    std::process::exit(1);
-                }
-            },
-            Err(e) => {
-                eprintln!("Couldn't get credentials: {}.  Try to login again.", e);
+/// The stored access token, exiting when there is none to use.
+fn access_token<T: CredStore>(context: &mut CommandContext<T>) -> String {
+    match get_token(context) {
+        Ok(token) => match token {
+            Some(token) => token,
+            None => {
+                eprintln!("You must login first.");
                 std::process::exit(1);
             }
-        };
+        },
+        Err(e) => {
+            eprintln!("Couldn't get credentials: {}.  Try to login again.", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+impl<T: CredStore> CommandExecutor<T> for TodosCommand {
+    fn execute(&self, context: &mut CommandContext<T>) {
+        let access_token = access_token(context);
         match self {
             TodosCommand::View(todos_options) => {
-                todos_view(todos_options, &context.config.todo_url, &access_token)
+                todos_view(todos_options, &context.server_url, &access_token)
+            }
+            TodosCommand::List(list_command) => {
+                todos_list(list_command, &context.server_url, &access_token)
             }
-            TodosCommand::List => todos_list(&context.config.todo_url, &access_token),
             TodosCommand::Add(todo_add_command) => {
-                todos_add(todo_add_command, &context.config.todo_url, &access_token)
+                todos_add(todo_add_command, &context.server_url, &access_token)
             }
             TodosCommand::Complete(todos_options) => {
-                todos_complete(todos_options, &context.config.todo_url, &access_token)
+                todos_complete(todos_options, &context.server_url, &access_token)
             }
             TodosCommand::Delete(todos_options) => {
-                todos_delete(todos_options, &context.config.todo_url, &access_token)
+                todos_delete(todos_options, &context.server_url, &access_token)
             }
+            TodosCommand::Export(export_command) => {
+                todos_export(export_command, &context.server_url, &access_token)
+            }
+            TodosCommand::Import(import_command) => {
+                todos_import(import_command, &context.server_url, &access_token)
+            }
+            TodosCommand::Reminders => todos_reminders(&context.server_url, &access_token),
         }
     }
 }
 
-pub fn invoke_command<T: CredStore>(context: &mut CommandContext<T>) {
-    let cli = Cli::parse();
+pub fn parse_cli() -> Cli {
+    Cli::parse()
+}
+
+pub fn invoke_command<T: CredStore>(cli: Cli, context: &mut CommandContext<T>) {
     cli.command.execute(context);
 }
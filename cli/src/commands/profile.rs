@@ -0,0 +1,42 @@
+use super::CommandContext;
+use crate::profile::{list_profiles, set_active_profile, validate_profile};
+use clap::Subcommand;
+use cred_store::CredStore;
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// List profiles with stored credentials
+    List,
+    /// Make a profile the default for later commands
+    Use { name: String },
+}
+
+pub fn profile<T: CredStore>(command: &ProfileCommand, context: &mut CommandContext<T>) {
+    match command {
+        ProfileCommand::List => match list_profiles() {
+            Ok(profiles) => {
+                for profile in profiles {
+                    let marker = if profile == context.profile { "*" } else { " " };
+                    println!("{} {}", marker, profile);
+                }
+            }
+            Err(e) => {
+                eprintln!("Couldn't list profiles: {}", e);
+                std::process::exit(1);
+            }
+        },
+        ProfileCommand::Use { name } => {
+            if let Err(e) = validate_profile(name) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+            match set_active_profile(name) {
+                Ok(()) => println!("Using profile {}.", name),
+                Err(e) => {
+                    eprintln!("Couldn't switch profile: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use super::CommandContext;
+use crate::auth::{is_token_expired, token_expiry};
+use cred_store::CredStore;
+
+pub fn status<T: CredStore>(context: &mut CommandContext<T>) {
+    let credentials = match context.cred_store.load() {
+        Ok(credentials) => credentials,
+        Err(_) => {
+            println!("Not logged in (profile {}).", context.profile);
+            return;
+        }
+    };
+    let access_token = match credentials.get("access_token") {
+        Some(access_token) => access_token,
+        None => {
+            println!("Not logged in (profile {}).", context.profile);
+            return;
+        }
+    };
+
+    println!("Profile: {}", context.profile);
+    let state = if is_token_expired(access_token) {
+        "expired"
+    } else {
+        "valid"
+    };
+    match token_expiry(access_token) {
+        Some(expiry) => println!("Access token: {} (expires {})", state, expiry.to_rfc2822()),
+        None => println!("Access token: {}", state),
+    }
+    let refresh = if credentials.get("refresh_token").is_some() {
+        "present"
+    } else {
+        "missing"
+    };
+    println!("Refresh token: {}", refresh);
+}
@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -5,4 +6,19 @@ pub struct Todo {
     pub id: String,
     pub task: String,
     pub completed: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_date: Option<NaiveDate>,
+    /// Sent back in `If-Match` when updating the todo.
+    #[serde(default)]
+    pub version: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewTodo {
+    pub task: String,
+    pub completed: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
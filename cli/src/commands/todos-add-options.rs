@@ -4,4 +4,7 @@ use clap::Parser;
 pub struct TodoAddCommand {
     #[arg(long = "task-name")]
     pub todo_name: String,
+
+    #[arg(long = "tag")]
+    pub tags: Vec<String>,
 }
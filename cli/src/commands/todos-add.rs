@@ -1,17 +1,33 @@
+use super::NewTodo;
 use crate::commands::todos_add_options::TodoAddCommand;
+use crate::http::{check_status, Retry, SendWithRetry};
 use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NewTodo {
-    pub task: String,
-    pub completed: bool,
+/// Mirrors the server's default limit so obviously bad input fails fast.
+const MAX_TASK_LENGTH: usize = 500;
+
+pub fn validate_task(task: &str) -> Result<(), String> {
+    if task.trim().is_empty() {
+        return Err("task must not be empty".to_string());
+    }
+    if task.chars().count() > MAX_TASK_LENGTH {
+        return Err(format!(
+            "task must be at most {} characters",
+            MAX_TASK_LENGTH
+        ));
+    }
+    Ok(())
 }
 
 pub fn todos_add(options: &TodoAddCommand, url: &str, access_token: &str) {
+    if let Err(e) = validate_task(&options.todo_name) {
+        eprintln!("Error: {}", e);
+        return;
+    }
     let new_todo = NewTodo {
         task: options.todo_name.clone(),
         completed: false,
+        tags: options.tags.clone(),
     };
     let client = Client::new();
     let todo_endpoint = format!("{}/todos", url);
@@ -20,12 +36,13 @@ pub fn todos_add(options: &TodoAddCommand, url: &str, access_token: &str) {
         .post(todo_endpoint)
         .header("Authorization", format! {"Bearer {}", access_token})
         .json(&new_todo)
-        .send();
+        .send_with_retry(Retry::UnlessSent);
 
     match resp {
-        Ok(_) => {
-            println!("Todo added");
-        }
+        Ok(response) => match check_status(response) {
+            Ok(_) => println!("Todo added"),
+            Err(e) => eprintln!("Error: {}", e),
+        },
         Err(e) => eprintln!("Error: {}", e),
     }
 }
@@ -1,28 +1,22 @@
 use super::Todo;
 use crate::commands::TodosSelectOptions;
+use crate::http::{read_json, Retry, SendWithRetry};
 use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateTodo {
-    pub completed: bool,
-}
 
 pub fn todos_complete(options: &TodosSelectOptions, url: &str, access_token: &str) {
     let task_id = options.task_id.clone();
     let client = Client::new();
-    let todo_endpoint = format!("{}/todos/{}", url, task_id);
-    let update_todo = UpdateTodo { completed: true };
 
+    // completing an already completed todo is a no-op on the server, so the
+    // request is safe to retry
     let resp = client
-        .patch(todo_endpoint)
+        .post(format!("{}/todos/{}/complete", url, task_id))
         .header("Authorization", format! {"Bearer {}", access_token})
-        .json(&update_todo)
-        .send();
+        .send_with_retry(Retry::Idempotent);
 
     match resp {
         Ok(response) => {
-            let _ = match response.json::<Todo>() {
+            let _ = match read_json::<Todo>(response) {
                 Ok(resp) => resp,
                 Err(e) => {
                     eprintln!("Error: {}", e);
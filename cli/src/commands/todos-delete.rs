@@ -1,4 +1,5 @@
 use crate::commands::TodosSelectOptions;
+use crate::http::{check_status, Retry, SendWithRetry};
 use reqwest::blocking::Client;
 
 pub fn todos_delete(options: &TodosSelectOptions, url: &str, access_token: &str) {
@@ -9,12 +10,13 @@ pub fn todos_delete(options: &TodosSelectOptions, url: &str, access_token: &str)
     let resp = client
         .delete(todo_endpoint)
         .header("Authorization", format! {"Bearer {}", access_token})
-        .send();
+        .send_with_retry(Retry::Idempotent);
 
     match resp {
-        Ok(_) => {
-            println!("Todo deleted.");
-        }
+        Ok(response) => match check_status(response) {
+            Ok(_) => println!("Todo deleted."),
+            Err(e) => eprintln!("Error: {}", e),
+        },
         Err(e) => eprintln!("Error: {}", e),
     }
 }
@@ -0,0 +1,8 @@
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+pub struct TodoExportCommand {
+    /// Write to this file instead of stdout
+    #[arg(long = "file")]
+    pub file: Option<String>,
+}
@@ -0,0 +1,43 @@
+use crate::commands::todos_export_options::TodoExportCommand;
+use crate::http::{read_json, Retry, SendWithRetry};
+use reqwest::blocking::Client;
+use serde_json::Value;
+
+pub fn todos_export(options: &TodoExportCommand, url: &str, access_token: &str) {
+    let client = Client::new();
+    // archived todos are hidden from the default listing
+    let todo_endpoint = format!("{}/todos?include_archived=true", url);
+
+    let resp = client
+        .get(todo_endpoint)
+        .header("Authorization", format! {"Bearer {}", access_token})
+        .send_with_retry(Retry::Idempotent);
+
+    // keep the server's representation as-is so no fields are lost in the backup
+    let todos = match resp.map_err(|e| e.to_string()).and_then(read_json::<Value>) {
+        Ok(Value::Array(todos)) => todos,
+        Ok(_) => {
+            eprintln!("Error: unexpected response from server");
+            return;
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let json = match serde_json::to_string_pretty(&todos) {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+
+    match &options.file {
+        Some(file) => match std::fs::write(file, json) {
+            Ok(_) => eprintln!("Exported {} todos to {}", todos.len(), file),
+            Err(e) => eprintln!("Error: {}", e),
+        },
+        None => println!("{}", json),
+    }
+}
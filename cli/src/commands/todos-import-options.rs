@@ -0,0 +1,11 @@
+use clap::Parser;
+
+#[derive(Parser, Debug)]
+pub struct TodoImportCommand {
+    /// JSON file containing an array of todos, as written by `todos export`
+    pub file: String,
+
+    /// Only report what would be imported
+    #[arg(long = "dry-run")]
+    pub dry_run: bool,
+}
@@ -0,0 +1,146 @@
+use super::{NewTodo, Todo};
+use crate::commands::todos_import_options::TodoImportCommand;
+use crate::http::{check_status, read_json, Retry, SendWithRetry};
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// An exported todo; creating a todo can't archive it, so that takes a
+/// second request.
+#[derive(Debug, Deserialize)]
+struct ImportedTodo {
+    #[serde(flatten)]
+    todo: NewTodo,
+    #[serde(default)]
+    archived: bool,
+}
+
+/// Parses an exported array, skipping entries that aren't valid todos.
+fn parse_todos(contents: &str) -> Result<Vec<ImportedTodo>, String> {
+    let entries = match serde_json::from_str::<Value>(contents) {
+        Ok(Value::Array(entries)) => entries,
+        Ok(_) => return Err("expected a JSON array of todos".to_string()),
+        Err(e) => return Err(e.to_string()),
+    };
+    let todos = entries
+        .into_iter()
+        .enumerate()
+        .filter_map(
+            |(i, entry)| match serde_json::from_value::<ImportedTodo>(entry) {
+                Ok(todo) => Some(todo),
+                Err(e) => {
+                    eprintln!("Warning: skipping entry {}: {}", i, e);
+                    None
+                }
+            },
+        )
+        .collect();
+    Ok(todos)
+}
+
+/// Adds `todo` through the batch endpoint, which returns the new todo's id,
+/// then archives it.
+fn import_archived(
+    client: &Client,
+    url: &str,
+    access_token: &str,
+    todo: &NewTodo,
+) -> Result<(), String> {
+    let created = client
+        .post(format!("{}/todos/batch", url))
+        .header("Authorization", format! {"Bearer {}", access_token})
+        .json(&[todo])
+        .send_with_retry(Retry::UnlessSent)
+        .map_err(|e| e.to_string())
+        .and_then(read_json::<Vec<Todo>>)?;
+    let id = match created.first() {
+        Some(created) => &created.id,
+        None => return Err("unexpected response from server".to_string()),
+    };
+    client
+        .post(format!("{}/todos/{}/archive", url, id))
+        .header("Authorization", format! {"Bearer {}", access_token})
+        .send_with_retry(Retry::Idempotent)
+        .map_err(|e| e.to_string())
+        .and_then(check_status)
+        .map(|_| ())
+        .map_err(|e| format!("imported but not archived: {}", e))
+}
+
+pub fn todos_import(options: &TodoImportCommand, url: &str, access_token: &str) {
+    let contents = match std::fs::read_to_string(&options.file) {
+        Ok(contents) => contents,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let todos = match parse_todos(&contents) {
+        Ok(todos) => todos,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+
+    if options.dry_run {
+        println!("Would import {} todos.", todos.len());
+        return;
+    }
+
+    let client = Client::new();
+    let todo_endpoint = format!("{}/todos", url);
+    let mut imported = 0;
+    let mut failed = 0;
+    for ImportedTodo { todo, archived } in &todos {
+        let result = if *archived {
+            import_archived(&client, url, access_token, todo)
+        } else {
+            client
+                .post(&todo_endpoint)
+                .header("Authorization", format! {"Bearer {}", access_token})
+                .json(todo)
+                .send_with_retry(Retry::UnlessSent)
+                .map_err(|e| e.to_string())
+                .and_then(check_status)
+                .map(|_| ())
+        };
+        match result {
+            Ok(()) => imported += 1,
+            Err(e) => {
+                eprintln!("Failed to import '{}': {}", todo.task, e);
+                failed += 1;
+            }
+        }
+    }
+    println!("Imported {} todos, {} failed.", imported, failed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_todos_skips_malformed() {
+        let contents = r#"[
+            {"id": "1", "task": "first", "completed": false, "tags": ["work"]},
+            {"id": "2", "completed": true},
+            "not a todo",
+            {"task": "third", "completed": true, "archived": true}
+        ]"#;
+        let todos = parse_todos(contents).unwrap();
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[0].todo.task, "first");
+        assert_eq!(todos[0].todo.tags, vec!["work".to_string()]);
+        assert!(!todos[0].archived);
+        assert_eq!(todos[1].todo.task, "third");
+        assert!(todos[1].todo.tags.is_empty());
+        assert!(todos[1].archived);
+    }
+
+    #[test]
+    fn test_parse_todos_requires_array() {
+        assert!(parse_todos(r#"{"task": "first"}"#).is_err());
+        assert!(parse_todos("not json").is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use clap::{Parser, ValueEnum};
+
+#[derive(Parser, Debug)]
+pub struct TodoListCommand {
+    /// Print plain text even when stdout is a terminal
+    #[arg(long = "no-color")]
+    pub no_color: bool,
+    /// Which todos to show
+    #[arg(long, value_enum, default_value_t = ListFilter::All)]
+    pub filter: ListFilter,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ListFilter {
+    /// Todos that are not completed yet
+    Open,
+    /// Completed todos
+    Done,
+    /// Every todo
+    All,
+}
+
+impl ListFilter {
+    pub fn matches(&self, completed: bool) -> bool {
+        match self {
+            ListFilter::Open => !completed,
+            ListFilter::Done => completed,
+            ListFilter::All => true,
+        }
+    }
+}
@@ -1,18 +1,74 @@
 use super::Todo;
-use reqwest::blocking::Client;
+use crate::commands::TodoListCommand;
+use crate::http::{read_json, Retry, SendWithRetry};
+use owo_colors::OwoColorize;
+use reqwest::blocking::{Client, Response};
+use std::io::IsTerminal;
 
-pub fn todos_list(url: &str, access_token: &str) {
+/// Colors are used only on a terminal, and never with `--no-color` or `NO_COLOR`.
+fn use_color(options: &TodoListCommand) -> bool {
+    let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    !options.no_color && !no_color_env && std::io::stdout().is_terminal()
+}
+
+fn format_todo(todo: &Todo, color: bool) -> String {
+    let tags = if todo.tags.is_empty() {
+        String::new()
+    } else {
+        format!(" [{}]", todo.tags.join(", "))
+    };
+    if !color {
+        return format!("{}: {} - {}{}", todo.id, todo.task, todo.completed, tags);
+    }
+    if todo.completed {
+        format!(
+            "✅ {} {}{}",
+            todo.id.dimmed(),
+            todo.task.dimmed().strikethrough(),
+            tags.dimmed()
+        )
+    } else {
+        format!(
+            "⬜ {} {}{}",
+            todo.id.dimmed(),
+            todo.task.bold(),
+            tags.cyan()
+        )
+    }
+}
+
+/// Header line such as `3 of 10 completed`. `total` is the server's
+/// `X-Total-Count` when it sent one, so the count covers todos beyond the
+/// returned ones.
+fn summary(todos: &[Todo], total: Option<u64>) -> String {
+    let completed = todos.iter().filter(|todo| todo.completed).count();
+    let total = total.unwrap_or(todos.len() as u64);
+    format!("{} of {} completed", completed, total)
+}
+
+fn total_count(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get("X-Total-Count")?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+pub fn todos_list(options: &TodoListCommand, url: &str, access_token: &str) {
     let client = Client::new();
     let todo_endpoint = format!("{}/todos", url);
 
     let resp = client
         .get(todo_endpoint)
         .header("Authorization", format! {"Bearer {}", access_token})
-        .send();
+        .send_with_retry(Retry::Idempotent);
 
     match resp {
         Ok(response) => {
-            let todos = match response.json::<Vec<Todo>>() {
+            let total = total_count(&response);
+            let todos = match read_json::<Vec<Todo>>(response) {
                 Ok(resp) => resp,
                 Err(e) => {
                     eprintln!("Error: {}", e);
@@ -23,11 +79,68 @@ pub fn todos_list(url: &str, access_token: &str) {
                 println!("No todos found.");
                 return;
             }
-            println!("Todos:");
-            todos.iter().for_each(|todo| {
-                println!("{}: {} - {}", todo.id, todo.task, todo.completed);
-            });
+            let color = use_color(options);
+            println!("Todos ({}):", summary(&todos, total));
+            let shown = todos
+                .iter()
+                .filter(|todo| options.filter.matches(todo.completed))
+                .collect::<Vec<&Todo>>();
+            if shown.is_empty() {
+                println!("No todos match the filter.");
+            }
+            for todo in shown {
+                println!("{}", format_todo(todo, color));
+            }
         }
         Err(e) => eprintln!("Error: {}", e),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::todos_list_options::ListFilter;
+
+    fn todo(completed: bool, tags: &[&str]) -> Todo {
+        Todo {
+            id: "1".to_string(),
+            task: "Buy milk".to_string(),
+            completed,
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            due_date: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_format_todo_plain() {
+        assert_eq!(format_todo(&todo(false, &[]), false), "1: Buy milk - false");
+        assert_eq!(
+            format_todo(&todo(true, &["home", "errand"]), false),
+            "1: Buy milk - true [home, errand]"
+        );
+    }
+
+    #[test]
+    fn test_summary() {
+        let todos = vec![todo(true, &[]), todo(false, &[]), todo(true, &[])];
+        assert_eq!(summary(&todos, None), "2 of 3 completed");
+        assert_eq!(summary(&todos, Some(10)), "2 of 10 completed");
+        assert_eq!(summary(&[], None), "0 of 0 completed");
+    }
+
+    #[test]
+    fn test_filter() {
+        assert!(ListFilter::Open.matches(false) && !ListFilter::Open.matches(true));
+        assert!(ListFilter::Done.matches(true) && !ListFilter::Done.matches(false));
+        assert!(ListFilter::All.matches(true) && ListFilter::All.matches(false));
+    }
+
+    #[test]
+    fn test_format_todo_color() {
+        let pending = format_todo(&todo(false, &[]), true);
+        assert!(pending.starts_with("⬜ "));
+        assert!(pending.contains("\u{1b}["));
+        assert!(format_todo(&todo(true, &[]), true).starts_with("✅ "));
+    }
+}
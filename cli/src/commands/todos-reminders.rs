@@ -0,0 +1,108 @@
+use super::Todo;
+use crate::http::{read_json, Retry, SendWithRetry};
+use chrono::{DateTime, Duration, Utc};
+use reqwest::blocking::Client;
+
+/// Open todos with due dates, split by how urgent they are.
+#[derive(Debug, Default)]
+struct Reminders<'a> {
+    overdue: Vec<&'a Todo>,
+    due_soon: Vec<&'a Todo>,
+}
+
+/// Due dates carry no time, so a todo is overdue once its day has passed and
+/// due soon if its day falls within the next 24 hours.
+fn group_reminders(todos: &[Todo], now: DateTime<Utc>) -> Reminders<'_> {
+    let today = now.date_naive();
+    let soon = (now + Duration::hours(24)).date_naive();
+    let mut reminders = Reminders::default();
+    for todo in todos.iter().filter(|todo| !todo.completed) {
+        match todo.due_date {
+            Some(due) if due < today => reminders.overdue.push(todo),
+            Some(due) if due <= soon => reminders.due_soon.push(todo),
+            _ => {}
+        }
+    }
+    reminders.overdue.sort_by_key(|todo| todo.due_date);
+    reminders.due_soon.sort_by_key(|todo| todo.due_date);
+    reminders
+}
+
+fn print_group(title: &str, todos: &[&Todo]) {
+    if todos.is_empty() {
+        return;
+    }
+    println!("{}:", title);
+    for todo in todos {
+        if let Some(due) = todo.due_date {
+            println!("  {}: {} (due {})", todo.id, todo.task, due);
+        }
+    }
+}
+
+pub fn todos_reminders(url: &str, access_token: &str) {
+    let client = Client::new();
+    let todo_endpoint = format!("{}/todos", url);
+
+    let resp = client
+        .get(todo_endpoint)
+        .header("Authorization", format! {"Bearer {}", access_token})
+        .send_with_retry(Retry::Idempotent);
+
+    match resp {
+        Ok(response) => {
+            let todos = match read_json::<Vec<Todo>>(response) {
+                Ok(resp) => resp,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return;
+                }
+            };
+            let reminders = group_reminders(&todos, Utc::now());
+            if reminders.overdue.is_empty() && reminders.due_soon.is_empty() {
+                println!("No upcoming due dates.");
+                return;
+            }
+            print_group("OVERDUE", &reminders.overdue);
+            print_group("Due soon", &reminders.due_soon);
+        }
+        Err(e) => eprintln!("Error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    fn todo(id: &str, completed: bool, due_date: Option<NaiveDate>) -> Todo {
+        Todo {
+            id: id.to_string(),
+            task: format!("task {}", id),
+            completed,
+            tags: vec![],
+            due_date,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_group_reminders() {
+        let now = Utc.with_ymd_and_hms(2023, 10, 12, 18, 0, 0).unwrap();
+        let date = |d| NaiveDate::from_ymd_opt(2023, 10, d);
+        let todos = vec![
+            todo("1", false, date(10)),
+            todo("2", false, date(12)),
+            todo("3", false, date(13)),
+            todo("4", false, date(14)),
+            todo("5", true, date(1)),
+            todo("6", false, None),
+        ];
+
+        let reminders = group_reminders(&todos, now);
+
+        let ids = |todos: &[&Todo]| todos.iter().map(|t| t.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&reminders.overdue), vec!["1"]);
+        assert_eq!(ids(&reminders.due_soon), vec!["2", "3"]);
+    }
+}
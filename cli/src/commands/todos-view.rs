@@ -1,5 +1,6 @@
 use super::Todo;
 use crate::commands::TodosSelectOptions;
+use crate::http::{read_json, Retry, SendWithRetry};
 use reqwest::blocking::Client;
 
 pub fn todos_view(options: &TodosSelectOptions, url: &str, access_token: &str) {
@@ -10,11 +11,11 @@ pub fn todos_view(options: &TodosSelectOptions, url: &str, access_token: &str) {
     let resp = client
         .get(todo_endpoint)
         .header("Authorization", format! {"Bearer {}", access_token})
-        .send();
+        .send_with_retry(Retry::Idempotent);
 
     match resp {
         Ok(response) => {
-            let todo = match response.json::<Todo>() {
+            let todo = match read_json::<Todo>(response) {
                 Ok(resp) => resp,
                 Err(e) => {
                     eprintln!("Error: {}", e);
@@ -0,0 +1,313 @@
+use super::todos_add::validate_task;
+use super::Todo;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The server calls the TUI makes; errors are messages for the status bar.
+pub trait TodoApi {
+    fn list(&self) -> Result<Vec<Todo>, String>;
+    fn add(&self, task: &str) -> Result<(), String>;
+    fn set_completed(&self, id: &str, completed: bool) -> Result<(), String>;
+    /// Changes the task, failing if `todo` changed on the server since it
+    /// was listed.
+    fn rename(&self, todo: &Todo, task: &str) -> Result<(), String>;
+    fn delete(&self, id: &str) -> Result<(), String>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mode {
+    Normal,
+    /// Typing the task of a new todo.
+    Adding(String),
+    /// Typing a new task for the selected todo.
+    Editing(String),
+    /// Waiting for `y` to delete the selected todo.
+    ConfirmDelete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Info(String),
+    Error(String),
+}
+
+pub struct App {
+    pub todos: Vec<Todo>,
+    pub selected: usize,
+    pub mode: Mode,
+    pub status: Option<Status>,
+    pub quit: bool,
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            todos: vec![],
+            selected: 0,
+            mode: Mode::Normal,
+            status: None,
+            quit: false,
+        }
+    }
+
+    pub fn selected_todo(&self) -> Option<&Todo> {
+        self.todos.get(self.selected)
+    }
+
+    /// Reloads the todos, keeping the selection on the same row where possible.
+    pub fn refresh(&mut self, api: &impl TodoApi) {
+        match api.list() {
+            Ok(todos) => {
+                self.todos = todos;
+                self.selected = self.selected.min(self.todos.len().saturating_sub(1));
+            }
+            Err(e) => self.status = Some(Status::Error(e)),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent, api: &impl TodoApi) {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return;
+        }
+        match &self.mode {
+            Mode::Normal => self.handle_normal_key(key.code, api),
+            Mode::Adding(_) | Mode::Editing(_) => self.handle_input_key(key.code, api),
+            Mode::ConfirmDelete => self.handle_confirm_key(key.code, api),
+        }
+    }
+
+    fn handle_normal_key(&mut self, code: KeyCode, api: &impl TodoApi) {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') if self.selected + 1 < self.todos.len() => {
+                self.selected += 1
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = self.todos.len().saturating_sub(1),
+            KeyCode::Char('r') => {
+                self.status = None;
+                self.refresh(api);
+            }
+            KeyCode::Char('a') => self.mode = Mode::Adding(String::new()),
+            KeyCode::Char('e') => {
+                if let Some(todo) = self.selected_todo() {
+                    self.mode = Mode::Editing(todo.task.clone());
+                }
+            }
+            KeyCode::Char(' ') | KeyCode::Char('c') => {
+                if let Some(todo) = self.selected_todo() {
+                    let completed = !todo.completed;
+                    let result = api.set_completed(&todo.id, completed);
+                    let done = if completed {
+                        "Todo completed."
+                    } else {
+                        "Todo reopened."
+                    };
+                    self.finish(result, done, api);
+                }
+            }
+            KeyCode::Char('d') if self.selected_todo().is_some() => self.mode = Mode::ConfirmDelete,
+            _ => {}
+        }
+    }
+
+    fn handle_input_key(&mut self, code: KeyCode, api: &impl TodoApi) {
+        let input = match &mut self.mode {
+            Mode::Adding(input) | Mode::Editing(input) => input,
+            _ => return,
+        };
+        match code {
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Esc => self.mode = Mode::Normal,
+            KeyCode::Enter => {
+                let task = input.trim().to_string();
+                if let Err(e) = validate_task(&task) {
+                    self.status = Some(Status::Error(e));
+                    return;
+                }
+                let mode = std::mem::replace(&mut self.mode, Mode::Normal);
+                match mode {
+                    Mode::Adding(_) => {
+                        let result = api.add(&task);
+                        self.finish(result, "Todo added.", api);
+                    }
+                    Mode::Editing(_) => {
+                        if let Some(todo) = self.selected_todo() {
+                            let result = api.rename(todo, &task);
+                            self.finish(result, "Todo updated.", api);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_confirm_key(&mut self, code: KeyCode, api: &impl TodoApi) {
+        self.mode = Mode::Normal;
+        match (code, self.selected_todo()) {
+            (KeyCode::Char('y'), Some(todo)) => {
+                let result = api.delete(&todo.id);
+                self.finish(result, "Todo deleted.", api);
+            }
+            _ => self.status = Some(Status::Info("Delete cancelled.".to_string())),
+        }
+    }
+
+    /// Reports the outcome of a change and reloads, so the list also shows
+    /// what other clients changed, or why this change failed.
+    fn finish(&mut self, result: Result<(), String>, done: &str, api: &impl TodoApi) {
+        self.status = Some(match result {
+            Ok(()) => Status::Info(done.to_string()),
+            Err(e) => Status::Error(e),
+        });
+        self.refresh(api);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Keeps todos in memory; `fail` makes every change return an error.
+    #[derive(Default)]
+    struct FakeApi {
+        todos: RefCell<Vec<Todo>>,
+        fail: bool,
+    }
+
+    impl FakeApi {
+        fn change(&self, change: impl FnOnce(&mut Vec<Todo>)) -> Result<(), String> {
+            if self.fail {
+                return Err("500 Internal Server Error".to_string());
+            }
+            change(&mut self.todos.borrow_mut());
+            Ok(())
+        }
+    }
+
+    impl TodoApi for FakeApi {
+        fn list(&self) -> Result<Vec<Todo>, String> {
+            Ok(self
+                .todos
+                .borrow()
+                .iter()
+                .map(|todo| todo_with(&todo.id, &todo.task, todo.completed))
+                .collect())
+        }
+
+        fn add(&self, task: &str) -> Result<(), String> {
+            self.change(|todos| {
+                let id = (todos.len() + 1).to_string();
+                todos.push(todo_with(&id, task, false));
+            })
+        }
+
+        fn set_completed(&self, id: &str, completed: bool) -> Result<(), String> {
+            self.change(|todos| {
+                todos
+                    .iter_mut()
+                    .filter(|todo| todo.id == id)
+                    .for_each(|todo| todo.completed = completed)
+            })
+        }
+
+        fn rename(&self, todo: &Todo, task: &str) -> Result<(), String> {
+            self.change(|todos| {
+                todos
+                    .iter_mut()
+                    .filter(|t| t.id == todo.id)
+                    .for_each(|t| t.task = task.to_string())
+            })
+        }
+
+        fn delete(&self, id: &str) -> Result<(), String> {
+            self.change(|todos| todos.retain(|todo| todo.id != id))
+        }
+    }
+
+    fn todo_with(id: &str, task: &str, completed: bool) -> Todo {
+        Todo {
+            id: id.to_string(),
+            task: task.to_string(),
+            completed,
+            tags: vec![],
+            due_date: None,
+            version: 0,
+        }
+    }
+
+    fn press(app: &mut App, api: &FakeApi, keys: &str) {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE), api);
+        }
+    }
+
+    #[test]
+    fn test_add_complete_edit_delete() {
+        let api = FakeApi::default();
+        let mut app = App::new();
+        app.refresh(&api);
+
+        press(&mut app, &api, "aBuy milk\naWalk dog\n");
+        assert_eq!(app.todos.len(), 2);
+        assert_eq!(app.status, Some(Status::Info("Todo added.".to_string())));
+
+        press(&mut app, &api, "j ");
+        assert!(app.todos[1].completed);
+        press(&mut app, &api, "c");
+        assert!(!app.todos[1].completed);
+
+        press(&mut app, &api, "ke");
+        assert_eq!(app.mode, Mode::Editing("Buy milk".to_string()));
+        press(&mut app, &api, "s\n");
+        assert_eq!(app.todos[0].task, "Buy milks");
+
+        press(&mut app, &api, "dn");
+        assert_eq!(app.todos.len(), 2);
+        press(&mut app, &api, "Gdy");
+        assert_eq!(app.todos.len(), 1);
+        assert_eq!(app.selected, 0);
+
+        press(&mut app, &api, "q");
+        assert!(app.quit);
+    }
+
+    #[test]
+    fn test_errors_shown_in_status() {
+        let api = FakeApi {
+            todos: RefCell::new(vec![todo_with("1", "Buy milk", false)]),
+            fail: true,
+        };
+        let mut app = App::new();
+        app.refresh(&api);
+
+        press(&mut app, &api, " ");
+        assert_eq!(
+            app.status,
+            Some(Status::Error("500 Internal Server Error".to_string()))
+        );
+        assert!(!app.todos[0].completed);
+
+        press(&mut app, &api, "a   \n");
+        assert_eq!(
+            app.status,
+            Some(Status::Error("task must not be empty".to_string()))
+        );
+        // the input stays open to fix the task
+        assert_eq!(app.mode, Mode::Adding("   ".to_string()));
+        press(&mut app, &api, "\x1b");
+        assert_eq!(app.mode, Mode::Normal);
+    }
+}
@@ -0,0 +1,261 @@
+use super::tui_app::{App, Mode, Status, TodoApi};
+use super::{NewTodo, Todo};
+use crate::http::{check_status, read_json, Retry, SendWithRetry};
+use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{Frame, Terminal};
+use reqwest::blocking::Client;
+use serde_json::json;
+use std::io::{self, Stdout};
+
+const HELP: &str = "a add  e edit  space complete  d delete  r refresh  q quit";
+
+/// Talks to the same endpoints as the `todos` commands.
+struct HttpApi<'a> {
+    client: Client,
+    url: &'a str,
+    access_token: &'a str,
+}
+
+impl HttpApi<'_> {
+    fn bearer(&self) -> String {
+        format!("Bearer {}", self.access_token)
+    }
+}
+
+impl TodoApi for HttpApi<'_> {
+    fn list(&self) -> Result<Vec<Todo>, String> {
+        self.client
+            .get(format!("{}/todos", self.url))
+            .header("Authorization", self.bearer())
+            .send_with_retry(Retry::Idempotent)
+            .map_err(|e| e.to_string())
+            .and_then(read_json)
+    }
+
+    fn add(&self, task: &str) -> Result<(), String> {
+        let new_todo = NewTodo {
+            task: task.to_string(),
+            completed: false,
+            tags: vec![],
+        };
+        self.client
+            .post(format!("{}/todos", self.url))
+            .header("Authorization", self.bearer())
+            .json(&new_todo)
+            .send_with_retry(Retry::UnlessSent)
+            .map_err(|e| e.to_string())
+            .and_then(check_status)
+            .map(|_| ())
+    }
+
+    fn set_completed(&self, id: &str, completed: bool) -> Result<(), String> {
+        let action = if completed { "complete" } else { "uncomplete" };
+        self.client
+            .post(format!("{}/todos/{}/{}", self.url, id, action))
+            .header("Authorization", self.bearer())
+            .send_with_retry(Retry::Idempotent)
+            .map_err(|e| e.to_string())
+            .and_then(check_status)
+            .map(|_| ())
+    }
+
+    fn rename(&self, todo: &Todo, task: &str) -> Result<(), String> {
+        self.client
+            .patch(format!("{}/todos/{}", self.url, todo.id))
+            .header("Authorization", self.bearer())
+            .header("If-Match", format!("\"{}\"", todo.version))
+            .json(&json!({ "task": task }))
+            .send_with_retry(Retry::Idempotent)
+            .map_err(|e| e.to_string())
+            .and_then(check_status)
+            .map(|_| ())
+    }
+
+    fn delete(&self, id: &str) -> Result<(), String> {
+        self.client
+            .delete(format!("{}/todos/{}", self.url, id))
+            .header("Authorization", self.bearer())
+            .send_with_retry(Retry::Idempotent)
+            .map_err(|e| e.to_string())
+            .and_then(check_status)
+            .map(|_| ())
+    }
+}
+
+/// Puts the terminal back on drop, so an error or panic doesn't leave it in
+/// raw mode.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+fn draw(frame: &mut Frame, app: &App) {
+    let input_height = match app.mode {
+        Mode::Adding(_) | Mode::Editing(_) => 3,
+        _ => 1,
+    };
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),
+            Constraint::Length(input_height),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    let completed = app.todos.iter().filter(|todo| todo.completed).count();
+    let title = format!(" Todos ({} of {} completed) ", completed, app.todos.len());
+    let items = app
+        .todos
+        .iter()
+        .map(|todo| {
+            let mark = if todo.completed { "[x]" } else { "[ ]" };
+            let item = ListItem::new(format!("{} {}", mark, todo.task));
+            if todo.completed {
+                item.style(Style::default().add_modifier(Modifier::DIM))
+            } else {
+                item
+            }
+        })
+        .collect::<Vec<ListItem>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select((!app.todos.is_empty()).then_some(app.selected));
+    frame.render_stateful_widget(list, areas[0], &mut state);
+
+    let prompt = match &app.mode {
+        Mode::Adding(input) => Paragraph::new(input.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" New todo (Enter to save, Esc to cancel) "),
+        ),
+        Mode::Editing(input) => Paragraph::new(input.as_str()).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Edit todo (Enter to save, Esc to cancel) "),
+        ),
+        Mode::ConfirmDelete => Paragraph::new(format!(
+            "Delete '{}'? (y/n)",
+            app.selected_todo()
+                .map(|todo| todo.task.as_str())
+                .unwrap_or("")
+        )),
+        Mode::Normal => Paragraph::new(HELP).style(Style::default().fg(Color::DarkGray)),
+    };
+    frame.render_widget(prompt, areas[1]);
+
+    let status = match &app.status {
+        Some(Status::Info(message)) => Line::from(message.as_str()),
+        Some(Status::Error(message)) => Line::styled(
+            format!("Error: {}", message),
+            Style::default().fg(Color::Red),
+        ),
+        None => Line::default(),
+    };
+    frame.render_widget(Paragraph::new(status), areas[2]);
+}
+
+fn run(app: &mut App, api: &impl TodoApi) -> io::Result<()> {
+    let _guard = TerminalGuard::enter()?;
+    let mut terminal: Terminal<CrosstermBackend<Stdout>> =
+        Terminal::new(CrosstermBackend::new(io::stdout()))?;
+    while !app.quit {
+        terminal.draw(|frame| draw(frame, app))?;
+        if let Event::Key(key) = event::read()? {
+            // Windows also reports key releases
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key, api);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn tui(url: &str, access_token: &str) {
+    let api = HttpApi {
+        client: Client::new(),
+        url,
+        access_token,
+    };
+    let mut app = App::new();
+    app.refresh(&api);
+    if let Err(e) = run(&mut app, &api) {
+        eprintln!("Error: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+
+    fn todo(id: &str, task: &str, completed: bool) -> Todo {
+        Todo {
+            id: id.to_string(),
+            task: task.to_string(),
+            completed,
+            tags: vec![],
+            due_date: None,
+            version: 1,
+        }
+    }
+
+    fn render(app: &App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(60, 8)).unwrap();
+        terminal.draw(|frame| draw(frame, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_draw() {
+        let mut app = App::new();
+        app.todos = vec![todo("1", "Buy milk", true), todo("2", "Walk dog", false)];
+        app.status = Some(Status::Error("404 Not Found: Todo not found".to_string()));
+        let screen = render(&app);
+        assert!(screen.contains("Todos (1 of 2 completed)"), "{}", screen);
+        assert!(screen.contains("[x] Buy milk"), "{}", screen);
+        assert!(screen.contains("[ ] Walk dog"), "{}", screen);
+        assert!(screen.contains("a add"), "{}", screen);
+        assert!(
+            screen.contains("Error: 404 Not Found: Todo not found"),
+            "{}",
+            screen
+        );
+
+        app.mode = Mode::ConfirmDelete;
+        assert!(render(&app).contains("Delete 'Buy milk'? (y/n)"));
+        app.mode = Mode::Adding("Call mom".to_string());
+        let screen = render(&app);
+        assert!(screen.contains("New todo"), "{}", screen);
+        assert!(screen.contains("Call mom"), "{}", screen);
+    }
+}
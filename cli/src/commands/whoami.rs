@@ -0,0 +1,52 @@
+use super::CommandContext;
+use crate::auth::{cache_userinfo, cached_userinfo, get_token, get_userinfo};
+use cred_store::CredStore;
+
+/// Prints the logged in user, from the cache unless `refresh` is set or the
+/// token now belongs to someone else.
+pub fn whoami<T: CredStore>(refresh: bool, context: &mut CommandContext<T>) {
+    let access_token = match get_token(context) {
+        Ok(Some(token)) => token,
+        Ok(None) => {
+            eprintln!("You must login first.");
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Couldn't get credentials: {}.  Try to login again.", e);
+            std::process::exit(1);
+        }
+    };
+    // get_token may have saved refreshed tokens behind the loaded copy
+    let mut credentials = match context.cred_store.load() {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            eprintln!("Couldn't load credentials: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let cached = match refresh {
+        true => None,
+        false => cached_userinfo(&credentials, &access_token),
+    };
+    let userinfo = match cached {
+        Some(userinfo) => userinfo,
+        None => match get_userinfo(&context.server_url, &access_token) {
+            Ok(userinfo) => {
+                cache_userinfo(&mut credentials, &userinfo, &access_token);
+                if credentials.save().is_err() {
+                    eprintln!("Couldn't cache user info.");
+                }
+                userinfo
+            }
+            Err(e) => {
+                eprintln!("Couldn't get user info: {}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+
+    println!("{} ({})", userinfo.name, userinfo.email);
+    println!("User id: {}", userinfo.id);
+    println!("Tenant id: {}", userinfo.tenant_id);
+}
@@ -1,27 +1,205 @@
 use dotenv::dotenv;
-use std::env;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+use std::{env, fs};
 
+/// Server used when neither `TODO_URL` nor `--server-url` is given.
+pub const DEFAULT_TODO_URL: &str = "http://localhost:3030";
+/// Scopes requested at login unless `SCOPES` or `--scope` says otherwise.
+pub const DEFAULT_SCOPES: &str = "openid profile email offline_access";
+
+/// Keys accepted by `todo config set` and the variables they stand for.
+pub const CONFIG_KEYS: [(&str, &str); 5] = [
+    ("domain", "DOMAIN"),
+    ("client_id", "CLIENT_ID"),
+    ("audience", "AUDIENCE"),
+    ("todo_url", "TODO_URL"),
+    ("scopes", "SCOPES"),
+];
+
+/// Values saved with `todo config set`, keyed by variable name.
+pub(crate) type ConfigFile = BTreeMap<String, String>;
+
+fn config_file_path() -> Result<PathBuf, Error> {
+    dirs::home_dir()
+        .map(|home| home.join(".todo").join("config.json"))
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "Home directory not found"))
+}
+
+fn load_config_file() -> ConfigFile {
+    config_file_path()
+        .and_then(fs::read_to_string)
+        .ok()
+        .and_then(|contents| serde_json::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Variable name for a `todo config set` key.
+pub fn config_var(key: &str) -> Result<&'static str, String> {
+    CONFIG_KEYS
+        .iter()
+        .find(|(name, _)| *name == key)
+        .map(|(_, var)| *var)
+        .ok_or_else(|| {
+            let keys: Vec<&str> = CONFIG_KEYS.iter().map(|(name, _)| *name).collect();
+            format!("unknown key `{}`, use one of {}", key, keys.join(", "))
+        })
+}
+
+/// Saves `value` for `key` in `~/.todo/config.json`; environment variables
+/// still take precedence over it.
+pub fn set_config_value(key: &str, value: &str) -> Result<(), Error> {
+    let var = config_var(key).map_err(|e| Error::new(ErrorKind::InvalidInput, e))?;
+    let path = config_file_path()?;
+    let mut file = load_config_file();
+    file.insert(var.to_string(), value.to_string());
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&file)?)
+}
+
+/// Identity provider settings are only needed to log in or refresh a token,
+/// so they are checked when used rather than at startup.
 #[derive(Debug)]
 pub struct Config {
-    pub domain: String,
-    pub client_id: String,
-    pub audience: String,
+    domain: Option<String>,
+    client_id: Option<String>,
+    audience: Option<String>,
     pub todo_url: String,
+    pub scopes: String,
+}
+
+/// Identity provider settings, all present.
+#[derive(Debug)]
+pub struct AuthConfig<'a> {
+    pub domain: &'a str,
+    pub client_id: &'a str,
+    pub audience: &'a str,
+}
+
+/// Names every required variable that is unset, so they can be fixed at once.
+#[derive(Debug, PartialEq)]
+pub struct MissingConfig(pub Vec<&'static str>);
+
+impl fmt::Display for MissingConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Missing configuration: {}. Set them in the environment or a .env file.",
+            self.0.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for MissingConfig {}
+
+/// Reads `name`, preferring a profile specific `<PROFILE>_<name>` variable.
+fn profile_var(profile: &str, name: &str) -> Result<String, env::VarError> {
+    let prefix = profile.to_uppercase().replace('-', "_");
+    env::var(format!("{}_{}", prefix, name)).or_else(|_| env::var(name))
 }
 
 impl Config {
-    pub fn from_env() -> Result<Self, env::VarError> {
+    /// Reads the environment, falling back to values saved in the config file.
+    pub fn from_env(profile: &str) -> Self {
         dotenv().ok();
-        let domain = env::var("DOMAIN")?;
-        let client_id = env::var("CLIENT_ID")?;
-        let audience = env::var("AUDIENCE")?;
-        let todo_url = env::var("TODO_URL")?;
+        Self::from_sources(profile, &load_config_file())
+    }
 
-        Ok(Self {
+    pub(crate) fn from_sources(profile: &str, file: &ConfigFile) -> Self {
+        let var = |name: &str| {
+            profile_var(profile, name)
+                .ok()
+                .or_else(|| file.get(name).cloned())
+        };
+        let domain = var("DOMAIN");
+        let client_id = var("CLIENT_ID");
+        let audience = var("AUDIENCE");
+        let todo_url = var("TODO_URL").unwrap_or_else(|| DEFAULT_TODO_URL.to_string());
+        let scopes = var("SCOPES").unwrap_or_else(|| DEFAULT_SCOPES.to_string());
+
+        Self {
             domain,
             client_id,
             audience,
             todo_url,
-        })
+            scopes,
+        }
+    }
+
+    /// Resolved values by `todo config set` key; unset ones are `None`.
+    pub fn entries(&self) -> Vec<(&'static str, Option<&str>)> {
+        vec![
+            ("domain", self.domain.as_deref()),
+            ("client_id", self.client_id.as_deref()),
+            ("audience", self.audience.as_deref()),
+            ("todo_url", Some(self.todo_url.as_str())),
+            ("scopes", Some(self.scopes.as_str())),
+        ]
+    }
+
+    pub fn auth(&self) -> Result<AuthConfig<'_>, MissingConfig> {
+        match (&self.domain, &self.client_id, &self.audience) {
+            (Some(domain), Some(client_id), Some(audience)) => Ok(AuthConfig {
+                domain,
+                client_id,
+                audience,
+            }),
+            _ => {
+                let missing = [
+                    ("DOMAIN", &self.domain),
+                    ("CLIENT_ID", &self.client_id),
+                    ("AUDIENCE", &self.audience),
+                ]
+                .into_iter()
+                .filter(|(_, value)| value.is_none())
+                .map(|(name, _)| name)
+                .collect();
+                Err(MissingConfig(missing))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_auth_lists_all_missing_vars() {
+        let mut config = Config {
+            domain: Some("example.auth0.com".to_string()),
+            client_id: None,
+            audience: None,
+            todo_url: DEFAULT_TODO_URL.to_string(),
+            scopes: DEFAULT_SCOPES.to_string(),
+        };
+        let err = config.auth().unwrap_err();
+        assert_eq!(err, MissingConfig(vec!["CLIENT_ID", "AUDIENCE"]));
+        assert_eq!(
+            err.to_string(),
+            "Missing configuration: CLIENT_ID, AUDIENCE. Set them in the environment or a .env file."
+        );
+
+        config.client_id = Some("client".to_string());
+        config.audience = Some("https://todos.example.com/".to_string());
+        assert_eq!(config.auth().unwrap().domain, "example.auth0.com");
+    }
+
+    #[test]
+    fn test_config_file_values_under_env() {
+        let mut file = ConfigFile::new();
+        file.insert("CLIENT_ID".to_string(), "from-file".to_string());
+        file.insert("TODO_URL".to_string(), "http://todo.internal".to_string());
+        env::set_var("CONFIG_TEST_CLIENT_ID", "from-env");
+        let config = Config::from_sources("config-test", &file);
+        assert_eq!(config.client_id.as_deref(), Some("from-env"));
+        assert_eq!(config.todo_url, "http://todo.internal");
+
+        assert_eq!(config_var("todo_url"), Ok("TODO_URL"));
+        assert!(config_var("password").is_err());
     }
 }
@@ -0,0 +1,209 @@
+use log::debug;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use std::time::{Duration, Instant};
+
+/// Attempts per request unless `HTTP_MAX_ATTEMPTS` says otherwise.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+/// Delay before the first retry; doubled for each one after it.
+const BASE_DELAY: Duration = Duration::from_millis(250);
+
+/// How safe a request is to send again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Retry {
+    /// Repeating the request has no further effect (GET, DELETE, conditional
+    /// PATCH), so retry on gateway errors and any connection failure.
+    Idempotent,
+    /// Only retry when the request never reached the server.
+    UnlessSent,
+}
+
+fn max_attempts() -> u32 {
+    std::env::var("HTTP_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|attempts| *attempts > 0)
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+}
+
+/// Gateway errors are usually a server restarting behind a proxy; other
+/// statuses, 4xx in particular, won't change on retry.
+fn retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn retryable_error(error: &reqwest::Error, retry: Retry) -> bool {
+    match retry {
+        Retry::Idempotent => error.is_connect() || error.is_timeout(),
+        Retry::UnlessSent => error.is_connect(),
+    }
+}
+
+/// Delay before retry number `retry` (starting at 1).
+fn backoff(retry: u32) -> Duration {
+    BASE_DELAY * 2u32.saturating_pow(retry - 1)
+}
+
+/// `METHOD url` of the request for the debug log; requests with a streaming
+/// body can't be inspected without consuming them.
+fn describe(request: &RequestBuilder) -> String {
+    match request.try_clone().map(|request| request.build()) {
+        Some(Ok(request)) => format!("{} {}", request.method(), request.url()),
+        _ => "request".to_string(),
+    }
+}
+
+/// Sends once, logging the outcome and how long it took at debug level.
+fn send_timed(request: RequestBuilder, description: &str) -> reqwest::Result<Response> {
+    let start = Instant::now();
+    let result = request.send();
+    match &result {
+        Ok(response) => debug!(
+            "{} -> {} in {:?}",
+            description,
+            response.status(),
+            start.elapsed()
+        ),
+        Err(e) => debug!("{} failed in {:?}: {}", description, start.elapsed(), e),
+    }
+    result
+}
+
+pub trait SendWithRetry {
+    /// Sends the request, retrying transient failures with exponential backoff.
+    fn send_with_retry(self, retry: Retry) -> reqwest::Result<Response>;
+    /// Sends the request once, for requests that must not be repeated; logged
+    /// like `send_with_retry`.
+    fn send_logged(self) -> reqwest::Result<Response>;
+}
+
+impl SendWithRetry for RequestBuilder {
+    fn send_with_retry(self, retry: Retry) -> reqwest::Result<Response> {
+        let description = describe(&self);
+        let max_attempts = max_attempts();
+        let mut attempt = 1;
+        loop {
+            // streaming bodies can't be cloned, so those get a single attempt
+            let request = match self.try_clone() {
+                Some(request) if attempt < max_attempts => request,
+                _ => return send_timed(self, &description),
+            };
+            match send_timed(request, &description) {
+                Ok(response)
+                    if retry == Retry::Idempotent && retryable_status(response.status()) => {}
+                Err(e) if retryable_error(&e, retry) => {}
+                result => return result,
+            }
+            debug!(
+                "Retrying {} in {:?} (attempt {} of {})",
+                description,
+                backoff(attempt),
+                attempt + 1,
+                max_attempts
+            );
+            std::thread::sleep(backoff(attempt));
+            attempt += 1;
+        }
+    }
+
+    fn send_logged(self) -> reqwest::Result<Response> {
+        let description = describe(&self);
+        send_timed(self, &description)
+    }
+}
+
+/// Describes a failed response. The server sends `{"message": ...}`, but
+/// proxies and older servers answer in plain text, so any other body is
+/// shown as is.
+fn error_message(status: StatusCode, body: &str) -> String {
+    let message = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|value| value["message"].as_str().map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string());
+    if message.is_empty() {
+        status.to_string()
+    } else {
+        format!("{}: {}", status, message)
+    }
+}
+
+/// Passes a successful response through and turns any other into an error
+/// carrying the server's message.
+pub fn check_status(response: Response) -> Result<Response, String> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().unwrap_or_default();
+    Err(error_message(status, &body))
+}
+
+/// Parses the body of a successful response, so an error body is reported
+/// as the server's message rather than as a parse error.
+pub fn read_json<T: DeserializeOwned>(response: Response) -> Result<T, String> {
+    check_status(response)?
+        .json::<T>()
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_status() {
+        assert!(retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(retryable_status(StatusCode::GATEWAY_TIMEOUT));
+        assert!(!retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!retryable_status(StatusCode::NOT_FOUND));
+        assert!(!retryable_status(StatusCode::OK));
+    }
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(250));
+        assert_eq!(backoff(2), Duration::from_millis(500));
+        assert_eq!(backoff(3), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn test_describe() {
+        let client = reqwest::blocking::Client::new();
+        assert_eq!(
+            describe(&client.get("http://localhost:3030/todos?limit=5")),
+            "GET http://localhost:3030/todos?limit=5"
+        );
+        assert_eq!(
+            describe(&client.delete("http://localhost:3030/todos/1")),
+            "DELETE http://localhost:3030/todos/1"
+        );
+    }
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(
+            error_message(
+                StatusCode::NOT_FOUND,
+                r#"{"message":"Todo not found","code":"NOT_FOUND"}"#
+            ),
+            "404 Not Found: Todo not found"
+        );
+        assert_eq!(
+            error_message(StatusCode::BAD_GATEWAY, "upstream unavailable\n"),
+            "502 Bad Gateway: upstream unavailable"
+        );
+        assert_eq!(
+            error_message(StatusCode::UNAUTHORIZED, r#"{"error":"expired"}"#),
+            r#"401 Unauthorized: {"error":"expired"}"#
+        );
+        assert_eq!(
+            error_message(StatusCode::INTERNAL_SERVER_ERROR, ""),
+            "500 Internal Server Error"
+        );
+    }
+}
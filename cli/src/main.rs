@@ -1,24 +1,70 @@
-use crate::commands::{invoke_command, CommandContext};
+use crate::commands::{invoke_command, parse_cli, CommandContext};
 use crate::config::Config;
+use crate::profile::{active_profile, credentials_file, DEFAULT_PROFILE};
 use cred_store::{CredStore, Credentials};
+use log::LevelFilter;
 
 mod auth;
 mod commands;
 mod config;
+mod http;
+mod profile;
+
+/// Warnings only by default; `RUST_LOG` can still refine the level per
+/// module, e.g. `RUST_LOG=reqwest=trace`.
+fn log_level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) => LevelFilter::Off,
+        (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::from_env()?;
+    let cli = parse_cli();
+    env_logger::Builder::new()
+        .filter_level(log_level(cli.verbose, cli.quiet))
+        .parse_default_env()
+        .init();
+    let profile = cli
+        .profile
+        .clone()
+        .or_else(active_profile)
+        .unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    let config = Config::from_env(&profile);
     let mut credentials = Credentials::new()
-        .set_file_name(".credentials".to_string())
+        .set_file_name(credentials_file(&profile))
         .build()
         .load()?;
 
+    let server_url = cli
+        .server_url
+        .clone()
+        .unwrap_or_else(|| config.todo_url.clone());
     let mut context = CommandContext {
         config: &config,
         cred_store: &mut credentials,
+        profile,
+        server_url,
     };
 
-    invoke_command(&mut context);
+    invoke_command(cli, &mut context);
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_level() {
+        assert_eq!(log_level(0, false), LevelFilter::Warn);
+        assert_eq!(log_level(1, false), LevelFilter::Info);
+        assert_eq!(log_level(2, false), LevelFilter::Debug);
+        assert_eq!(log_level(5, false), LevelFilter::Trace);
+        assert_eq!(log_level(0, true), LevelFilter::Off);
+    }
+}
@@ -0,0 +1,99 @@
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::path::PathBuf;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const CREDENTIALS_FILE: &str = ".credentials";
+const ACTIVE_PROFILE_FILE: &str = ".todo-profile";
+
+/// Profile names end up in file names, so only a safe subset is allowed.
+pub fn validate_profile(name: &str) -> Result<(), String> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(())
+    } else {
+        Err(format!(
+            "invalid profile `{}`, use letters, digits, `-` and `_`",
+            name
+        ))
+    }
+}
+
+/// Credentials file for `profile`; the default profile keeps the original name.
+pub fn credentials_file(profile: &str) -> String {
+    if profile == DEFAULT_PROFILE {
+        CREDENTIALS_FILE.to_string()
+    } else {
+        format!("{}-{}", CREDENTIALS_FILE, profile)
+    }
+}
+
+fn home_dir() -> Result<PathBuf, Error> {
+    dirs::home_dir().ok_or_else(|| Error::new(ErrorKind::NotFound, "Home directory not found"))
+}
+
+/// Profile selected with `todo profile use`, if any.
+pub fn active_profile() -> Option<String> {
+    let path = home_dir().ok()?.join(ACTIVE_PROFILE_FILE);
+    fs::read_to_string(path)
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| validate_profile(name).is_ok())
+}
+
+pub fn set_active_profile(profile: &str) -> Result<(), Error> {
+    fs::write(home_dir()?.join(ACTIVE_PROFILE_FILE), profile)
+}
+
+/// Profiles with stored credentials, sorted by name.
+pub fn list_profiles() -> Result<Vec<String>, Error> {
+    let mut profiles = fs::read_dir(home_dir()?)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|file_name| profile_from_file_name(&file_name))
+        .collect::<Vec<String>>();
+    profiles.sort();
+    Ok(profiles)
+}
+
+fn profile_from_file_name(file_name: &str) -> Option<String> {
+    if file_name == CREDENTIALS_FILE {
+        return Some(DEFAULT_PROFILE.to_string());
+    }
+    file_name
+        .strip_prefix(CREDENTIALS_FILE)?
+        .strip_prefix('-')
+        .filter(|name| validate_profile(name).is_ok())
+        .map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credentials_file() {
+        assert_eq!(credentials_file(DEFAULT_PROFILE), ".credentials");
+        assert_eq!(credentials_file("work"), ".credentials-work");
+        assert_eq!(
+            profile_from_file_name(&credentials_file("work")),
+            Some("work".to_string())
+        );
+        assert_eq!(
+            profile_from_file_name(".credentials"),
+            Some(DEFAULT_PROFILE.to_string())
+        );
+        assert_eq!(profile_from_file_name(".credentials.json"), None);
+    }
+
+    #[test]
+    fn test_validate_profile() {
+        assert!(validate_profile("work-2").is_ok());
+        assert!(validate_profile("").is_err());
+        assert!(validate_profile("../etc").is_err());
+    }
+}
@@ -1,6 +1,9 @@
 #[path = "file-store.rs"]
 pub mod file_store;
+#[path = "memory-store.rs"]
+pub mod memory_store;
 pub mod traits;
 
 pub use file_store::*;
+pub use memory_store::MemoryStore;
 pub use traits::CredStore;
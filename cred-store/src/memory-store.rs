@@ -0,0 +1,78 @@
+use super::traits::CredStore;
+use std::collections::HashMap;
+use std::io::Error;
+use std::sync::{Arc, Mutex};
+
+/// A `CredStore` that never touches the filesystem. `save` keeps a snapshot
+/// that later `load`s return and `delete` drops; clones share the snapshot,
+/// so it lives as long as any of them.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    data: HashMap<String, String>,
+    saved: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+}
+
+impl CredStore for MemoryStore {
+    fn add(&mut self, key: String, value: String) -> &mut Self {
+        self.data.insert(key, value);
+        self
+    }
+
+    fn get(&self, key: &str) -> Option<&String> {
+        self.data.get(key)
+    }
+
+    fn clear(&mut self) -> &mut Self {
+        self.data.clear();
+        self
+    }
+
+    fn keys_present(&self, keys: &[String]) -> bool {
+        keys.iter().all(|key| self.data.contains_key(key))
+    }
+
+    fn load(&self) -> Result<Self, Error> {
+        Ok(MemoryStore {
+            data: self.saved.lock().unwrap().clone(),
+            saved: self.saved.clone(),
+        })
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        *self.saved.lock().unwrap() = self.data.clone();
+        Ok(())
+    }
+
+    fn delete(&self) -> Result<(), Error> {
+        self.saved.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_store() {
+        let mut store = MemoryStore::new().load().unwrap();
+        store.add("username".to_string(), "admin".to_string());
+        assert!(store.load().unwrap().get("username").is_none());
+
+        store.save().unwrap();
+        let loaded = store.load().unwrap();
+        assert_eq!(loaded.get("username").unwrap(), "admin");
+        assert!(loaded.keys_present(&["username".to_string()]));
+
+        loaded.delete().unwrap();
+        assert!(store.load().unwrap().get("username").is_none());
+        // deleting only drops the saved snapshot
+        assert_eq!(store.get("username").unwrap(), "admin");
+    }
+}
@@ -1,48 +1,178 @@
 use chrono::prelude::*;
-use clap::Parser;
-use jsonwebtoken::{encode, EncodingKey, Header};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, ValueEnum};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::ffi::OsString;
+use std::process;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Serialize, Deserialize)]
+enum Alg {
+    /// Shared secret, only verifiable by holders of the secret
+    HS256,
+    /// RSA private key, verifiable by the server through JWKS
+    RS256,
+}
 
 #[derive(Parser, Debug, Serialize, Deserialize)]
 #[command(author, version, about, long_about = None)]
 struct Args {
+    #[arg(short, long, value_enum, default_value_t = Alg::HS256)]
+    alg: Alg,
+    /// Required for HS256
+    #[arg(short, long)]
+    secret: Option<String>,
+    /// Required for RS256
     #[arg(short, long)]
-    secret: String,
+    private_key: Option<String>,
+    /// Required for RS256
+    #[arg(short, long)]
+    kid: Option<String>,
     #[arg(short, long)]
     tenant_id: String,
     #[arg(short, long)]
     user_id: String,
     #[arg(short, long, default_value_t = 3600)]
     exp: usize,
+    #[arg(long)]
+    aud: Option<String>,
+    #[arg(long)]
+    iss: Option<String>,
+    #[arg(long)]
+    sub: Option<String>,
+    /// Extra claim as key=value; the value is parsed as JSON when possible
+    #[arg(short, long = "claim", value_parser = parse_claim)]
+    claims: Vec<(String, Value)>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    tenant_id: String,
-    user_id: String,
-    exp: usize,
+/// Parses the command line and checks that the key arguments match `--alg`,
+/// which clap's `required_if_eq` misses when `--alg` is left at its default.
+fn parse_args<I, T>(itr: I) -> Result<Args, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let args = Args::try_parse_from(itr)?;
+    let missing = match args.alg {
+        Alg::HS256 if args.secret.is_none() => Some("--secret is required for HS256"),
+        Alg::RS256 if args.private_key.is_none() => Some("--private-key is required for RS256"),
+        Alg::RS256 if args.kid.is_none() => Some("--kid is required for RS256"),
+        _ => None,
+    };
+    match missing {
+        Some(message) => Err(Args::command().error(ErrorKind::MissingRequiredArgument, message)),
+        None => Ok(args),
+    }
+}
+
+fn parse_claim(s: &str) -> Result<(String, Value), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid claim `{}`, expected key=value", s))?;
+    if key.is_empty() {
+        return Err(format!("invalid claim `{}`, key is empty", s));
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((key.to_string(), value))
+}
+
+fn build_claims(args: &Args) -> Map<String, Value> {
+    let exp = (Utc::now() + chrono::Duration::seconds(args.exp as i64)).timestamp();
+    let mut claims = Map::new();
+    claims.insert("tenant_id".to_string(), args.tenant_id.clone().into());
+    claims.insert("user_id".to_string(), args.user_id.clone().into());
+    claims.insert("exp".to_string(), exp.into());
+    if let Some(aud) = &args.aud {
+        claims.insert("aud".to_string(), aud.clone().into());
+    }
+    if let Some(iss) = &args.iss {
+        claims.insert("iss".to_string(), iss.clone().into());
+    }
+    if let Some(sub) = &args.sub {
+        claims.insert("sub".to_string(), sub.clone().into());
+    }
+    for (key, value) in &args.claims {
+        claims.insert(key.clone(), value.clone());
+    }
+    claims
+}
+
+fn encoding_key(args: &Args) -> Result<(Header, EncodingKey), Box<dyn std::error::Error>> {
+    match args.alg {
+        Alg::HS256 => {
+            let secret = args
+                .secret
+                .as_ref()
+                .ok_or("--secret is required for HS256")?;
+            let mut header = Header::new(Algorithm::HS256);
+            header.kid = args.kid.clone();
+            Ok((header, EncodingKey::from_secret(secret.as_ref())))
+        }
+        Alg::RS256 => {
+            let path = args
+                .private_key
+                .as_ref()
+                .ok_or("--private-key is required for RS256")?;
+            let pem = std::fs::read(path)?;
+            let kid = args.kid.clone().ok_or("--kid is required for RS256")?;
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some(kid);
+            Ok((header, EncodingKey::from_rsa_pem(&pem)?))
+        }
+    }
 }
 
 fn main() {
-    let args = Args::parse();
-    let tenant_id = args.tenant_id;
-    let user_id = args.user_id;
-    let duration = args.exp;
-
-    let my_claims = Claims {
-        tenant_id,
-        user_id,
-        exp: (Utc::now() + chrono::Duration::seconds(duration as i64)).timestamp() as usize,
-    };
+    let args = parse_args(std::env::args_os()).unwrap_or_else(|e| e.exit());
+    let my_claims = build_claims(&args);
 
-    let key = args.secret;
-    let token = match encode(
-        &Header::default(),
-        &my_claims,
-        &EncodingKey::from_secret(key.as_ref()),
-    ) {
+    let (header, key) = match encoding_key(&args) {
+        Ok(k) => k,
+        Err(e) => {
+            eprintln!("Error loading the signing key: {}", e);
+            process::exit(1);
+        }
+    };
+    let token = match encode(&header, &my_claims, &key) {
         Ok(t) => t,
-        Err(_) => panic!("Error generating the token"),
+        Err(e) => {
+            eprintln!("Error generating the token: {}", e);
+            process::exit(1);
+        }
     };
     println!("Generated JWT: {}", token);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args_requires_key() {
+        let err = parse_args(["genjwt", "-t", "t", "-u", "u"]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::MissingRequiredArgument);
+        let err = parse_args([
+            "genjwt", "-a", "rs256", "-p", "key.pem", "-t", "t", "-u", "u",
+        ])
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::MissingRequiredArgument);
+
+        let args = parse_args(["genjwt", "-s", "secret", "-t", "t", "-u", "u"]).unwrap();
+        assert!(encoding_key(&args).is_ok());
+    }
+
+    #[test]
+    fn test_parse_claim() {
+        assert_eq!(
+            parse_claim("admin=true").unwrap(),
+            ("admin".to_string(), Value::Bool(true))
+        );
+        assert_eq!(
+            parse_claim("role=editor").unwrap(),
+            ("role".to_string(), Value::String("editor".to_string()))
+        );
+        assert!(parse_claim("=x").is_err());
+        assert!(parse_claim("novalue").is_err());
+    }
+}
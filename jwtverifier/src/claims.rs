@@ -0,0 +1,116 @@
+use serde::{Deserialize, Serialize};
+
+/// Registered JWT claims (RFC 7519) plus the OAuth `scope` claim, for callers
+/// that don't need a custom claims type.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StandardClaims {
+    #[serde(default)]
+    pub iss: Option<String>,
+    #[serde(default)]
+    pub sub: Option<String>,
+    /// A single audience string is normalized to a one-element list.
+    #[serde(default, deserialize_with = "string_or_vec::deserialize")]
+    pub aud: Vec<String>,
+    pub exp: u64,
+    #[serde(default)]
+    pub iat: Option<u64>,
+    #[serde(default)]
+    pub nbf: Option<u64>,
+    #[serde(default)]
+    pub scope: Option<String>,
+}
+
+impl StandardClaims {
+    /// The space separated `scope` claim as a list.
+    pub fn scopes(&self) -> Vec<&str> {
+        self.scope
+            .as_deref()
+            .map(|scope| scope.split_whitespace().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Deserializes a claim that may be either a single string or an array of
+/// strings into a `Vec<String>`, e.g. `aud`:
+///
+/// ```ignore
+/// #[serde(deserialize_with = "jwtverifier::string_or_vec::deserialize")]
+/// aud: Vec<String>,
+/// ```
+pub mod string_or_vec {
+    use serde::{Deserialize, Deserializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrVec {
+        String(String),
+        Vec(Vec<String>),
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Ok(match StringOrVec::deserialize(deserializer)? {
+            StringOrVec::String(value) => vec![value],
+            StringOrVec::Vec(values) => values,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_keys::{self, test_jwks};
+    use crate::verify_jwt;
+
+    #[derive(Debug, Clone, Deserialize)]
+    struct AudClaims {
+        #[serde(deserialize_with = "string_or_vec::deserialize")]
+        aud: Vec<String>,
+    }
+
+    fn sign(aud: serde_json::Value) -> String {
+        test_keys::sign(serde_json::json!({
+            "sub": "auth0|1",
+            "aud": aud,
+            "exp": 4102444800_u64,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_scalar_aud() {
+        let jwt = sign(serde_json::json!("x"));
+        let token = verify_jwt::<AudClaims>(&jwt, &test_jwks(), Some("x".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(token.claims.aud, vec!["x".to_string()]);
+        let result = verify_jwt::<AudClaims>(&jwt, &test_jwks(), Some("y".to_string())).await;
+        assert_eq!(result.unwrap_err().to_string(), "InvalidAudience");
+    }
+
+    #[tokio::test]
+    async fn test_array_aud() {
+        let jwt = sign(serde_json::json!(["x", "y"]));
+        let token = verify_jwt::<AudClaims>(&jwt, &test_jwks(), Some("y".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(token.claims.aud, vec!["x".to_string(), "y".to_string()]);
+        let token = verify_jwt::<StandardClaims>(&jwt, &test_jwks(), Some("x".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(token.claims.aud.len(), 2);
+    }
+
+    #[test]
+    fn test_standard_claims() {
+        let claims: StandardClaims = serde_json::from_str(
+            r#"{"iss":"https://example.auth0.com/","sub":"auth0|1","aud":"https://todos.example.com/","exp":2,"iat":1,"scope":"openid email"}"#,
+        )
+        .unwrap();
+        assert_eq!(claims.sub.as_deref(), Some("auth0|1"));
+        assert_eq!(claims.aud, vec!["https://todos.example.com/".to_string()]);
+        assert_eq!(claims.nbf, None);
+        assert_eq!(claims.scopes(), vec!["openid", "email"]);
+    }
+}
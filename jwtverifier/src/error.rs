@@ -0,0 +1,78 @@
+use crate::stats::FailureReason;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum JwtVerifierError {
+    /// The JWKS couldn't be fetched or parsed.
+    JwksFetch(String),
+    /// The token header has no `kid`.
+    MissingKid,
+    /// No key in the JWKS matches the token's `kid`.
+    UnknownKey(String),
+    /// The matching JWK can't be used to verify signatures.
+    UnsupportedKey,
+    /// No verifier is configured for the token's issuer; `None` when the
+    /// token has no `iss` claim.
+    UnknownIssuer(Option<String>),
+    /// The verifier was built with an unusable domain or JWKS URL.
+    InvalidConfig(String),
+    /// Decoding or validating the token failed.
+    Jwt(jsonwebtoken::errors::Error),
+}
+
+impl JwtVerifierError {
+    pub fn reason(&self) -> FailureReason {
+        match self {
+            JwtVerifierError::JwksFetch(_) => FailureReason::JwksFetch,
+            JwtVerifierError::MissingKid => FailureReason::Malformed,
+            JwtVerifierError::UnknownKey(_) => FailureReason::UnknownKey,
+            JwtVerifierError::UnsupportedKey => FailureReason::Other,
+            JwtVerifierError::UnknownIssuer(_) => FailureReason::UnknownIssuer,
+            JwtVerifierError::InvalidConfig(_) => FailureReason::Other,
+            JwtVerifierError::Jwt(e) => e.into(),
+        }
+    }
+}
+
+impl fmt::Display for JwtVerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JwtVerifierError::JwksFetch(e) => write!(f, "failed to fetch jwks: {}", e),
+            JwtVerifierError::MissingKid => write!(f, "kid not found in jwt header"),
+            JwtVerifierError::UnknownKey(kid) => write!(f, "jwk not found: {}", kid),
+            JwtVerifierError::UnsupportedKey => write!(f, "symmetric jwk not supported"),
+            JwtVerifierError::UnknownIssuer(Some(iss)) => write!(f, "unknown issuer: {}", iss),
+            JwtVerifierError::UnknownIssuer(None) => write!(f, "iss not found in jwt"),
+            JwtVerifierError::InvalidConfig(e) => write!(f, "invalid verifier config: {}", e),
+            // keep jsonwebtoken's messages, e.g. `ExpiredSignature`
+            JwtVerifierError::Jwt(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for JwtVerifierError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            JwtVerifierError::Jwt(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for JwtVerifierError {
+    fn from(err: jsonwebtoken::errors::Error) -> Self {
+        JwtVerifierError::Jwt(err)
+    }
+}
+
+impl From<jsonwebtoken::errors::ErrorKind> for JwtVerifierError {
+    fn from(kind: jsonwebtoken::errors::ErrorKind) -> Self {
+        JwtVerifierError::Jwt(kind.into())
+    }
+}
+
+impl From<reqwest::Error> for JwtVerifierError {
+    fn from(err: reqwest::Error) -> Self {
+        JwtVerifierError::JwksFetch(err.to_string())
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{decode_unverified, JwtVerifier, JwtVerifierError, StandardClaims};
+use jsonwebtoken::TokenData;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// Verifies tokens from several issuers, each with its own JWKS, audience and
+/// cache. The verifier is picked by the token's `iss` before the signature is
+/// checked; that is safe because the signature then has to match the keys of
+/// the issuer the token claims.
+#[derive(Debug, Clone, Default)]
+pub struct MultiTenantVerifier {
+    verifiers: HashMap<String, JwtVerifier>,
+}
+
+impl MultiTenantVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accepts tokens whose `iss` is exactly `issuer`, verified against the
+    /// JWKS of `domain` with audience `audience`.
+    pub fn tenant(self, issuer: &str, domain: &str, audience: &str) -> Self {
+        let verifier = JwtVerifier::new(domain)
+            .use_cache(true)
+            .validate_aud(audience)
+            .build();
+        self.tenant_verifier(issuer, verifier)
+    }
+
+    /// Like `tenant`, for a verifier configured by the caller.
+    pub fn tenant_verifier(mut self, issuer: &str, verifier: JwtVerifier) -> Self {
+        self.verifiers.insert(issuer.to_string(), verifier);
+        self
+    }
+
+    /// The configured issuers with their verifiers, e.g. to prefetch or to
+    /// export stats.
+    pub fn verifiers(&self) -> impl Iterator<Item = (&str, &JwtVerifier)> {
+        self.verifiers
+            .iter()
+            .map(|(issuer, verifier)| (issuer.as_str(), verifier))
+    }
+
+    /// The verifier for the issuer `jwt` claims to come from.
+    pub fn verifier_for(&self, jwt: &str) -> Result<&JwtVerifier, JwtVerifierError> {
+        let iss = decode_unverified::<StandardClaims>(jwt)?.claims.iss;
+        match iss {
+            Some(iss) => match self.verifiers.get(&iss) {
+                Some(verifier) => Ok(verifier),
+                None => Err(JwtVerifierError::UnknownIssuer(Some(iss))),
+            },
+            None => Err(JwtVerifierError::UnknownIssuer(None)),
+        }
+    }
+
+    pub async fn verify<Claims: DeserializeOwned + Clone>(
+        &self,
+        jwt: &str,
+    ) -> Result<TokenData<Claims>, JwtVerifierError> {
+        self.verifier_for(jwt)?.clone().verify(jwt).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_keys::{sign, test_jwks_json};
+    use crate::FailureReason;
+    use mockito::mock;
+
+    fn token(iss: Option<&str>, aud: &str) -> String {
+        let mut claims = serde_json::json!({
+            "sub": "auth0|1",
+            "aud": aud,
+            "exp": 4102444800_u64,
+        });
+        if let Some(iss) = iss {
+            claims["iss"] = iss.into();
+        }
+        sign(claims)
+    }
+
+    #[tokio::test]
+    async fn test_multi_tenant_verifier() {
+        let _a = mock("GET", "/tenant-a/.well-known/jwks.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(test_jwks_json().to_string())
+            .create();
+        let _b = mock("GET", "/tenant-b/.well-known/jwks.json")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(test_jwks_json().to_string())
+            .create();
+        let verifier = MultiTenantVerifier::new()
+            .tenant("https://a/", "http://localhost:1234/tenant-a", "api-a")
+            .tenant("https://b/", "http://localhost:1234/tenant-b", "api-b");
+
+        let claims = verifier
+            .verify::<StandardClaims>(&token(Some("https://a/"), "api-a"))
+            .await
+            .unwrap()
+            .claims;
+        assert_eq!(claims.iss.as_deref(), Some("https://a/"));
+        assert!(verifier
+            .verify::<StandardClaims>(&token(Some("https://b/"), "api-b"))
+            .await
+            .is_ok());
+
+        // each issuer only accepts its own audience
+        let err = verifier
+            .verify::<StandardClaims>(&token(Some("https://b/"), "api-a"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), FailureReason::InvalidAudience);
+
+        let err = verifier
+            .verify::<StandardClaims>(&token(Some("https://c/"), "api-a"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "unknown issuer: https://c/");
+        let err = verifier
+            .verify::<StandardClaims>(&token(None, "api-a"))
+            .await
+            .unwrap_err();
+        assert_eq!(err.reason(), FailureReason::UnknownIssuer);
+
+        let fetches = |issuer: &str| {
+            verifier
+                .verifiers()
+                .find(|(iss, _)| *iss == issuer)
+                .map(|(_, verifier)| verifier.stats().jwks_fetches())
+        };
+        assert_eq!(fetches("https://a/"), Some(1));
+        assert_eq!(fetches("https://b/"), Some(1));
+    }
+}
@@ -0,0 +1,110 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Why a token failed verification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureReason {
+    /// The JWKS endpoint couldn't be fetched or parsed.
+    JwksFetch,
+    /// The token isn't a well-formed JWT or has no `kid`.
+    Malformed,
+    /// No key in the JWKS matches the token's `kid`.
+    UnknownKey,
+    Expired,
+    /// The token's `nbf` is still in the future.
+    NotYetValid,
+    /// A claim required by the verifier is missing.
+    MissingClaim,
+    InvalidSignature,
+    InvalidAudience,
+    /// The token's `iss` isn't one the verifier is configured for.
+    UnknownIssuer,
+    Other,
+}
+
+impl FailureReason {
+    pub const ALL: [FailureReason; 10] = [
+        FailureReason::JwksFetch,
+        FailureReason::Malformed,
+        FailureReason::UnknownKey,
+        FailureReason::Expired,
+        FailureReason::NotYetValid,
+        FailureReason::MissingClaim,
+        FailureReason::InvalidSignature,
+        FailureReason::InvalidAudience,
+        FailureReason::UnknownIssuer,
+        FailureReason::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureReason::JwksFetch => "jwks_fetch",
+            FailureReason::Malformed => "malformed",
+            FailureReason::UnknownKey => "unknown_key",
+            FailureReason::Expired => "expired",
+            FailureReason::NotYetValid => "not_yet_valid",
+            FailureReason::MissingClaim => "missing_claim",
+            FailureReason::InvalidSignature => "invalid_signature",
+            FailureReason::InvalidAudience => "invalid_audience",
+            FailureReason::UnknownIssuer => "unknown_issuer",
+            FailureReason::Other => "other",
+        }
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl From<&jsonwebtoken::errors::Error> for FailureReason {
+    fn from(err: &jsonwebtoken::errors::Error) -> Self {
+        use jsonwebtoken::errors::ErrorKind;
+        match err.kind() {
+            ErrorKind::ExpiredSignature => FailureReason::Expired,
+            ErrorKind::ImmatureSignature => FailureReason::NotYetValid,
+            ErrorKind::MissingRequiredClaim(_) => FailureReason::MissingClaim,
+            ErrorKind::InvalidSignature => FailureReason::InvalidSignature,
+            ErrorKind::InvalidAudience => FailureReason::InvalidAudience,
+            ErrorKind::InvalidToken
+            | ErrorKind::Base64(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::Utf8(_) => FailureReason::Malformed,
+            _ => FailureReason::Other,
+        }
+    }
+}
+
+/// Counters shared by all clones of a `JwtVerifier`.
+#[derive(Debug, Default)]
+pub struct VerifierStats {
+    jwks_fetches: AtomicU64,
+    cache_hits: AtomicU64,
+    failures: [AtomicU64; FailureReason::ALL.len()],
+}
+
+impl VerifierStats {
+    /// Number of times the JWKS was requested from the network.
+    pub fn jwks_fetches(&self) -> u64 {
+        self.jwks_fetches.load(Ordering::Relaxed)
+    }
+
+    /// Number of verifications served from the cached JWKS.
+    pub fn cache_hits(&self) -> u64 {
+        self.cache_hits.load(Ordering::Relaxed)
+    }
+
+    pub fn failures(&self, reason: FailureReason) -> u64 {
+        self.failures[reason.index()].load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_jwks_fetch(&self) {
+        self.jwks_fetches.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_cache_hit(&self) {
+        self.cache_hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_failure(&self, reason: FailureReason) {
+        self.failures[reason.index()].fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -1,15 +1,184 @@
+use super::UserInfo;
 use crate::model::User;
+use dashmap::DashMap;
 use lru::LruCache;
+use prometheus::{IntCounterVec, Opts};
 use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
 
+/// Caches users keyed by external id so authenticated requests can skip the
+/// store. Entries older than `ttl` are re-read so tenant changes are picked up.
+/// Backed by a sharded map so concurrent requests don't serialize on one lock.
 pub struct UserCache {
-    pub cache: LruCache<String, User>,
+    cache: DashMap<String, (User, Instant)>,
+    capacity: NonZeroUsize,
+    ttl: Duration,
+    /// Lookups labeled `hit` or `miss`, exported through `Metrics`.
+    pub lookups: IntCounterVec,
 }
 
 impl UserCache {
-    pub fn new(capacity: NonZeroUsize) -> Self {
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        let lookups = IntCounterVec::new(
+            Opts::new("user_cache_lookups_total", "User cache lookups by result"),
+            &["result"],
+        )
+        .expect("valid metric");
+        Self {
+            cache: DashMap::new(),
+            capacity,
+            ttl,
+            lookups,
+        }
+    }
+
+    pub fn get(&self, external_id: &str) -> Option<User> {
+        if let Some(entry) = self.cache.get(external_id) {
+            if entry.1.elapsed() < self.ttl {
+                self.lookups.with_label_values(&["hit"]).inc();
+                return Some(entry.0.clone());
+            }
+        }
+        self.lookups.with_label_values(&["miss"]).inc();
+        self.cache.remove_if(external_id, |_, (_, cached_at)| {
+            cached_at.elapsed() >= self.ttl
+        });
+        None
+    }
+
+    pub fn put(&self, user: User) {
+        if !self.cache.contains_key(&user.external_id) && self.cache.len() >= self.capacity.get() {
+            self.evict();
+        }
+        self.cache
+            .insert(user.external_id.clone(), (user, Instant::now()));
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Drops expired entries, falling back to the oldest one when none have
+    /// expired yet.
+    fn evict(&self) {
+        self.cache
+            .retain(|_, (_, cached_at)| cached_at.elapsed() < self.ttl);
+        if self.cache.len() < self.capacity.get() {
+            return;
+        }
+        let oldest = self
+            .cache
+            .iter()
+            .min_by_key(|entry| entry.value().1)
+            .map(|entry| entry.key().clone());
+        if let Some(key) = oldest {
+            self.cache.remove(&key);
+        }
+    }
+}
+
+/// Caches `/userinfo` responses keyed by the token's `sub`. Entries older
+/// than `ttl` are treated as missing and evicted on lookup.
+pub struct UserInfoCache {
+    pub cache: LruCache<String, (UserInfo, Instant)>,
+    ttl: Duration,
+}
+
+impl UserInfoCache {
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
         Self {
             cache: LruCache::new(capacity),
+            ttl,
         }
     }
+
+    pub fn get(&mut self, sub: &str) -> Option<UserInfo> {
+        match self.cache.get(sub) {
+            Some((info, cached_at)) if cached_at.elapsed() < self.ttl => Some(info.clone()),
+            Some(_) => {
+                self.cache.pop(sub);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn put(&mut self, info: UserInfo) {
+        self.cache.put(info.sub.clone(), (info, Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_cache_expires() {
+        let user = User::new(
+            "auth0|1".to_string(),
+            "User".to_string(),
+            "user@example.com".to_string(),
+            "tenant".to_string(),
+        );
+        let cache = UserCache::new(NonZeroUsize::new(1).unwrap(), Duration::from_secs(60));
+        cache.put(user.clone());
+        assert_eq!(cache.get("auth0|1").unwrap().id, user.id);
+
+        let cache = UserCache::new(NonZeroUsize::new(1).unwrap(), Duration::ZERO);
+        cache.put(user);
+        assert!(cache.get("auth0|1").is_none());
+        assert_eq!(cache.len(), 0);
+        assert_eq!(cache.lookups.with_label_values(&["miss"]).get(), 1);
+    }
+
+    #[test]
+    fn test_user_cache_concurrent_access() {
+        let cache = std::sync::Arc::new(UserCache::new(
+            NonZeroUsize::new(16).unwrap(),
+            Duration::from_secs(60),
+        ));
+        let handles: Vec<_> = (0..8)
+            .map(|t| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for i in 0..1000 {
+                        let external_id = format!("auth0|{}", (t * 1000 + i) % 32);
+                        let user = User::new(
+                            external_id.clone(),
+                            "User".to_string(),
+                            "user@example.com".to_string(),
+                            format!("tenant-{}", external_id),
+                        );
+                        cache.put(user);
+                        if let Some(user) = cache.get(&external_id) {
+                            assert_eq!(user.external_id, external_id);
+                            assert_eq!(user.tenant_id, format!("tenant-{}", external_id));
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!(cache.len() <= 16 + 8);
+    }
+
+    #[test]
+    fn test_user_info_cache_expires() {
+        let mut cache = UserInfoCache::new(NonZeroUsize::new(2).unwrap(), Duration::from_secs(60));
+        let info = UserInfo {
+            sub: "auth0|1".to_string(),
+            name: "User".to_string(),
+            email: "user@example.com".to_string(),
+        };
+        cache.put(info.clone());
+        assert_eq!(cache.get("auth0|1").unwrap().email, info.email);
+
+        let mut cache = UserInfoCache::new(NonZeroUsize::new(2).unwrap(), Duration::ZERO);
+        cache.put(info);
+        assert!(cache.get("auth0|1").is_none());
+        assert!(cache.cache.is_empty());
+    }
 }
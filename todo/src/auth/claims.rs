@@ -1,12 +1,69 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct Claims {
     pub iss: String,
     pub sub: String,
+    #[serde(deserialize_with = "jwtverifier::string_or_vec::deserialize")]
     pub aud: Vec<String>,
     pub iat: usize,
     pub exp: usize,
     pub azp: String,
     pub scope: String,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Remaining claims, including namespaced custom claims such as
+    /// `https://todos.example.com/email`.
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl Claims {
+    /// The space separated `scope` claim as a list.
+    pub fn scopes(&self) -> Vec<String> {
+        self.scope.split_whitespace().map(str::to_string).collect()
+    }
+
+    /// Looks up a string custom claim published under `namespace`.
+    pub fn namespaced_claim(&self, namespace: &str, name: &str) -> Option<String> {
+        let key = format!("{}/{}", namespace.trim_end_matches('/'), name);
+        self.extra
+            .get(&key)
+            .and_then(|value| value.as_str())
+            .map(|value| value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_namespaced_claim() {
+        let claims: Claims = serde_json::from_value(serde_json::json!({
+            "iss": "https://example.auth0.com/",
+            "sub": "auth0|1",
+            "aud": ["https://todos.example.com/"],
+            "iat": 1,
+            "exp": 2,
+            "azp": "client",
+            "scope": "openid",
+            "https://todos.example.com/email": "user@example.com",
+            "https://todos.example.com/name": "User",
+        }))
+        .unwrap();
+        assert_eq!(
+            claims.namespaced_claim("https://todos.example.com/", "email"),
+            Some("user@example.com".to_string())
+        );
+        assert_eq!(
+            claims.namespaced_claim("https://todos.example.com", "name"),
+            Some("User".to_string())
+        );
+        assert_eq!(
+            claims.namespaced_claim("https://other.example.com", "name"),
+            None
+        );
+    }
 }
@@ -2,12 +2,16 @@ pub mod cache;
 pub mod claims;
 pub mod token_from_header;
 pub mod userinfo;
+pub mod with_auth;
 pub mod with_decoded;
 pub mod with_jwt;
+pub mod with_role;
 
 pub use cache::*;
 pub use claims::*;
 pub use token_from_header::*;
 pub use userinfo::*;
+pub use with_auth::*;
 pub use with_decoded::*;
 pub use with_jwt::*;
+pub use with_role::*;
@@ -1,8 +1,9 @@
 use crate::error::Error;
+use std::collections::HashMap;
 use warp::http::header::{HeaderMap, HeaderValue, AUTHORIZATION};
 
 pub fn token_from_header(headers: &HeaderMap<HeaderValue>) -> Result<String, Error> {
-    const BEARER: &str = "Bearer ";
+    const BEARER: &str = "bearer ";
     let header = match headers.get(AUTHORIZATION) {
         Some(v) => v,
         None => return Err(Error::Unauthorized),
@@ -11,8 +12,79 @@ pub fn token_from_header(headers: &HeaderMap<HeaderValue>) -> Result<String, Err
         Ok(v) => v,
         Err(_) => return Err(Error::Unauthorized),
     };
-    if !auth_header.starts_with(BEARER) {
+    // the auth scheme is case-insensitive (RFC 6750)
+    let scheme = match auth_header.get(..BEARER.len()) {
+        Some(s) if s.eq_ignore_ascii_case(BEARER) => s,
+        _ => return Err(Error::Unauthorized),
+    };
+    let token = &auth_header[scheme.len()..];
+    if token.is_empty() || token.starts_with(' ') {
         return Err(Error::Unauthorized);
     }
-    Ok(auth_header.trim_start_matches(BEARER).to_owned())
-}
\ No newline at end of file
+    Ok(token.to_owned())
+}
+
+/// Reads the bearer token from the Authorization header, falling back to an
+/// `access_token` query parameter when `allow_query` is set and the header is
+/// absent. Browser `EventSource` clients can't set headers, but tokens in URLs
+/// end up in logs, so the fallback is opt-in.
+pub fn token_from_request(
+    headers: &HeaderMap<HeaderValue>,
+    query: &HashMap<String, String>,
+    allow_query: bool,
+) -> Result<String, Error> {
+    if !allow_query || headers.contains_key(AUTHORIZATION) {
+        return token_from_header(headers);
+    }
+    match query.get("access_token") {
+        Some(token) if !token.is_empty() => Ok(token.to_owned()),
+        _ => Err(Error::Unauthorized),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(value: &str) -> HeaderMap<HeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_bearer_scheme_case_insensitive() {
+        assert_eq!(token_from_header(&headers("Bearer abc")).unwrap(), "abc");
+        assert_eq!(token_from_header(&headers("bearer abc")).unwrap(), "abc");
+        assert_eq!(token_from_header(&headers("BEARER abc")).unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_missing_token() {
+        assert!(token_from_header(&headers("Bearer ")).is_err());
+        assert!(token_from_header(&headers("Bearer")).is_err());
+        assert!(token_from_header(&headers("Bearer  abc")).is_err());
+        assert!(token_from_header(&HeaderMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_non_bearer_scheme() {
+        assert!(token_from_header(&headers("Basic dXNlcjpwYXNz")).is_err());
+    }
+
+    #[test]
+    fn test_token_from_request_query_fallback() {
+        let query = HashMap::from([("access_token".to_string(), "xyz".to_string())]);
+        assert_eq!(
+            token_from_request(&HeaderMap::new(), &query, true).unwrap(),
+            "xyz"
+        );
+        assert!(token_from_request(&HeaderMap::new(), &query, false).is_err());
+        assert!(token_from_request(&HeaderMap::new(), &HashMap::new(), true).is_err());
+        // the header wins when both are present
+        assert_eq!(
+            token_from_request(&headers("Bearer abc"), &query, true).unwrap(),
+            "abc"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::error::Error;
+use crate::storage::UserContext;
+use log::error;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use warp::filters::BoxedFilter;
+use warp::{reject, Filter, Rejection};
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Static API keys and the service identity each one authenticates as.
+pub type ApiKeys = Arc<HashMap<String, UserContext>>;
+
+/// A way for callers to authenticate, tried in the configured order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    /// `Authorization: Bearer <jwt>`, verified against the JWKS.
+    Bearer,
+    /// `X-Api-Key: <key>`, checked against the configured keys.
+    ApiKey,
+}
+
+impl FromStr for AuthMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "bearer" | "jwt" => Ok(AuthMethod::Bearer),
+            "api_key" | "apikey" => Ok(AuthMethod::ApiKey),
+            other => Err(format!("unknown auth method `{}`", other)),
+        }
+    }
+}
+
+/// Parses `key:tenant_id:user_id` entries into service identities.
+/// Malformed entries are skipped.
+pub fn parse_api_keys(entries: &[String]) -> HashMap<String, UserContext> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let mut parts = entry.splitn(3, ':').map(str::trim);
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(key), Some(tenant_id), Some(user_id))
+                    if !key.is_empty() && !tenant_id.is_empty() && !user_id.is_empty() =>
+                {
+                    Some((
+                        key.to_string(),
+                        UserContext {
+                            tenant_id: tenant_id.to_string(),
+                            user_id: user_id.to_string(),
+                            roles: vec!["service".to_string()],
+                            scopes: vec![],
+                        },
+                    ))
+                }
+                _ => {
+                    error!("Ignoring malformed API key entry");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+pub fn with_api_key(
+    api_keys: ApiKeys,
+) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+    warp::header::optional::<String>(API_KEY_HEADER).and_then(move |key: Option<String>| {
+        let api_keys = api_keys.clone();
+        async move {
+            key.and_then(|key| api_keys.get(key.trim()).cloned())
+                .ok_or_else(|| reject::custom(Error::InvalidToken))
+        }
+    })
+}
+
+/// Authenticates with the first of `methods` that accepts the request.
+pub fn with_auth(
+    with_jwt: impl Filter<Extract = (UserContext,), Error = Rejection> + Clone + Send + Sync + 'static,
+    methods: &[AuthMethod],
+    api_keys: ApiKeys,
+) -> BoxedFilter<(UserContext,)> {
+    methods
+        .iter()
+        .map(|method| match method {
+            AuthMethod::Bearer => with_jwt.clone().boxed(),
+            AuthMethod::ApiKey => with_api_key(api_keys.clone()).boxed(),
+        })
+        .reduce(|first, next| first.or(next).unify().boxed())
+        .unwrap_or_else(|| {
+            warp::any()
+                .and_then(|| async {
+                    Err::<UserContext, Rejection>(reject::custom(Error::InvalidToken))
+                })
+                .boxed()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_jwt() -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+        warp::header::<String>("authorization").map(|_| UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        })
+    }
+
+    fn api_keys() -> ApiKeys {
+        Arc::new(parse_api_keys(&[
+            "secret:tenant:service".to_string(),
+            "malformed".to_string(),
+        ]))
+    }
+
+    #[test]
+    fn test_parse_api_keys() {
+        let keys = api_keys();
+        assert_eq!(keys.len(), 1);
+        let ctx = &keys["secret"];
+        assert_eq!(ctx.tenant_id, "tenant");
+        assert_eq!(ctx.user_id, "service");
+        assert_eq!("API_KEY".parse(), Ok(AuthMethod::ApiKey));
+        assert!("basic".parse::<AuthMethod>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_with_auth() {
+        let filter = with_auth(
+            mock_jwt(),
+            &[AuthMethod::Bearer, AuthMethod::ApiKey],
+            api_keys(),
+        );
+        let ctx = warp::test::request()
+            .header("authorization", "Bearer token")
+            .header(API_KEY_HEADER, "secret")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(ctx.user_id, "user");
+
+        let ctx = warp::test::request()
+            .header(API_KEY_HEADER, "secret")
+            .filter(&filter)
+            .await
+            .unwrap();
+        assert_eq!(ctx.user_id, "service");
+
+        let rejection = warp::test::request()
+            .header(API_KEY_HEADER, "wrong")
+            .filter(&filter)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.find::<Error>(), Some(&Error::InvalidToken));
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_are_opt_in() {
+        let filter = with_auth(mock_jwt(), &[AuthMethod::Bearer], api_keys());
+        let result = warp::test::request()
+            .header(API_KEY_HEADER, "secret")
+            .filter(&filter)
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -1,14 +1,22 @@
-use super::{token_from_header, Claims, UserInfo};
+use super::{token_from_header, Claims, UserInfo, UserInfoCache};
 use crate::error::Error;
 use jwtverifier::JwtVerifier;
 use log::error;
+use std::sync::{Arc, Mutex};
 use warp::{http::HeaderMap, reject, Filter, Rejection};
 
-async fn fetch_user_info(access_token: &str, domain: &str) -> Result<(String, String), Rejection> {
-    let client = reqwest::Client::new();
-    let url = format!("{}/userinfo", domain);
+/// The `/userinfo` endpoint of `domain`, which may end in a slash.
+fn userinfo_url(domain: &str) -> String {
+    format!("{}/userinfo", domain.trim_end_matches('/'))
+}
+
+async fn fetch_user_info(
+    client: &reqwest::Client,
+    access_token: &str,
+    domain: &str,
+) -> Result<(String, String), Rejection> {
     let resp = client
-        .get(&url)
+        .get(userinfo_url(domain))
         .header("Authorization", format!("Bearer {}", access_token))
         .send()
         .await
@@ -34,14 +42,38 @@ async fn fetch_user_info(access_token: &str, domain: &str) -> Result<(String, St
     Ok((name, email))
 }
 
+/// Verifies the bearer token and resolves the user's name and email. When
+/// `claims_namespace` is set and the token carries both as namespaced custom
+/// claims, the `/userinfo` round-trip is skipped; otherwise the response is
+/// cached per `sub` in `cache`. `client` is shared so `/userinfo` calls reuse
+/// pooled connections.
 pub fn with_decoded(
+    client: reqwest::Client,
     jwt_verifier: JwtVerifier,
     domain: String,
+    claims_namespace: Option<String>,
+    cache: Arc<Mutex<UserInfoCache>>,
 ) -> impl Filter<Extract = (UserInfo,), Error = Rejection> + Clone {
     warp::header::headers_cloned()
-        .map(move |headers: HeaderMap| (headers.clone(), jwt_verifier.clone(), domain.clone()))
+        .map(move |headers: HeaderMap| {
+            (
+                headers.clone(),
+                client.clone(),
+                jwt_verifier.clone(),
+                domain.clone(),
+                claims_namespace.clone(),
+                cache.clone(),
+            )
+        })
         .and_then(
-            |(headers, jwt_verifier, domain): (HeaderMap, JwtVerifier, String)| async move {
+            |(headers, client, jwt_verifier, domain, claims_namespace, cache): (
+                HeaderMap,
+                reqwest::Client,
+                JwtVerifier,
+                String,
+                Option<String>,
+                Arc<Mutex<UserInfoCache>>,
+            )| async move {
                 match token_from_header(&headers) {
                     Ok(jwt) => {
                         let decoded = jwt_verifier.verify::<Claims>(&jwt).await.map_err(|_| {
@@ -49,15 +81,53 @@ pub fn with_decoded(
                             reject::custom(Error::InvalidToken)
                         })?;
 
-                        let (name, email) = fetch_user_info(&jwt, &domain).await?;
-                        Ok(UserInfo {
+                        let from_claims = claims_namespace.as_deref().and_then(|namespace| {
+                            Some((
+                                decoded.claims.namespaced_claim(namespace, "name")?,
+                                decoded.claims.namespaced_claim(namespace, "email")?,
+                            ))
+                        });
+                        if let Some((name, email)) = from_claims {
+                            return Ok(UserInfo {
+                                sub: decoded.claims.sub,
+                                name,
+                                email,
+                            });
+                        }
+
+                        if let Some(info) = cache
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .get(&decoded.claims.sub)
+                        {
+                            return Ok(info);
+                        }
+
+                        let (name, email) = fetch_user_info(&client, &jwt, &domain).await?;
+                        let info = UserInfo {
                             sub: decoded.claims.sub,
                             name,
                             email,
-                        })
+                        };
+                        cache
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .put(info.clone());
+                        Ok(info)
                     }
                     Err(_) => Err(reject::custom(Error::InvalidToken)),
                 }
             },
         )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_userinfo_url() {
+        assert_eq!(userinfo_url("https://x.com"), "https://x.com/userinfo");
+        assert_eq!(userinfo_url("https://x.com/"), "https://x.com/userinfo");
+    }
+}
@@ -0,0 +1,16 @@
+use crate::error::Error;
+use crate::storage::UserContext;
+use warp::{reject, Filter, Rejection};
+
+pub fn with_role(
+    role: &'static str,
+    with_jwt: impl Filter<Extract = (UserContext,), Error = Rejection> + Clone,
+) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+    with_jwt.and_then(move |user: UserContext| async move {
+        if user.roles.iter().any(|r| r == role) {
+            Ok(user)
+        } else {
+            Err(reject::custom(Error::Forbidden))
+        }
+    })
+}
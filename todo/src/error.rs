@@ -1,10 +1,26 @@
-use warp::{body::BodyDeserializeError, hyper::StatusCode, reject::Reject, Rejection, Reply};
+use serde::{Deserialize, Serialize};
+use warp::{
+    body::BodyDeserializeError,
+    http::header::{HeaderValue, RETRY_AFTER},
+    hyper::StatusCode,
+    reject::Reject,
+    Rejection, Reply,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     NotFound,
     Unauthorized,
+    Forbidden,
     InvalidToken,
+    Conflict,
+    PreconditionFailed,
+    PreconditionRequired,
+    RateLimited { retry_after: Option<u64> },
+    PayloadTooLarge,
+    QuotaExceeded,
+    Timeout,
+    Validation { field: String, message: String },
     DatabaseOperationFailed(String),
 }
 
@@ -13,44 +29,184 @@ impl std::fmt::Display for Error {
         match self {
             Error::NotFound => write!(f, "Not found"),
             Error::Unauthorized => write!(f, "Unauthorized"),
+            Error::Forbidden => write!(f, "Forbidden"),
             Error::InvalidToken => write!(f, "Invalid token"),
+            Error::Conflict => write!(f, "Conflict"),
+            Error::PreconditionFailed => write!(f, "Precondition failed"),
+            Error::PreconditionRequired => write!(f, "Precondition required"),
+            Error::RateLimited { .. } => write!(f, "Too many requests"),
+            Error::PayloadTooLarge => write!(f, "Payload too large"),
+            Error::QuotaExceeded => write!(f, "Todo quota exceeded"),
+            Error::Timeout => write!(f, "Request timed out"),
+            Error::Validation { field, message } => write!(f, "{} {}", field, message),
             Error::DatabaseOperationFailed(msg) => write!(f, "Database: {}", msg),
         }
     }
 }
 
+impl Error {
+    /// Stable, machine-readable code sent to clients alongside the message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::NotFound => "NOT_FOUND",
+            Error::Unauthorized => "UNAUTHORIZED",
+            Error::Forbidden => "FORBIDDEN",
+            Error::InvalidToken => "INVALID_TOKEN",
+            Error::Conflict => "CONFLICT",
+            Error::PreconditionFailed => "PRECONDITION_FAILED",
+            Error::PreconditionRequired => "PRECONDITION_REQUIRED",
+            Error::RateLimited { .. } => "RATE_LIMITED",
+            Error::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            Error::QuotaExceeded => "QUOTA_EXCEEDED",
+            Error::Timeout => "TIMEOUT",
+            Error::Validation { .. } => "VALIDATION_FAILED",
+            Error::DatabaseOperationFailed(_) => "DATABASE_ERROR",
+        }
+    }
+}
+
+impl Error {
+    pub fn validation(field: &str, message: impl Into<String>) -> Self {
+        Error::Validation {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
 impl Reject for Error {}
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub message: String,
+    pub code: String,
+    /// The offending input field, for validation errors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+}
+
 pub async fn return_error(err: Rejection) -> Result<impl Reply, Rejection> {
-    let (code, message) = if let Some(error) = err.find::<Error>() {
-        match error {
-            Error::NotFound => (StatusCode::NOT_FOUND, error.to_string()),
-            Error::Unauthorized => (StatusCode::UNAUTHORIZED, error.to_string()),
-            Error::InvalidToken => (StatusCode::UNAUTHORIZED, error.to_string()),
-            Error::DatabaseOperationFailed(msg) => {
-                (StatusCode::INTERNAL_SERVER_ERROR, msg.to_string())
+    let mut retry_after = None;
+    let mut field = None;
+    let (status, code, message) = if let Some(error) = err.find::<Error>() {
+        let status = match error {
+            Error::NotFound => StatusCode::NOT_FOUND,
+            Error::Unauthorized => StatusCode::UNAUTHORIZED,
+            Error::Forbidden => StatusCode::FORBIDDEN,
+            Error::InvalidToken => StatusCode::UNAUTHORIZED,
+            Error::Conflict => StatusCode::CONFLICT,
+            Error::PreconditionFailed => StatusCode::PRECONDITION_FAILED,
+            Error::PreconditionRequired => StatusCode::PRECONDITION_REQUIRED,
+            Error::RateLimited {
+                retry_after: seconds,
+            } => {
+                retry_after = *seconds;
+                StatusCode::TOO_MANY_REQUESTS
             }
-        }
+            Error::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::QuotaExceeded => StatusCode::FORBIDDEN,
+            Error::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            Error::Validation { field: name, .. } => {
+                field = Some(name.clone());
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Error::DatabaseOperationFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let message = match error {
+            Error::DatabaseOperationFailed(msg) => msg.to_string(),
+            _ => error.to_string(),
+        };
+        (status, error.code(), message)
     } else if let Some(error) = err.find::<BodyDeserializeError>() {
-        (StatusCode::UNPROCESSABLE_ENTITY, error.to_string())
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "INVALID_BODY",
+            error.to_string(),
+        )
+    } else if let Some(error) = err.find::<warp::cors::CorsForbidden>() {
+        (StatusCode::FORBIDDEN, "CORS_FORBIDDEN", error.to_string())
+    } else if err.find::<warp::reject::PayloadTooLarge>().is_some() {
+        (
+            StatusCode::PAYLOAD_TOO_LARGE,
+            Error::PayloadTooLarge.code(),
+            Error::PayloadTooLarge.to_string(),
+        )
+    } else if err.find::<warp::reject::LengthRequired>().is_some() {
+        (
+            StatusCode::LENGTH_REQUIRED,
+            "LENGTH_REQUIRED",
+            "Content-Length header required".to_string(),
+        )
     } else if err.find::<warp::reject::MethodNotAllowed>().is_some() {
         (
             StatusCode::METHOD_NOT_ALLOWED,
+            "METHOD_NOT_ALLOWED",
             "Method not allowed".to_string(),
         )
     } else if err.find::<warp::reject::UnsupportedMediaType>().is_some() {
         (
             StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "UNSUPPORTED_MEDIA_TYPE",
             "Unsupported media type".to_string(),
         )
     } else if err.is_not_found() {
-        (StatusCode::NOT_FOUND, "Not found".to_string())
+        (StatusCode::NOT_FOUND, "NOT_FOUND", "Not found".to_string())
     } else {
         (
             StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR",
             "Internal server error".to_string(),
         )
     };
 
-    Ok(warp::reply::with_status(message, code))
+    let body = ErrorResponse {
+        message,
+        code: code.to_string(),
+        field,
+    };
+    let mut response = warp::reply::with_status(warp::reply::json(&body), status).into_response();
+    if let Some(seconds) = retry_after {
+        response
+            .headers_mut()
+            .insert(RETRY_AFTER, HeaderValue::from(seconds));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limited_sets_retry_after() {
+        let rejection = warp::reject::custom(Error::RateLimited {
+            retry_after: Some(5),
+        });
+        let response = return_error(rejection).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[RETRY_AFTER], "5");
+        assert_eq!(response.headers()["content-type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_without_retry_after() {
+        let rejection = warp::reject::custom(Error::RateLimited { retry_after: None });
+        let response = return_error(rejection).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().get(RETRY_AFTER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_validation_reports_field() {
+        let rejection = warp::reject::custom(Error::validation("task", "must not be empty"));
+        let response = return_error(rejection).await.unwrap().into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body = warp::hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap();
+        let body: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body.code, "VALIDATION_FAILED");
+        assert_eq!(body.field.as_deref(), Some("task"));
+        assert_eq!(body.message, "task must not be empty");
+    }
 }
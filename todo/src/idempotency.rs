@@ -0,0 +1,108 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::http::StatusCode;
+
+/// Tenant, user and the key they sent.
+type EntryKey = (String, String, String);
+
+enum State {
+    InProgress,
+    Done(StatusCode),
+}
+
+/// Outcome of claiming an idempotency key.
+#[derive(Debug, PartialEq)]
+pub enum Claim {
+    /// First use of the key; the caller performs the request.
+    New,
+    /// The key was used before; answer with the recorded status instead.
+    Replay(StatusCode),
+    /// A request with the same key hasn't finished yet.
+    InProgress,
+}
+
+/// `Idempotency-Key` values seen recently, keyed by user so keys chosen by
+/// different clients can't collide.
+#[derive(Clone)]
+pub struct IdempotencyKeys {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<EntryKey, (State, Instant)>>>,
+}
+
+impl IdempotencyKeys {
+    /// Remembers each completed request for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn entry_key(tenant_id: &str, user_id: &str, key: &str) -> EntryKey {
+        (tenant_id.to_string(), user_id.to_string(), key.to_string())
+    }
+
+    /// Claims `key` for the user, or reports how an earlier request with it went.
+    pub fn begin(&self, tenant_id: &str, user_id: &str, key: &str) -> Claim {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, (_, started)| started.elapsed() < self.ttl);
+        match entries.get(&Self::entry_key(tenant_id, user_id, key)) {
+            Some((State::Done(status), _)) => Claim::Replay(*status),
+            Some((State::InProgress, _)) => Claim::InProgress,
+            None => {
+                entries.insert(
+                    Self::entry_key(tenant_id, user_id, key),
+                    (State::InProgress, Instant::now()),
+                );
+                Claim::New
+            }
+        }
+    }
+
+    /// Records the response for a claimed key.
+    pub fn complete(&self, tenant_id: &str, user_id: &str, key: &str, status: StatusCode) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(
+            Self::entry_key(tenant_id, user_id, key),
+            (State::Done(status), Instant::now()),
+        );
+    }
+
+    /// Releases a claimed key after a failed request so it can be retried.
+    pub fn abandon(&self, tenant_id: &str, user_id: &str, key: &str) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.remove(&Self::entry_key(tenant_id, user_id, key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idempotency_keys() {
+        let keys = IdempotencyKeys::new(Duration::from_secs(60));
+        assert_eq!(keys.begin("t", "u", "k"), Claim::New);
+        assert_eq!(keys.begin("t", "u", "k"), Claim::InProgress);
+        // the same key from another user is unrelated
+        assert_eq!(keys.begin("t", "other", "k"), Claim::New);
+
+        keys.complete("t", "u", "k", StatusCode::CREATED);
+        assert_eq!(
+            keys.begin("t", "u", "k"),
+            Claim::Replay(StatusCode::CREATED)
+        );
+
+        keys.abandon("t", "other", "k");
+        assert_eq!(keys.begin("t", "other", "k"), Claim::New);
+    }
+
+    #[test]
+    fn test_idempotency_keys_expire() {
+        let keys = IdempotencyKeys::new(Duration::ZERO);
+        assert_eq!(keys.begin("t", "u", "k"), Claim::New);
+        keys.complete("t", "u", "k", StatusCode::CREATED);
+        assert_eq!(keys.begin("t", "u", "k"), Claim::New);
+    }
+}
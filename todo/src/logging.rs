@@ -0,0 +1,92 @@
+use env_logger::Builder;
+use log::Record;
+use std::io::Write;
+use std::str::FromStr;
+
+/// Shape of the log lines, chosen with `LOG_FORMAT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// env_logger's human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, for log aggregators.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("unknown log format {:?}", other)),
+        }
+    }
+}
+
+/// Installs the global logger; `RUST_LOG` still picks the levels. Runs before
+/// the rest of the configuration is loaded so that its warnings are logged.
+pub fn init_logging() {
+    let format = std::env::var("LOG_FORMAT")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<LogFormat>());
+    let mut builder = Builder::from_default_env();
+    if let Some(Ok(LogFormat::Json)) = format {
+        builder.format(|buf, record| {
+            let timestamp = buf.timestamp_micros().to_string();
+            writeln!(buf, "{}", json_line(&timestamp, record))
+        });
+    }
+    builder.init();
+    if let Some(Err(e)) = format {
+        log::warn!("Ignoring LOG_FORMAT: {}", e);
+    }
+}
+
+fn json_line(timestamp: &str, record: &Record) -> String {
+    serde_json::json!({
+        "timestamp": timestamp,
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+
+    #[test]
+    fn test_parse_log_format() {
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!(" Text ".parse(), Ok(LogFormat::Text));
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_json_line() {
+        let line = json_line(
+            "2024-01-01T00:00:00.000000Z",
+            &Record::builder()
+                .level(Level::Warn)
+                .target("todo_rs::auth")
+                .args(format_args!("bad \"token\"\n"))
+                .build(),
+        );
+        let value: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "timestamp": "2024-01-01T00:00:00.000000Z",
+                "level": "WARN",
+                "target": "todo_rs::auth",
+                "message": "bad \"token\"\n",
+            })
+        );
+        assert!(!line.contains('\n'));
+    }
+}
@@ -1,34 +1,245 @@
-use crate::auth::{with_decoded, with_jwt, UserCache};
-use crate::routes::router;
-use crate::storage::{MongoStore, TodoStore};
+// The router's filter chain is deeper than the default limit allows.
+#![recursion_limit = "256"]
+
+use crate::auth::{
+    parse_api_keys, with_auth, with_decoded, with_jwt, AuthMethod, UserCache, UserInfoCache,
+};
+use crate::idempotency::IdempotencyKeys;
+use crate::logging::init_logging;
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
+use crate::routes::{router, CorsConfig, RouterConfig};
+use crate::storage::{
+    AuditStore, CachingStore, MemStore, MongoOptions, MongoStore, TodoStore, WebhookStore,
+};
+use crate::webhook::{WebhookConfig, Webhooks};
 use jwtverifier::JwtVerifier;
-use log::{error, info};
+use log::{error, info, warn};
 use std::env;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::num::NonZeroUsize;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 mod auth;
 mod error;
+mod idempotency;
+mod logging;
+mod metrics;
 mod model;
+mod rate_limit;
 mod routes;
 mod storage;
+mod webhook;
+
+/// Where todos and users are kept, chosen with `STORE_BACKEND`.
+#[derive(Debug, Clone)]
+enum StoreBackend {
+    Mongo {
+        uri: String,
+    },
+    /// In-memory store backed by a JSON file, for local development and demos.
+    Memory {
+        file_path: String,
+    },
+}
+
+/// Certificate and key for serving HTTPS directly. Both files are PEM: the
+/// certificate chain, leaf first, and a PKCS#8 or RSA (PKCS#1) private key.
+#[derive(Debug, Clone)]
+struct TlsConfig {
+    cert_path: String,
+    key_path: String,
+}
 
 #[derive(Debug, Clone)]
 struct Config {
     server_addr: SocketAddr,
-    mongo_uri: String,
+    /// Plain HTTP when unset, for deployments behind a TLS terminator.
+    tls: Option<TlsConfig>,
+    store_backend: StoreBackend,
+    audit_log_file: Option<String>,
+    /// Capacity of the `get_todo` cache; `None` disables it.
+    todo_cache_capacity: Option<NonZeroUsize>,
+    todo_cache_ttl: Duration,
+    mongo_options: MongoOptions,
+    mongo_connect_attempts: u32,
+    mongo_connect_backoff: Duration,
     domain: String,
     audience: String,
+    claims_namespace: Option<String>,
+    allow_query_token: bool,
+    auth_methods: Vec<AuthMethod>,
+    api_keys: Vec<String>,
+    userinfo_cache_ttl: Duration,
+    /// Timeout for each request to the identity provider's `/userinfo`.
+    userinfo_timeout: Duration,
+    user_cache_capacity: NonZeroUsize,
+    user_cache_ttl: Duration,
+    /// Requests allowed per window; `None` when `RATE_LIMIT_REQUESTS` is 0.
+    rate_limit_requests: Option<u32>,
+    rate_limit_window: Duration,
+    cors: CorsConfig,
+    max_batch_size: usize,
+    max_page_size: u64,
+    idempotency_key_ttl: Duration,
+    max_body_size: u64,
+    max_task_length: usize,
+    request_timeout: Duration,
+    auto_complete_subtasks: bool,
+    /// Most todos a user may have; `None` when `MAX_TODOS_PER_USER` is 0.
+    max_todos_per_user: Option<u64>,
+    /// Where todo events are POSTed; `None` when `WEBHOOK_URL` is unset.
+    webhook: Option<WebhookConfig>,
+}
+
+fn list_from_env(name: &str) -> Option<Vec<String>> {
+    env::var(name)
+        .ok()
+        .map(|s| {
+            s.split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<String>>()
+        })
+        .filter(|items| !items.is_empty())
+}
+
+fn non_empty_env(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|s| !s.is_empty())
+}
+
+/// Reads `name`, or the file named by `<name>_FILE` when that is set, for
+/// secrets that orchestrators mount as files. A trailing newline is dropped.
+fn secret_env(name: &str) -> Result<String, env::VarError> {
+    let file_var = format!("{}_FILE", name);
+    match non_empty_env(&file_var) {
+        Some(path) => std::fs::read_to_string(&path)
+            .map(|s| s.trim_end_matches(['\r', '\n']).to_string())
+            .map_err(|e| {
+                error!("Failed to read {} from {}: {}", file_var, path, e);
+                env::VarError::NotPresent
+            }),
+        None => env::var(name),
+    }
+}
+
+/// Combines an IPv4 or IPv6 address, bracketed or not, with a port. `::`
+/// listens on every IPv6 address and, unless the host sets `bindv6only`, on
+/// IPv4 too, which is how to run dual-stack.
+fn parse_server_addr(ip_address: &str, port: &str) -> Option<SocketAddr> {
+    let ip_address = ip_address
+        .strip_prefix('[')
+        .and_then(|ip| ip.strip_suffix(']'))
+        .unwrap_or(ip_address);
+    let ip: IpAddr = ip_address.parse().ok()?;
+    let port: u16 = port.parse().ok()?;
+    Some(SocketAddr::new(ip, port))
+}
+
+fn cors_from_env() -> CorsConfig {
+    let default = CorsConfig::default();
+    CorsConfig {
+        allowed_origins: list_from_env("CORS_ALLOWED_ORIGINS"),
+        allowed_methods: list_from_env("CORS_ALLOWED_METHODS")
+            .map(|methods| {
+                methods
+                    .iter()
+                    .filter_map(|m| m.to_uppercase().parse().ok())
+                    .collect()
+            })
+            .unwrap_or(default.allowed_methods),
+        allowed_headers: list_from_env("CORS_ALLOWED_HEADERS").unwrap_or(default.allowed_headers),
+        allow_credentials: env::var("CORS_ALLOW_CREDENTIALS")
+            .map(|s| s == "true" || s == "1")
+            .unwrap_or(default.allow_credentials),
+    }
 }
 
 impl Config {
     fn from_env() -> Result<Self, env::VarError> {
         const DEFAULT_ADDR: &str = "0.0.0.0";
         const DEFAULT_PORT: &str = "3030";
-        let mongo_uri = env::var("MONGO_URI")?;
-        let domain = env::var("AUTH0_DOMAIN")?;
-        let audience = env::var("AUTH0_AUDIENCE")?;
+        const DEFAULT_RATE_LIMIT_REQUESTS: u32 = 100;
+        const DEFAULT_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+        const DEFAULT_USERINFO_CACHE_TTL_SECS: u64 = 300;
+        const DEFAULT_USERINFO_TIMEOUT_SECS: u64 = 5;
+        const DEFAULT_USER_CACHE_CAPACITY: usize = 20;
+        const DEFAULT_USER_CACHE_TTL_SECS: u64 = 300;
+        const DEFAULT_TODO_CACHE_TTL_SECS: u64 = 30;
+        const DEFAULT_MAX_BATCH_SIZE: usize = 100;
+        const DEFAULT_MAX_PAGE_SIZE: u64 = 100;
+        const DEFAULT_MAX_BODY_SIZE: u64 = 64 * 1024;
+        const DEFAULT_IDEMPOTENCY_KEY_TTL_SECS: u64 = 24 * 60 * 60;
+        const DEFAULT_MAX_TASK_LENGTH: usize = 500;
+        const DEFAULT_REQUEST_TIMEOUT_SECS: u64 = 30;
+        const DEFAULT_MAX_TODOS_PER_USER: u64 = 1000;
+        const DEFAULT_MONGO_SERVER_SELECTION_TIMEOUT_SECS: u64 = 5;
+        const DEFAULT_MONGO_CONNECT_ATTEMPTS: u32 = 5;
+        const DEFAULT_MONGO_CONNECT_BACKOFF_SECS: u64 = 1;
+        const DEFAULT_MEMSTORE_FILE: &str = "todos.json";
+        let store_backend = match env::var("STORE_BACKEND").as_deref() {
+            Ok("memory") => StoreBackend::Memory {
+                file_path: non_empty_env("MEMSTORE_FILE")
+                    .unwrap_or(DEFAULT_MEMSTORE_FILE.to_string()),
+            },
+            _ => StoreBackend::Mongo {
+                uri: secret_env("MONGO_URI")?,
+            },
+        };
+        let default_mongo = MongoOptions::default();
+        let mongo_options = MongoOptions {
+            max_pool_size: env::var("MONGO_MAX_POOL_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            connect_timeout: env::var("MONGO_CONNECT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs),
+            server_selection_timeout: Some(
+                env::var("MONGO_SERVER_SELECTION_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(Duration::from_secs(
+                        DEFAULT_MONGO_SERVER_SELECTION_TIMEOUT_SECS,
+                    )),
+            ),
+            operation_timeout: env::var("MONGO_OPERATION_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .unwrap_or(default_mongo.operation_timeout),
+            db_name: non_empty_env("MONGO_DB").unwrap_or(default_mongo.db_name),
+            todo_collection: non_empty_env("MONGO_TODO_COLLECTION")
+                .unwrap_or(default_mongo.todo_collection),
+            user_collection: non_empty_env("MONGO_USER_COLLECTION")
+                .unwrap_or(default_mongo.user_collection),
+        };
+        let mongo_connect_attempts = env::var("MONGO_CONNECT_ATTEMPTS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|attempts| *attempts > 0)
+            .unwrap_or(DEFAULT_MONGO_CONNECT_ATTEMPTS);
+        let mongo_connect_backoff = env::var("MONGO_CONNECT_BACKOFF_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_MONGO_CONNECT_BACKOFF_SECS));
+        let domain = secret_env("AUTH0_DOMAIN")?;
+        let audience = secret_env("AUTH0_AUDIENCE")?;
+        let claims_namespace = env::var("AUTH0_CLAIMS_NAMESPACE")
+            .ok()
+            .filter(|s| !s.is_empty());
+        let allow_query_token = env::var("ALLOW_QUERY_TOKEN")
+            .map(|s| s == "true" || s == "1")
+            .unwrap_or(false);
+        // API keys are opt-in: list `api_key` in AUTH_METHODS and set API_KEYS
+        let auth_methods = list_from_env("AUTH_METHODS")
+            .map(|methods| methods.iter().filter_map(|m| m.parse().ok()).collect())
+            .unwrap_or_else(|| vec![AuthMethod::Bearer]);
+        let api_keys = list_from_env("API_KEYS").unwrap_or_default();
         let ip_address = env::var("TODO_ADDR")
             .map(|s| {
                 if s.is_empty() {
@@ -47,45 +258,313 @@ impl Config {
                 }
             })
             .unwrap_or(DEFAULT_PORT.to_string());
-        let full_addr = format!("{}:{}", ip_address, port);
-        let server_addr = full_addr.parse().map_err(|_| env::VarError::NotPresent)?;
+        let server_addr = parse_server_addr(&ip_address, &port).ok_or_else(|| {
+            error!("Invalid TODO_ADDR {:?} or TODO_PORT {:?}", ip_address, port);
+            env::VarError::NotPresent
+        })?;
+        let tls = match (
+            non_empty_env("TLS_CERT_PATH"),
+            non_empty_env("TLS_KEY_PATH"),
+        ) {
+            (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                cert_path,
+                key_path,
+            }),
+            (None, None) => None,
+            _ => {
+                error!("TLS_CERT_PATH and TLS_KEY_PATH must be set together");
+                return Err(env::VarError::NotPresent);
+            }
+        };
+        let rate_limit_requests = Some(
+            env::var("RATE_LIMIT_REQUESTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_RATE_LIMIT_REQUESTS),
+        )
+        .filter(|requests| *requests > 0);
+        let rate_limit_window = env::var("RATE_LIMIT_WINDOW_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_RATE_LIMIT_WINDOW_SECS));
+        let userinfo_cache_ttl = env::var("USERINFO_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_USERINFO_CACHE_TTL_SECS));
+        let userinfo_timeout = env::var("USERINFO_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_USERINFO_TIMEOUT_SECS));
+        let user_cache_capacity = env::var("USER_CACHE_CAPACITY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .and_then(NonZeroUsize::new)
+            .unwrap_or(NonZeroUsize::new(DEFAULT_USER_CACHE_CAPACITY).unwrap());
+        let user_cache_ttl = env::var("USER_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(DEFAULT_USER_CACHE_TTL_SECS));
+
+        let webhook = non_empty_env("WEBHOOK_URL").map(|url| {
+            let secret = secret_env("WEBHOOK_SECRET").ok().filter(|s| !s.is_empty());
+            if secret.is_none() {
+                warn!("WEBHOOK_SECRET is not set, webhook payloads will not be signed");
+            }
+            WebhookConfig::new(url, secret)
+        });
 
         Ok(Self {
             server_addr,
-            mongo_uri,
+            tls,
+            store_backend,
+            audit_log_file: non_empty_env("AUDIT_LOG_FILE"),
+            todo_cache_capacity: env::var("TODO_CACHE_CAPACITY")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .and_then(NonZeroUsize::new),
+            todo_cache_ttl: env::var("TODO_CACHE_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(Duration::from_secs(DEFAULT_TODO_CACHE_TTL_SECS)),
+            mongo_options,
+            mongo_connect_attempts,
+            mongo_connect_backoff,
             domain,
             audience,
+            claims_namespace,
+            allow_query_token,
+            auth_methods,
+            api_keys,
+            userinfo_cache_ttl,
+            userinfo_timeout,
+            user_cache_capacity,
+            user_cache_ttl,
+            rate_limit_requests,
+            rate_limit_window,
+            cors: cors_from_env(),
+            max_batch_size: env::var("MAX_BATCH_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_MAX_BATCH_SIZE),
+            max_page_size: env::var("MAX_PAGE_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|size| *size > 0)
+                .unwrap_or(DEFAULT_MAX_PAGE_SIZE),
+            idempotency_key_ttl: env::var("IDEMPOTENCY_KEY_TTL_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(Duration::from_secs(DEFAULT_IDEMPOTENCY_KEY_TTL_SECS)),
+            max_body_size: env::var("MAX_BODY_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|size| *size > 0)
+                .unwrap_or(DEFAULT_MAX_BODY_SIZE),
+            max_task_length: env::var("MAX_TASK_LENGTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_MAX_TASK_LENGTH),
+            request_timeout: env::var("REQUEST_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(Duration::from_secs(DEFAULT_REQUEST_TIMEOUT_SECS)),
+            auto_complete_subtasks: env::var("AUTO_COMPLETE_SUBTASKS")
+                .map(|s| s == "true" || s == "1")
+                .unwrap_or(false),
+            max_todos_per_user: Some(
+                env::var("MAX_TODOS_PER_USER")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(DEFAULT_MAX_TODOS_PER_USER),
+            )
+            .filter(|max| *max > 0),
+            webhook,
         })
     }
 }
 
+/// Connects to MongoDB, retrying with exponential backoff so the server
+/// survives a database that comes up shortly after it.
+async fn connect_mongo(
+    uri: &str,
+    config: &Config,
+) -> Result<MongoStore, Box<dyn std::error::Error>> {
+    let mut backoff = config.mongo_connect_backoff;
+    let mut attempt = 1;
+    loop {
+        match MongoStore::init(uri.to_string(), config.mongo_options.clone()).await {
+            Ok(store) => return Ok(store),
+            Err(e) if attempt < config.mongo_connect_attempts => {
+                warn!(
+                    "Failed to connect to MongoDB (attempt {}/{}): {}; retrying in {:?}",
+                    attempt, config.mongo_connect_attempts, e, backoff
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Adds the optional read cache, the optional webhooks and the audit trail
+/// around a backend store.
+fn decorate<S: TodoStore + 'static>(store: S, config: &Config) -> Arc<dyn TodoStore> {
+    match config.todo_cache_capacity {
+        Some(capacity) => notifying(
+            CachingStore::new(store, capacity, config.todo_cache_ttl),
+            config,
+        ),
+        None => notifying(store, config),
+    }
+}
+
+fn notifying<S: TodoStore + 'static>(store: S, config: &Config) -> Arc<dyn TodoStore> {
+    match &config.webhook {
+        Some(webhook) => {
+            info!("Sending todo events to {}", webhook.url);
+            audited(
+                WebhookStore::new(store, Webhooks::start(webhook.clone())),
+                config,
+            )
+        }
+        None => audited(store, config),
+    }
+}
+
+/// Every store records its mutations in the audit trail.
+fn audited<S: TodoStore + 'static>(store: S, config: &Config) -> Arc<dyn TodoStore> {
+    let store = AuditStore::new(store);
+    match &config.audit_log_file {
+        Some(path) => Arc::new(store.with_file(path).unwrap_or_else(|e| {
+            error!("Failed to open audit log {}: {}", path, e);
+            std::process::exit(1);
+        })),
+        None => Arc::new(store),
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
+    init_logging();
 
     let config = Config::from_env().expect("Failed to load configuration");
 
-    let mongo_store = MongoStore::init(config.mongo_uri)
-        .await
-        .unwrap_or_else(|e| {
-            error!("Failed to connect to MongoDB: {:?}", e);
-            std::process::exit(1);
-        });
-    let store: Arc<dyn TodoStore> = Arc::new(mongo_store.clone());
-    let cache: Arc<Mutex<UserCache>> =
-        Arc::new(Mutex::new(UserCache::new(NonZeroUsize::new(20).unwrap())));
+    let mut mem_store = None;
+    let store: Arc<dyn TodoStore> = match &config.store_backend {
+        StoreBackend::Mongo { uri } => {
+            let mongo_store = connect_mongo(uri, &config).await.unwrap_or_else(|e| {
+                error!("Failed to connect to MongoDB: {:?}", e);
+                std::process::exit(1);
+            });
+            decorate(mongo_store, &config)
+        }
+        StoreBackend::Memory { file_path } => {
+            info!("Using in-memory store backed by {}", file_path);
+            let store = MemStore::new(file_path.clone());
+            mem_store = Some(store.clone());
+            decorate(store, &config)
+        }
+    };
+    let cache = Arc::new(UserCache::new(
+        config.user_cache_capacity,
+        config.user_cache_ttl,
+    ));
+    let metrics = Metrics::new();
+    metrics.register_user_cache(&cache);
+    let userinfo_cache = Arc::new(Mutex::new(UserInfoCache::new(
+        NonZeroUsize::new(100).unwrap(),
+        config.userinfo_cache_ttl,
+    )));
     let store_for_routes = store.clone();
     let jwt_verifier = JwtVerifier::new(&config.domain)
         .use_cache(true)
         .validate_aud(&config.audience)
-        .build();
-    let with_jwt_middleware = with_jwt(jwt_verifier.clone(), store.clone(), cache);
-    let with_decoded_middleware = with_decoded(jwt_verifier, config.domain.clone());
+        .try_build()
+        .unwrap_or_else(|e| {
+            error!("Invalid AUTH0_DOMAIN: {}", e);
+            std::process::exit(1);
+        });
+    jwt_verifier.prefetch().await.unwrap_or_else(|e| {
+        error!("Failed to fetch JWKS from {}: {}", config.domain, e);
+        std::process::exit(1);
+    });
+    metrics.register_jwt_verifier(&jwt_verifier);
+    let with_jwt_middleware = with_auth(
+        with_jwt(
+            jwt_verifier.clone(),
+            store.clone(),
+            cache,
+            config.allow_query_token,
+        ),
+        &config.auth_methods,
+        Arc::new(parse_api_keys(&config.api_keys)),
+    );
+    let userinfo_client = reqwest::Client::builder()
+        .timeout(config.userinfo_timeout)
+        .build()
+        .unwrap_or_else(|e| {
+            error!("Failed to build the userinfo HTTP client: {}", e);
+            std::process::exit(1);
+        });
+    let with_decoded_middleware = with_decoded(
+        userinfo_client,
+        jwt_verifier,
+        config.domain.clone(),
+        config.claims_namespace.clone(),
+        userinfo_cache,
+    );
+
+    let router_config = RouterConfig {
+        rate_limiter: config
+            .rate_limit_requests
+            .map(|requests| RateLimiter::new(requests, config.rate_limit_window)),
+        cors: config.cors.clone(),
+        max_batch_size: config.max_batch_size,
+        max_page_size: config.max_page_size,
+        idempotency_keys: IdempotencyKeys::new(config.idempotency_key_ttl),
+        max_body_size: config.max_body_size,
+        max_task_length: config.max_task_length,
+        metrics: Some(metrics),
+        request_timeout: config.request_timeout,
+        auto_complete_subtasks: config.auto_complete_subtasks,
+        max_todos_per_user: config.max_todos_per_user,
+    };
 
-    info!("Server started at {}", config.server_addr);
+    let routes = router(
+        store_for_routes,
+        with_jwt_middleware,
+        with_decoded_middleware,
+        router_config,
+    );
+    let server = async {
+        match &config.tls {
+            Some(tls) => {
+                info!("Serving HTTPS at {}", config.server_addr);
+                warp::serve(routes)
+                    .tls()
+                    .cert_path(&tls.cert_path)
+                    .key_path(&tls.key_path)
+                    .run(config.server_addr)
+                    .await
+            }
+            None => {
+                info!("Server started at {}", config.server_addr);
+                warp::serve(routes).run(config.server_addr).await
+            }
+        }
+    };
 
     tokio::select! {
-        _ = warp::serve(router(store_for_routes, with_jwt_middleware, with_decoded_middleware)).run(config.server_addr) => {
+        _ = server => {
             info!("Server shutting down...");
         }
         _ = tokio::signal::ctrl_c() => {
@@ -93,5 +572,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    if let Some(mem_store) = mem_store {
+        if let Err(e) = mem_store.shutdown().await {
+            error!("Failed to save todos: {}", e);
+        }
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_addr() {
+        let addr = parse_server_addr("0.0.0.0", "3030").unwrap();
+        assert!(addr.is_ipv4());
+        assert_eq!(addr.port(), 3030);
+
+        for ip in ["::", "[::]"] {
+            let addr = parse_server_addr(ip, "3030").unwrap();
+            assert!(addr.is_ipv6());
+            assert!(addr.ip().is_unspecified());
+            assert_eq!(addr.to_string(), "[::]:3030");
+        }
+        let addr = parse_server_addr("::1", "8080").unwrap();
+        assert_eq!(addr.to_string(), "[::1]:8080");
+
+        assert!(parse_server_addr("localhost", "3030").is_none());
+        assert!(parse_server_addr("::1", "http").is_none());
+        assert!(parse_server_addr("[::1", "3030").is_none());
+    }
+}
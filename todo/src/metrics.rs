@@ -0,0 +1,241 @@
+use crate::auth::UserCache;
+use jwtverifier::{FailureReason, JwtVerifier};
+use log::error;
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, Registry, TextEncoder,
+};
+use uuid::Uuid;
+use warp::log::Info;
+
+/// Prometheus metrics for the todo server, exposed at `/metrics`.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    requests: IntCounterVec,
+    latency: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests by route and status"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let latency = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "HTTP request latency by route",
+            ),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        registry
+            .register(Box::new(requests.clone()))
+            .expect("metric registered once");
+        registry
+            .register(Box::new(latency.clone()))
+            .expect("metric registered once");
+        Self {
+            registry,
+            requests,
+            latency,
+        }
+    }
+
+    /// Exposes the hit/miss counters of the `with_jwt` user cache.
+    pub fn register_user_cache(&self, cache: &UserCache) {
+        if let Err(e) = self.registry.register(Box::new(cache.lookups.clone())) {
+            error!("Failed to register user cache metrics: {}", e);
+        }
+    }
+
+    /// Exposes the JWKS fetch, cache hit and failure counters of `verifier`.
+    pub fn register_jwt_verifier(&self, verifier: &JwtVerifier) {
+        let collector = JwtVerifierCollector::new(verifier.clone());
+        if let Err(e) = self.registry.register(Box::new(collector)) {
+            error!("Failed to register JWT verifier metrics: {}", e);
+        }
+    }
+
+    pub fn observe(&self, info: Info) {
+        let method = info.method().as_str();
+        let route = route_label(info.path());
+        self.requests
+            .with_label_values(&[method, route, info.status().as_str()])
+            .inc();
+        self.latency
+            .with_label_values(&[method, route])
+            .observe(info.elapsed().as_secs_f64());
+    }
+
+    /// Renders all metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Copies the verifier's own counters into Prometheus metrics on each scrape.
+struct JwtVerifierCollector {
+    verifier: JwtVerifier,
+    jwks_fetches: IntCounter,
+    cache_hits: IntCounter,
+    failures: IntCounterVec,
+}
+
+impl JwtVerifierCollector {
+    fn new(verifier: JwtVerifier) -> Self {
+        Self {
+            verifier,
+            jwks_fetches: IntCounter::new("jwks_fetches_total", "JWKS requests to the issuer")
+                .expect("valid metric"),
+            cache_hits: IntCounter::new(
+                "jwks_cache_hits_total",
+                "Token verifications served from the cached JWKS",
+            )
+            .expect("valid metric"),
+            failures: IntCounterVec::new(
+                Opts::new(
+                    "jwt_verification_failures_total",
+                    "Failed token verifications by reason",
+                ),
+                &["reason"],
+            )
+            .expect("valid metric"),
+        }
+    }
+}
+
+impl Collector for JwtVerifierCollector {
+    fn desc(&self) -> Vec<&Desc> {
+        let mut descs = self.jwks_fetches.desc();
+        descs.extend(self.cache_hits.desc());
+        descs.extend(self.failures.desc());
+        descs
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        let stats = self.verifier.stats();
+        self.jwks_fetches.reset();
+        self.jwks_fetches.inc_by(stats.jwks_fetches());
+        self.cache_hits.reset();
+        self.cache_hits.inc_by(stats.cache_hits());
+        for reason in FailureReason::ALL {
+            let counter = self.failures.with_label_values(&[reason.as_str()]);
+            counter.reset();
+            counter.inc_by(stats.failures(reason));
+        }
+        let mut families = self.jwks_fetches.collect();
+        families.extend(self.cache_hits.collect());
+        families.extend(self.failures.collect());
+        families
+    }
+}
+
+/// Paths served by the router, with `:id` for todo and subtask ids and
+/// `:user` for user ids.
+const ROUTES: &[&str] = &[
+    "/todos",
+    "/todos/stream",
+    "/todos/assigned",
+    "/todos/archived",
+    "/todos/changes",
+    "/todos/batch",
+    "/todos/purge",
+    "/todos/:id",
+    "/todos/:id/share",
+    "/todos/:id/archive",
+    "/todos/:id/unarchive",
+    "/todos/:id/complete",
+    "/todos/:id/uncomplete",
+    "/todos/:id/subtasks",
+    "/todos/:id/subtasks/:id",
+    "/todos/:id/subtasks/:id/toggle",
+    "/users",
+    "/users/:user",
+    "/users/:user/export",
+    "/admin/stats",
+    "/metrics",
+    "/userinfo",
+    "/ws",
+];
+
+/// Label for any path that isn't one of `ROUTES`, so scans and bad ids
+/// can't grow the number of series.
+const UNMATCHED: &str = "unmatched";
+
+/// Maps a request path to the route it was served by.
+fn route_label(path: &str) -> &'static str {
+    let segments: Vec<&str> = path.split('/').collect();
+    ROUTES
+        .iter()
+        .find(|route| {
+            let template: Vec<&str> = route.split('/').collect();
+            template.len() == segments.len()
+                && template
+                    .iter()
+                    .zip(&segments)
+                    .all(|(expected, segment)| match *expected {
+                        ":id" => Uuid::parse_str(segment).is_ok(),
+                        ":user" => !segment.is_empty(),
+                        expected => expected == *segment,
+                    })
+        })
+        .copied()
+        .unwrap_or(UNMATCHED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_label() {
+        assert_eq!(route_label("/todos"), "/todos");
+        assert_eq!(
+            route_label("/todos/00000000-0000-0000-0000-000000000000"),
+            "/todos/:id"
+        );
+        assert_eq!(
+            route_label(
+                "/todos/00000000-0000-0000-0000-000000000000/subtasks/00000000-0000-0000-0000-000000000001/toggle"
+            ),
+            "/todos/:id/subtasks/:id/toggle"
+        );
+        assert_eq!(
+            route_label("/users/auth0|123/export"),
+            "/users/:user/export"
+        );
+    }
+
+    #[test]
+    fn test_route_label_unmatched() {
+        for path in [
+            "/",
+            "/wp-login.php",
+            "/.env",
+            "/todos/not-a-uuid",
+            "/todos/00000000-0000-0000-0000-000000000000/unknown",
+            "/users/",
+        ] {
+            assert_eq!(route_label(path), UNMATCHED, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_jwt_verifier_metrics() {
+        let metrics = Metrics::new();
+        metrics.register_jwt_verifier(&JwtVerifier::new("http://localhost"));
+        let rendered = metrics.render();
+        assert!(rendered.contains("jwks_fetches_total 0"));
+        assert!(rendered.contains("jwks_cache_hits_total 0"));
+        assert!(rendered.contains(r#"jwt_verification_failures_total{reason="expired"} 0"#));
+    }
+}
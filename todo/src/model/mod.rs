@@ -1,5 +1,7 @@
+pub mod recurrence;
 pub mod todo;
 pub mod user;
 
+pub use recurrence::*;
 pub use todo::*;
 pub use user::*;
@@ -0,0 +1,167 @@
+use crate::model::todo::{Subtask, Todo};
+use chrono::{Days, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Recurrence {
+    /// Date of the occurrence following `date`. Monthly recurrence clamps to
+    /// the last day of shorter months, so Jan 31 is followed by Feb 28/29.
+    pub fn next_date(&self, date: NaiveDate) -> NaiveDate {
+        let next = match self {
+            Recurrence::Daily => date.checked_add_days(Days::new(1)),
+            Recurrence::Weekly => date.checked_add_days(Days::new(7)),
+            Recurrence::Monthly => date.checked_add_months(Months::new(1)),
+        };
+        next.unwrap_or(date)
+    }
+}
+
+/// Builds the next occurrence of a recurring todo that has just been completed.
+/// Todos without a due date are scheduled relative to today. The occurrence
+/// starts unarchived, with a fresh, unticked copy of the checklist.
+pub fn next_occurrence(todo: &Todo) -> Option<Todo> {
+    let recurrence = todo.recurrence?;
+    let now = Utc::now();
+    let due_date = todo.due_date.unwrap_or_else(|| now.date_naive());
+    Some(Todo {
+        id: Uuid::new_v4().to_string(),
+        completed: false,
+        completed_at: None,
+        due_date: Some(recurrence.next_date(due_date)),
+        version: 1,
+        created_at: Some(now),
+        updated_at: Some(now),
+        deleted_at: None,
+        archived: false,
+        subtasks: todo
+            .subtasks
+            .iter()
+            .map(|subtask| Subtask {
+                id: Uuid::new_v4().to_string(),
+                text: subtask.text.clone(),
+                done: false,
+            })
+            .collect(),
+        ..todo.clone()
+    })
+}
+
+/// Whether an update moved `before` into a completed state that should spawn
+/// the next occurrence.
+pub fn spawns_occurrence(before: &Todo, after: &Todo) -> bool {
+    !before.completed && after.completed && after.recurrence.is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_next_date_daily() {
+        assert_eq!(
+            Recurrence::Daily.next_date(date(2023, 3, 14)),
+            date(2023, 3, 15)
+        );
+        assert_eq!(
+            Recurrence::Daily.next_date(date(2023, 12, 31)),
+            date(2024, 1, 1)
+        );
+    }
+
+    #[test]
+    fn test_next_date_weekly() {
+        assert_eq!(
+            Recurrence::Weekly.next_date(date(2023, 3, 14)),
+            date(2023, 3, 21)
+        );
+        assert_eq!(
+            Recurrence::Weekly.next_date(date(2024, 2, 26)),
+            date(2024, 3, 4)
+        );
+    }
+
+    #[test]
+    fn test_next_date_monthly() {
+        assert_eq!(
+            Recurrence::Monthly.next_date(date(2023, 3, 14)),
+            date(2023, 4, 14)
+        );
+        assert_eq!(
+            Recurrence::Monthly.next_date(date(2023, 12, 5)),
+            date(2024, 1, 5)
+        );
+    }
+
+    #[test]
+    fn test_next_date_monthly_month_end() {
+        assert_eq!(
+            Recurrence::Monthly.next_date(date(2023, 1, 31)),
+            date(2023, 2, 28)
+        );
+        assert_eq!(
+            Recurrence::Monthly.next_date(date(2024, 1, 31)),
+            date(2024, 2, 29)
+        );
+        assert_eq!(
+            Recurrence::Monthly.next_date(date(2023, 3, 31)),
+            date(2023, 4, 30)
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence() {
+        let todo = Todo {
+            id: "1".to_string(),
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            task: "water plants".to_string(),
+            completed: true,
+            tags: vec!["home".to_string()],
+            version: 3,
+            created_at: None,
+            due_date: Some(date(2023, 3, 14)),
+            recurrence: Some(Recurrence::Weekly),
+            shared_with: vec![],
+            assigned_to: None,
+            subtasks: vec![Subtask {
+                id: "s1".to_string(),
+                text: "check soil".to_string(),
+                done: true,
+            }],
+            completed_at: None,
+            archived: true,
+            updated_at: None,
+            deleted_at: None,
+        };
+        let next = next_occurrence(&todo).unwrap();
+        assert_ne!(next.id, todo.id);
+        assert!(!next.completed);
+        assert_eq!(next.version, 1);
+        assert_eq!(next.due_date, Some(date(2023, 3, 21)));
+        assert_eq!(next.task, todo.task);
+        assert_eq!(next.tags, todo.tags);
+        assert!(!next.archived);
+        assert_eq!(next.subtasks.len(), 1);
+        assert_eq!(next.subtasks[0].text, "check soil");
+        assert!(!next.subtasks[0].done);
+        assert_ne!(next.subtasks[0].id, "s1");
+
+        let once = Todo {
+            recurrence: None,
+            ..todo
+        };
+        assert!(next_occurrence(&once).is_none());
+    }
+}
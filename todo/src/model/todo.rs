@@ -1,6 +1,64 @@
-use serde::{Deserialize, Serialize};
+use crate::error::Error;
+use crate::model::recurrence::Recurrence;
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use uuid::Uuid;
 
+/// Formats `time` with a fixed number of fractional digits, so that string
+/// order matches time order. Mongo compares the stored strings directly.
+pub fn format_timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+/// Serde adapter storing optional timestamps through `format_timestamp`.
+mod fixed_timestamp {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => serializer.serialize_some(&format_timestamp(time)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        Option::<DateTime<Utc>>::deserialize(deserializer)
+    }
+}
+
+fn validate_text(field: &str, text: &str, max_length: usize) -> Result<(), Error> {
+    if text.trim().is_empty() {
+        return Err(Error::validation(field, "must not be empty"));
+    }
+    if text.chars().count() > max_length {
+        return Err(Error::validation(
+            field,
+            format!("must be at most {} characters", max_length),
+        ));
+    }
+    Ok(())
+}
+
+/// Todo ids are UUIDs; anything else is rejected before it reaches a query.
+pub fn validate_id(id: &str) -> Result<(), Error> {
+    Uuid::parse_str(id)
+        .map(|_| ())
+        .map_err(|_| Error::validation("id", "must be a UUID"))
+}
+
+fn validate_task(task: &str, max_task_length: usize) -> Result<(), Error> {
+    validate_text("task", task, max_task_length)
+}
+
+pub fn nothing_to_update() -> Error {
+    Error::validation("body", "must set at least one field to update")
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct Todo {
     pub id: String,
@@ -8,28 +66,269 @@ pub struct Todo {
     pub user_id: String,
     pub task: String,
     pub completed: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub version: u64,
+    /// Unset on todos stored before creation times were recorded.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// Users in the same tenant who can read this todo besides its owner.
+    #[serde(default)]
+    pub shared_with: Vec<String>,
+    /// User in the same tenant responsible for this todo.
+    #[serde(default)]
+    pub assigned_to: Option<String>,
+    #[serde(default)]
+    pub subtasks: Vec<Subtask>,
+    /// When the todo was last marked done; cleared when it is reopened.
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Archived todos are left out of the default listing.
+    #[serde(default)]
+    pub archived: bool,
+    /// Set on every change; change feeds page through todos by this value.
+    #[serde(default, with = "fixed_timestamp")]
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Deleted todos are kept as tombstones so sync clients learn about them.
+    #[serde(
+        default,
+        with = "fixed_timestamp",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Todo {
     pub fn new(tenant_id: String, user_id: String, new_todo: NewTodo) -> Self {
+        let now = Utc::now();
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: new_todo.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
             tenant_id,
             user_id,
             task: new_todo.task,
             completed: new_todo.completed,
+            tags: new_todo.tags,
+            version: 1,
+            created_at: Some(now),
+            due_date: new_todo.due_date,
+            recurrence: new_todo.recurrence,
+            shared_with: vec![],
+            assigned_to: None,
+            subtasks: vec![],
+            completed_at: match new_todo.completed {
+                true => Some(now),
+                false => None,
+            },
+            archived: false,
+            updated_at: Some(now),
+            deleted_at: None,
+        }
+    }
+
+    /// Records a change made at `now`: bumps the version and `updated_at`.
+    pub fn touch(&mut self, now: DateTime<Utc>) {
+        self.version += 1;
+        self.updated_at = Some(now);
+    }
+
+    /// Sets `completed`, stamping or clearing `completed_at` when the state
+    /// actually changes.
+    pub fn set_completed(&mut self, completed: bool, now: DateTime<Utc>) {
+        if self.completed != completed {
+            self.completed_at = match completed {
+                true => Some(now),
+                false => None,
+            };
+        }
+        self.completed = completed;
+    }
+
+    /// Marks the todo completed once it has subtasks and all of them are done.
+    /// Returns whether the todo changed.
+    pub fn complete_if_subtasks_done(&mut self) -> bool {
+        if self.completed || self.subtasks.is_empty() || !self.subtasks.iter().all(|s| s.done) {
+            return false;
+        }
+        self.set_completed(true, Utc::now());
+        true
+    }
+}
+
+/// A checklist item within a todo.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Subtask {
+    pub id: String,
+    pub text: String,
+    pub done: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NewSubtask {
+    pub text: String,
+}
+
+impl NewSubtask {
+    pub fn validate(&self, max_task_length: usize) -> Result<(), Error> {
+        validate_text("text", &self.text, max_task_length)
+    }
+}
+
+impl From<NewSubtask> for Subtask {
+    fn from(new_subtask: NewSubtask) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            text: new_subtask.text,
+            done: false,
         }
     }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct NewTodo {
+    #[serde(default)]
+    pub id: Option<String>,
     pub task: String,
     pub completed: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+}
+
+impl NewTodo {
+    pub fn validate(&self, max_task_length: usize) -> Result<(), Error> {
+        if let Some(id) = &self.id {
+            validate_id(id)?;
+        }
+        validate_task(&self.task, max_task_length)
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct UpdateTodo {
     pub task: Option<String>,
     pub completed: Option<bool>,
+    pub tags: Option<Vec<String>>,
+    pub assigned_to: Option<String>,
+}
+
+impl UpdateTodo {
+    /// An update with no fields set; stores reject it rather than bumping the
+    /// version without changing anything.
+    pub fn is_empty(&self) -> bool {
+        self.task.is_none()
+            && self.completed.is_none()
+            && self.tags.is_none()
+            && self.assigned_to.is_none()
+    }
+
+    /// Applies the set fields to `todo`, mirroring the `$set` the Mongo store
+    /// sends. `now` stamps `completed_at` if the todo gets completed.
+    pub fn apply(&self, todo: &mut Todo, now: DateTime<Utc>) {
+        if let Some(task) = &self.task {
+            todo.task = task.clone();
+        }
+        if let Some(completed) = self.completed {
+            todo.set_completed(completed, now);
+        }
+        if let Some(tags) = &self.tags {
+            todo.tags = tags.clone();
+        }
+        if let Some(assigned_to) = &self.assigned_to {
+            todo.assigned_to = Some(assigned_to.clone());
+        }
+    }
+
+    pub fn validate(&self, max_task_length: usize) -> Result<(), Error> {
+        if self.is_empty() {
+            return Err(nothing_to_update());
+        }
+        match &self.task {
+            Some(task) => validate_task(task, max_task_length),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_task() {
+        assert!(validate_task("buy milk", 10).is_ok());
+        assert!(validate_task("", 10).is_err());
+        assert!(validate_task("   ", 10).is_err());
+        assert!(validate_task("buy more milk", 10).is_err());
+        // length is counted in characters, not bytes
+        assert!(validate_task("żółć", 4).is_ok());
+    }
+
+    #[test]
+    fn test_validate_id() {
+        assert!(validate_id(&Uuid::new_v4().to_string()).is_ok());
+        assert_eq!(
+            validate_id(r#"{"$ne": null}"#),
+            Err(Error::validation("id", "must be a UUID"))
+        );
+        let new_todo = NewTodo {
+            id: Some("1".to_string()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        assert!(new_todo.validate(10).is_err());
+    }
+
+    #[test]
+    fn test_validate_empty_update() {
+        let update = UpdateTodo {
+            task: None,
+            completed: None,
+            tags: None,
+            assigned_to: None,
+        };
+        assert!(update.is_empty());
+        assert_eq!(update.validate(10), Err(nothing_to_update()));
+    }
+
+    #[test]
+    fn test_complete_if_subtasks_done() {
+        let subtask = |done| Subtask {
+            id: Uuid::new_v4().to_string(),
+            text: "step".to_string(),
+            done,
+        };
+        let mut todo = Todo::new(
+            "tenant".to_string(),
+            "user".to_string(),
+            NewTodo {
+                id: None,
+                task: "test".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            },
+        );
+        assert!(!todo.complete_if_subtasks_done());
+
+        todo.subtasks = vec![subtask(true), subtask(false)];
+        assert!(!todo.complete_if_subtasks_done());
+        assert!(!todo.completed);
+
+        todo.subtasks[1].done = true;
+        assert!(todo.complete_if_subtasks_done());
+        assert!(todo.completed);
+        assert!(todo.completed_at.is_some());
+    }
 }
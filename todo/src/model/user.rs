@@ -20,3 +20,22 @@ impl User {
         }
     }
 }
+
+/// What admins see when listing users; leaves out contact details and the
+/// identity provider's id.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserSummary {
+    pub id: String,
+    pub name: String,
+    pub tenant_id: String,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            tenant_id: user.tenant_id,
+        }
+    }
+}
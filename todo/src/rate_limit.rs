@@ -0,0 +1,114 @@
+use crate::error::Error;
+use crate::storage::UserContext;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use warp::{reject, Filter, Rejection};
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+type BucketKey = (String, String);
+
+/// Token bucket rate limiter keyed by tenant and user id.
+#[derive(Clone)]
+pub struct RateLimiter {
+    capacity: f64,
+    refill_per_sec: f64,
+    buckets: Arc<Mutex<HashMap<BucketKey, Bucket>>>,
+}
+
+impl RateLimiter {
+    /// Allows `requests` per `window`, refilled continuously.
+    pub fn new(requests: u32, window: Duration) -> Self {
+        Self {
+            capacity: requests as f64,
+            refill_per_sec: requests as f64 / window.as_secs_f64(),
+            buckets: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes a token from the user's bucket. When the bucket is empty, returns
+    /// how long until the next token becomes available, or `None` if it never
+    /// refills. Buckets that have refilled completely are dropped, since a new
+    /// bucket starts full anyway.
+    pub fn check(&self, tenant_id: &str, user_id: &str) -> Result<(), Option<Duration>> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        buckets.retain(|_, bucket| {
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens + elapsed * self.refill_per_sec < self.capacity
+        });
+        let key = (tenant_id.to_string(), user_id.to_string());
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: self.capacity,
+            last_refill: now,
+        });
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        bucket.last_refill = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else if self.refill_per_sec > 0.0 {
+            Err(Some(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.refill_per_sec,
+            )))
+        } else {
+            Err(None)
+        }
+    }
+}
+
+pub fn with_rate_limit(
+    rate_limiter: Option<RateLimiter>,
+    with_jwt: impl Filter<Extract = (UserContext,), Error = Rejection> + Clone,
+) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+    with_jwt.and_then(move |user: UserContext| {
+        let rate_limiter = rate_limiter.clone();
+        async move {
+            match rate_limiter.map(|limiter| limiter.check(&user.tenant_id, &user.user_id)) {
+                Some(Err(wait)) => Err(reject::custom(Error::RateLimited {
+                    retry_after: wait.map(|wait| wait.as_secs_f64().ceil() as u64),
+                })),
+                _ => Ok(user),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        assert!(limiter.check("tenant", "user").is_ok());
+        assert!(limiter.check("tenant", "user").is_ok());
+        let wait = limiter.check("tenant", "user").unwrap_err().unwrap();
+        assert!(wait <= Duration::from_secs(30));
+        // buckets are per tenant and user
+        assert!(limiter.check("tenant", "user2").is_ok());
+        assert!(limiter.check("tenant2", "user").is_ok());
+    }
+
+    #[test]
+    fn test_rate_limiter_drops_full_buckets() {
+        let limiter = RateLimiter::new(2, Duration::from_millis(20));
+        assert!(limiter.check("tenant", "user").is_ok());
+        assert!(limiter.check("tenant", "user2").is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 2);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.check("tenant", "user").is_ok());
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rate_limiter_zero_requests() {
+        let limiter = RateLimiter::new(0, Duration::from_secs(60));
+        assert_eq!(limiter.check("tenant", "user"), Err(None));
+    }
+}
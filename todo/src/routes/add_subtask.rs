@@ -0,0 +1,22 @@
+use crate::model::todo::{NewSubtask, Subtask};
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use uuid::Uuid;
+use warp::http::StatusCode;
+
+pub async fn add_subtask(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+    max_task_length: usize,
+    new_subtask: NewSubtask,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    new_subtask.validate(max_task_length)?;
+    let todo = store
+        .add_subtask(&user, id.to_string(), Subtask::from(new_subtask))
+        .await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&todo),
+        StatusCode::CREATED,
+    ))
+}
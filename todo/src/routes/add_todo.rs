@@ -1,13 +1,74 @@
+use crate::error::Error;
+use crate::idempotency::{Claim, IdempotencyKeys};
 use crate::model::todo::NewTodo;
 use crate::storage::store::{TodoStore, UserContext};
 use std::sync::Arc;
 use warp::http::StatusCode;
+use warp::reject;
 
+const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+/// Rejects adding `adding` todos when that would take the user past `quota`.
+/// Counts what `count_todos` does, so todos shared with the user count too,
+/// and concurrent adds can overshoot it slightly.
+pub async fn check_quota(
+    user: &UserContext,
+    store: &Arc<dyn TodoStore>,
+    quota: Option<u64>,
+    adding: u64,
+) -> Result<(), Error> {
+    match quota {
+        Some(quota) if store.count_todos(user, None).await? + adding > quota => {
+            Err(Error::QuotaExceeded)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// With an `Idempotency-Key`, a repeated request answers like the first one
+/// instead of adding the todo again.
 pub async fn add_todo(
     user: UserContext,
     store: Arc<dyn TodoStore>,
+    max_task_length: usize,
+    max_todos: Option<u64>,
+    idempotency_keys: IdempotencyKeys,
+    idempotency_key: Option<String>,
     new_todo: NewTodo,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    store.add_todo(&user, new_todo).await?;
-    Ok(StatusCode::CREATED)
+    new_todo.validate(max_task_length)?;
+    let key = match idempotency_key {
+        Some(key) if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH => {
+            return Err(reject::custom(Error::validation(
+                "Idempotency-Key",
+                format!("must be 1 to {} bytes", MAX_IDEMPOTENCY_KEY_LENGTH),
+            )))
+        }
+        Some(key) => key,
+        None => {
+            check_quota(&user, &store, max_todos, 1).await?;
+            store.add_todo(&user, new_todo).await?;
+            return Ok(StatusCode::CREATED);
+        }
+    };
+
+    match idempotency_keys.begin(&user.tenant_id, &user.user_id, &key) {
+        Claim::Replay(status) => return Ok(status),
+        Claim::InProgress => return Err(reject::custom(Error::Conflict)),
+        Claim::New => {}
+    }
+    let result = match check_quota(&user, &store, max_todos, 1).await {
+        Ok(()) => store.add_todo(&user, new_todo).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => {
+            idempotency_keys.complete(&user.tenant_id, &user.user_id, &key, StatusCode::CREATED);
+            Ok(StatusCode::CREATED)
+        }
+        Err(e) => {
+            idempotency_keys.abandon(&user.tenant_id, &user.user_id, &key);
+            Err(reject::custom(e))
+        }
+    }
 }
@@ -0,0 +1,28 @@
+use crate::error::Error;
+use crate::model::todo::NewTodo;
+use crate::routes::add_todo::check_quota;
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use warp::http::StatusCode;
+
+pub async fn add_todos(
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+    max_batch_size: usize,
+    max_task_length: usize,
+    max_todos: Option<u64>,
+    new_todos: Vec<NewTodo>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    if new_todos.len() > max_batch_size {
+        return Err(warp::reject::custom(Error::PayloadTooLarge));
+    }
+    for new_todo in &new_todos {
+        new_todo.validate(max_task_length)?;
+    }
+    check_quota(&user, &store, max_todos, new_todos.len() as u64).await?;
+    let todos = store.add_todos(&user, new_todos).await?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&todos),
+        StatusCode::CREATED,
+    ))
+}
@@ -0,0 +1,21 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn archive_todo(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let todo = store.archive_todo(&user, id.to_string()).await?;
+    Ok(warp::reply::json(&todo))
+}
+
+pub async fn unarchive_todo(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let todo = store.unarchive_todo(&user, id.to_string()).await?;
+    Ok(warp::reply::json(&todo))
+}
@@ -0,0 +1,39 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use uuid::Uuid;
+use warp::http::header::ETAG;
+
+/// Unlike `PATCH /todos/{id}`, needs no `If-Match`: only the completion
+/// fields change, so there is no concurrent edit to lose.
+async fn set_completed(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+    completed: bool,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let updated = store
+        .set_completed(&user, id.to_string(), completed)
+        .await?;
+    let etag = format!("\"{}\"", updated.todo.version);
+    Ok(warp::reply::with_header(
+        warp::reply::json(&updated),
+        ETAG,
+        etag,
+    ))
+}
+
+pub async fn complete_todo(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    set_completed(id, user, store, true).await
+}
+
+pub async fn uncomplete_todo(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    set_completed(id, user, store, false).await
+}
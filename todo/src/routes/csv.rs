@@ -0,0 +1,83 @@
+use crate::model::Todo;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+const CSV_HEADER: &str = "id,task,completed,created_at,due_date";
+
+/// Whether the `Accept` header asks for CSV rather than the default JSON.
+pub fn accepts_csv(accept: Option<&str>) -> bool {
+    accept.is_some_and(|accept| {
+        accept.split(',').any(|range| {
+            let media_type = range.split(';').next().unwrap_or_default().trim();
+            media_type.eq_ignore_ascii_case("text/csv")
+        })
+    })
+}
+
+/// Quotes a field when it contains a delimiter, quote or line break (RFC 4180).
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn todos_to_csv(todos: &[Todo]) -> String {
+    let mut csv = format!("{}\r\n", CSV_HEADER);
+    for todo in todos {
+        let row = [
+            escape(&todo.id),
+            escape(&todo.task),
+            todo.completed.to_string(),
+            todo.created_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            todo.due_date.map(|d| d.to_string()).unwrap_or_default(),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::NewTodo;
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    #[test]
+    fn test_accepts_csv() {
+        assert!(accepts_csv(Some("text/csv")));
+        assert!(accepts_csv(Some("application/json;q=0.5, Text/CSV; q=0.9")));
+        assert!(!accepts_csv(Some("application/json")));
+        assert!(!accepts_csv(Some("*/*")));
+        assert!(!accepts_csv(None));
+    }
+
+    #[test]
+    fn test_todos_to_csv() {
+        let new_todo = NewTodo {
+            id: Some("1".to_string()),
+            task: r#"buy "oat" milk, eggs"#.to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: NaiveDate::from_ymd_opt(2023, 10, 12),
+            recurrence: None,
+        };
+        let mut todo = Todo::new("tenant".to_string(), "user".to_string(), new_todo);
+        todo.created_at = Some(Utc.with_ymd_and_hms(2023, 10, 1, 9, 30, 0).unwrap());
+        let mut undated = todo.clone();
+        undated.id = "2".to_string();
+        undated.task = "plain".to_string();
+        undated.completed = true;
+        undated.created_at = None;
+        undated.due_date = None;
+
+        assert_eq!(
+            todos_to_csv(&[todo, undated]),
+            "id,task,completed,created_at,due_date\r\n\
+             1,\"buy \"\"oat\"\" milk, eggs\",false,2023-10-01T09:30:00+00:00,2023-10-12\r\n\
+             2,plain,true,,\r\n"
+        );
+    }
+}
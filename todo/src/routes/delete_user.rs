@@ -0,0 +1,13 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+
+/// Deletes user `id` and all of their todos, returning how many todos went
+/// with them. Admins can only delete users in their own tenant.
+pub async fn delete_user(
+    id: String,
+    admin: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let deletion = store.delete_user(&admin, id).await?;
+    Ok(warp::reply::json(&deletion))
+}
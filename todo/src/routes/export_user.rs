@@ -0,0 +1,13 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+
+/// Everything stored about user `id` as one JSON document. Admins can only
+/// export users in their own tenant; anyone else is reported as not found.
+pub async fn export_user(
+    id: String,
+    admin: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let export = store.export_user_data(admin.tenant_id, id).await?;
+    Ok(warp::reply::json(&export))
+}
@@ -0,0 +1,14 @@
+use crate::storage::store::{TodoFilter, TodoStore, UserContext};
+use std::sync::Arc;
+
+pub async fn get_archived_todos(
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let filter = TodoFilter {
+        archived: Some(true),
+        ..TodoFilter::default()
+    };
+    let todos = store.get_todos(&user, &filter).await?;
+    Ok(warp::reply::json(&todos))
+}
@@ -0,0 +1,10 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+
+pub async fn get_assigned_todos(
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let todos = store.get_assigned_todos(&user).await?;
+    Ok(warp::reply::json(&todos))
+}
@@ -0,0 +1,71 @@
+use crate::error::Error;
+use crate::model::{format_timestamp, Todo};
+use crate::storage::store::{ChangeCursor, TodoStore, UserContext};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::reject;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ChangesQuery {
+    /// The `cursor` of the previous response, or an RFC 3339 timestamp;
+    /// omitted on the first sync
+    pub since: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChangesPage {
+    /// Changed todos, oldest change first; deleted ones carry `deleted_at`
+    pub changes: Vec<Todo>,
+    /// Pass as `since` on the next poll; unset until something has changed
+    pub cursor: Option<String>,
+}
+
+/// `<RFC 3339 timestamp>,<todo id>`, or a bare timestamp to skip every change
+/// made at that instant.
+fn parse_cursor(cursor: &str) -> Result<ChangeCursor, Error> {
+    let (updated_at, id) = match cursor.split_once(',') {
+        Some((updated_at, id)) => (updated_at, Some(id.to_string())),
+        None => (cursor, None),
+    };
+    let updated_at = DateTime::parse_from_rfc3339(updated_at)
+        .map_err(|_| Error::validation("since", "must be a cursor or an RFC 3339 timestamp"))?
+        .with_timezone(&Utc);
+    Ok(ChangeCursor { updated_at, id })
+}
+
+fn format_cursor(cursor: &ChangeCursor) -> String {
+    let updated_at = format_timestamp(&cursor.updated_at);
+    match &cursor.id {
+        Some(id) => format!("{},{}", updated_at, id),
+        None => updated_at,
+    }
+}
+
+/// Returns at most `max_page_size` changes; clients keep polling with the
+/// returned cursor until `changes` comes back empty.
+pub async fn get_changes(
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+    max_page_size: u64,
+    query: ChangesQuery,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let since = match &query.since {
+        Some(since) => Some(parse_cursor(since).map_err(reject::custom)?),
+        None => None,
+    };
+    let changes = store
+        .changes_since(&user, since.clone(), max_page_size)
+        .await?;
+    let cursor = changes
+        .last()
+        .and_then(|todo| {
+            todo.updated_at.map(|updated_at| ChangeCursor {
+                updated_at,
+                id: Some(todo.id.clone()),
+            })
+        })
+        .or(since)
+        .map(|cursor| format_cursor(&cursor));
+    Ok(warp::reply::json(&ChangesPage { changes, cursor }))
+}
@@ -0,0 +1,10 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+
+pub async fn get_store_stats(
+    _admin: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let stats = store.stats().await?;
+    Ok(warp::reply::json(&stats))
+}
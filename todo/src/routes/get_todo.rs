@@ -1,6 +1,7 @@
 use crate::storage::store::{TodoStore, UserContext};
 use std::sync::Arc;
 use uuid::Uuid;
+use warp::http::header::ETAG;
 
 pub async fn get_todo(
     id: Uuid,
@@ -8,5 +9,13 @@ pub async fn get_todo(
     store: Arc<dyn TodoStore>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let todo = store.get_todo(&user, id.to_string()).await?;
-    Ok(warp::reply::json(&todo))
+    let etag = todo
+        .as_ref()
+        .map(|todo| format!("\"{}\"", todo.version))
+        .unwrap_or_default();
+    Ok(warp::reply::with_header(
+        warp::reply::json(&todo),
+        ETAG,
+        etag,
+    ))
 }
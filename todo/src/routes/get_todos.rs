@@ -1,10 +1,120 @@
-use crate::storage::store::{TodoStore, UserContext};
+use crate::model::Todo;
+use crate::routes::csv::{accepts_csv, todos_to_csv, CSV_CONTENT_TYPE};
+use crate::storage::store::{TodoFilter, TodoStore, UserContext};
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use warp::Reply;
 
+#[derive(Debug, Default, Deserialize)]
+pub struct TodosQuery {
+    /// Comma separated list of tags, e.g. `?tag=work,home`
+    pub tag: Option<String>,
+    /// Id of the last todo on the previous page
+    pub after: Option<String>,
+    /// Page size; larger values are silently capped at `max_page_size`
+    pub limit: Option<u64>,
+    /// Also list archived todos, which are hidden by default
+    pub include_archived: Option<bool>,
+    /// Wrap even an unpaginated response in a `TodosPage` that carries
+    /// `total`, `limit` and `offset`, for clients that can't read headers
+    pub envelope: Option<bool>,
+}
+
+/// Response body for paginated requests; unpaginated requests get a bare array
+/// unless they ask for an envelope. An envelope always has the shape
+/// `{ data, next_cursor, total, limit, offset }`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TodosPage {
+    pub data: Vec<Todo>,
+    pub next_cursor: Option<String>,
+    /// Only set in an envelope
+    #[serde(flatten, default, skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<Envelope>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    /// Same as `X-Total-Count`
+    pub total: u64,
+    /// Page size after capping; `null` when the whole list was requested
+    pub limit: Option<u64>,
+    /// Always 0: pages follow `next_cursor` rather than an offset, so `data`
+    /// starts at the beginning of the requested page
+    pub offset: u64,
+}
+
+impl From<TodosQuery> for TodoFilter {
+    fn from(query: TodosQuery) -> Self {
+        let tags = query
+            .tag
+            .map(|tags| {
+                tags.split(',')
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        TodoFilter {
+            tags,
+            after: query.after,
+            limit: query.limit,
+            archived: match query.include_archived {
+                Some(true) => None,
+                _ => Some(false),
+            },
+        }
+    }
+}
+
+/// Responds with JSON unless the `Accept` header asks for CSV; a CSV page
+/// carries only the rows, without a cursor.
 pub async fn get_todos(
     user: UserContext,
     store: Arc<dyn TodoStore>,
+    max_page_size: u64,
+    accept: Option<String>,
+    mut query: TodosQuery,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let todos = store.get_todos(&user).await?;
-    Ok(warp::reply::json(&todos))
+    let paginated = query.after.is_some() || query.limit.is_some();
+    let envelope = query.envelope.unwrap_or(false);
+    query.limit = query.limit.map(|limit| limit.min(max_page_size));
+    let mut filter: TodoFilter = query.into();
+    let limit = filter.limit;
+    // fetch one extra todo to learn whether another page exists
+    filter.limit = limit.map(|limit| limit + 1);
+    let mut todos = store.get_todos(&user, &filter).await?;
+    let total = store.count_matching(&user, &filter).await?;
+
+    let body = if accepts_csv(accept.as_deref()) {
+        if let Some(limit) = limit {
+            todos.truncate(limit as usize);
+        }
+        warp::reply::with_header(todos_to_csv(&todos), "Content-Type", CSV_CONTENT_TYPE)
+            .into_response()
+    } else if paginated || envelope {
+        let next_cursor = match limit {
+            Some(limit) if todos.len() as u64 > limit => {
+                todos.truncate(limit as usize);
+                todos.last().map(|todo| todo.id.clone())
+            }
+            _ => None,
+        };
+        warp::reply::json(&TodosPage {
+            data: todos,
+            next_cursor,
+            envelope: envelope.then_some(Envelope {
+                total,
+                limit,
+                offset: 0,
+            }),
+        })
+        .into_response()
+    } else {
+        warp::reply::json(&todos).into_response()
+    };
+    Ok(warp::reply::with_header(
+        body,
+        "X-Total-Count",
+        total.to_string(),
+    ))
 }
@@ -0,0 +1,16 @@
+use crate::error::Error;
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use warp::reject;
+
+pub async fn get_user(
+    id: String,
+    admin: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match store.get_user_by_id(id).await? {
+        // admins can only look up users in their own tenant
+        Some(user) if user.tenant_id == admin.tenant_id => Ok(warp::reply::json(&user)),
+        _ => Err(reject::custom(Error::NotFound)),
+    }
+}
@@ -0,0 +1,36 @@
+use crate::error::Error;
+use crate::model::UserSummary;
+use crate::storage::store::{TodoStore, UserContext};
+use serde::Deserialize;
+use std::sync::Arc;
+use warp::reject;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct UsersQuery {
+    /// Defaults to the admin's own tenant, the only one they may list
+    pub tenant_id: Option<String>,
+    /// Page size; defaults to and is capped at `max_page_size`
+    pub limit: Option<u64>,
+    /// Number of users to skip
+    pub offset: Option<u64>,
+}
+
+pub async fn list_users(
+    admin: UserContext,
+    store: Arc<dyn TodoStore>,
+    max_page_size: u64,
+    query: UsersQuery,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let tenant_id = query.tenant_id.unwrap_or(admin.tenant_id.clone());
+    if tenant_id != admin.tenant_id {
+        return Err(reject::custom(Error::Forbidden));
+    }
+    let limit = query.limit.unwrap_or(max_page_size).min(max_page_size);
+    let users: Vec<UserSummary> = store
+        .list_users(tenant_id, limit, query.offset.unwrap_or(0))
+        .await?
+        .into_iter()
+        .map(UserSummary::from)
+        .collect();
+    Ok(warp::reply::json(&users))
+}
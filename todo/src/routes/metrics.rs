@@ -0,0 +1,12 @@
+use crate::metrics::Metrics;
+
+pub async fn metrics(metrics: Option<Metrics>) -> Result<impl warp::Reply, warp::Rejection> {
+    match metrics {
+        Some(metrics) => Ok(warp::reply::with_header(
+            metrics.render(),
+            "content-type",
+            "text/plain; version=0.0.4",
+        )),
+        None => Err(warp::reject::not_found()),
+    }
+}
@@ -1,15 +1,60 @@
+pub mod add_subtask;
 pub mod add_todo;
+pub mod add_todos;
+pub mod archive_todo;
+pub mod complete_todo;
+pub mod csv;
 pub mod delete_todo;
+pub mod delete_user;
+pub mod export_user;
+pub mod get_archived_todos;
+pub mod get_assigned_todos;
+pub mod get_changes;
+pub mod get_store_stats;
 pub mod get_todo;
 pub mod get_todos;
+pub mod get_user;
+pub mod list_users;
+pub mod metrics;
+pub mod purge_deleted;
+pub mod remove_subtask;
 pub mod router;
+pub mod router_config;
+pub mod share_todo;
+#[cfg(test)]
+pub mod test_harness;
+pub mod timeout;
+pub mod todos_stream;
+pub mod toggle_subtask;
 pub mod update_todo;
 pub mod userinfo;
+pub mod ws;
 
+pub use add_subtask::*;
 pub use add_todo::*;
+pub use add_todos::*;
+pub use archive_todo::*;
+pub use complete_todo::*;
 pub use delete_todo::*;
+pub use delete_user::*;
+pub use export_user::*;
+pub use get_archived_todos::*;
+pub use get_assigned_todos::*;
+pub use get_changes::*;
+pub use get_store_stats::*;
 pub use get_todo::*;
 pub use get_todos::*;
+pub use get_user::*;
+pub use list_users::*;
+pub use metrics::*;
+pub use purge_deleted::*;
+pub use remove_subtask::*;
 pub use router::*;
+pub use router_config::*;
+pub use share_todo::*;
+pub use timeout::*;
+pub use todos_stream::*;
+pub use toggle_subtask::*;
 pub use update_todo::*;
 pub use userinfo::*;
+pub use ws::*;
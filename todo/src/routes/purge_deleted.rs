@@ -0,0 +1,37 @@
+use crate::error::Error;
+use crate::storage::store::{TodoStore, UserContext};
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::reject;
+
+const DEFAULT_OLDER_THAN_DAYS: u32 = 30;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PurgeQuery {
+    /// Only purge todos deleted more than this many days ago; defaults to 30
+    pub older_than_days: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PurgeResult {
+    pub purged: u64,
+}
+
+pub async fn purge_deleted(
+    admin: UserContext,
+    store: Arc<dyn TodoStore>,
+    query: PurgeQuery,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let days = query.older_than_days.unwrap_or(DEFAULT_OLDER_THAN_DAYS);
+    if days > 36500 {
+        return Err(reject::custom(Error::validation(
+            "older_than_days",
+            "must be at most 36500",
+        )));
+    }
+    let purged = store
+        .purge_deleted(&admin, Duration::days(days.into()))
+        .await?;
+    Ok(warp::reply::json(&PurgeResult { purged }))
+}
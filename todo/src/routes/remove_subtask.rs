@@ -0,0 +1,15 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn remove_subtask(
+    id: Uuid,
+    subtask_id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let todo = store
+        .remove_subtask(&user, id.to_string(), subtask_id.to_string())
+        .await?;
+    Ok(warp::reply::json(&todo))
+}
@@ -1,124 +1,457 @@
 use super::*;
-use crate::auth::UserInfo;
+use crate::auth::{with_role, UserInfo};
 use crate::error::return_error;
+use crate::rate_limit::with_rate_limit;
 use crate::storage::{TodoStore, UserContext};
+use serde::de::DeserializeOwned;
 use std::sync::Arc;
 use uuid::Uuid;
-use warp::{http::Method, Filter, Rejection};
+use warp::{Filter, Rejection};
+
+/// JSON body of at most `limit` bytes; bigger ones are rejected with 413
+/// before they are read.
+fn json_body<T: DeserializeOwned + Send>(
+    limit: u64,
+) -> impl Filter<Extract = (T,), Error = Rejection> + Copy {
+    warp::body::content_length_limit(limit).and(warp::body::json())
+}
 
 pub fn router(
     store: Arc<dyn TodoStore>,
     with_jwt: impl Filter<Extract = (UserContext,), Error = Rejection> + Clone + Send + Sync + 'static,
     with_decoded: impl Filter<Extract = (UserInfo,), Error = Rejection> + Clone + Send + Sync + 'static,
+    config: RouterConfig,
 ) -> impl Filter<Extract = impl warp::Reply, Error = warp::Rejection> + Clone {
     let with_store = warp::any().map(move || store.clone());
+    let with_jwt = with_rate_limit(config.rate_limiter.clone(), with_jwt);
+    let max_task_length = config.max_task_length;
+    let with_max_task_length = warp::any().map(move || max_task_length);
+    let timeout = config.request_timeout;
+    let max_todos = config.max_todos_per_user;
+    let max_body_size = config.max_body_size;
 
-    let cors = warp::cors()
-        .allow_any_origin()
-        .allow_headers(vec!["User-Agent", "Content-Type", "Authorization"])
-        .allow_methods(&[Method::GET, Method::POST, Method::DELETE, Method::PATCH]);
+    let cors = config.cors.builder();
 
     let get_todo_route = warp::get()
         .and(warp::path!("todos" / Uuid))
         .and(warp::path::end())
         .and(with_jwt.clone())
         .and(with_store.clone())
-        .and_then(get_todo);
+        .and_then(move |id, user, store| with_timeout(timeout, get_todo(id, user, store)));
+
+    let todos_stream_route = warp::get()
+        .and(warp::path!("todos" / "stream"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(todos_stream);
+
+    // authenticate before the upgrade but let the socket report the failure,
+    // since browsers can't see the status of a rejected handshake
+    let ws_route = warp::path("ws")
+        .and(warp::path::end())
+        .and(warp::ws())
+        .and(
+            with_jwt
+                .clone()
+                .map(Some)
+                .or_else(|_| async { Ok::<(Option<UserContext>,), Rejection>((None,)) }),
+        )
+        .and(with_store.clone())
+        .and(with_max_task_length)
+        .and(warp::any().map(move || max_todos))
+        .and_then(ws);
+
+    let get_assigned_todos_route = warp::get()
+        .and(warp::path!("todos" / "assigned"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |user, store| with_timeout(timeout, get_assigned_todos(user, store)));
+
+    let get_archived_todos_route = warp::get()
+        .and(warp::path!("todos" / "archived"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |user, store| with_timeout(timeout, get_archived_todos(user, store)));
+
+    let max_page_size = config.max_page_size;
+    let get_changes_route = warp::get()
+        .and(warp::path!("todos" / "changes"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and(warp::any().map(move || max_page_size))
+        .and(warp::query::<ChangesQuery>())
+        .and_then(move |user, store, max_page_size, query| {
+            with_timeout(timeout, get_changes(user, store, max_page_size, query))
+        });
 
     let get_todos_route = warp::get()
         .and(warp::path("todos"))
         .and(warp::path::end())
         .and(with_jwt.clone())
         .and(with_store.clone())
-        .and_then(get_todos);
+        .and(warp::any().map(move || max_page_size))
+        .and(warp::header::optional::<String>("accept"))
+        .and(warp::query::<TodosQuery>())
+        .and_then(move |user, store, max_page_size, accept, query| {
+            with_timeout(
+                timeout,
+                get_todos(user, store, max_page_size, accept, query),
+            )
+        });
 
+    let idempotency_keys = config.idempotency_keys.clone();
     let add_todo_route = warp::post()
         .and(warp::path("todos"))
         .and(warp::path::end())
         .and(with_jwt.clone())
         .and(with_store.clone())
-        .and(warp::body::json())
-        .and_then(add_todo);
+        .and(with_max_task_length)
+        .and(warp::any().map(move || idempotency_keys.clone()))
+        .and(warp::header::optional::<String>("idempotency-key"))
+        .and(json_body(max_body_size))
+        .and_then(
+            move |user, store, max_task_length, idempotency_keys, idempotency_key, new_todo| {
+                with_timeout(
+                    timeout,
+                    add_todo(
+                        user,
+                        store,
+                        max_task_length,
+                        max_todos,
+                        idempotency_keys,
+                        idempotency_key,
+                        new_todo,
+                    ),
+                )
+            },
+        );
+
+    let max_batch_size = config.max_batch_size;
+    let add_todos_route = warp::post()
+        .and(warp::path!("todos" / "batch"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and(warp::any().map(move || max_batch_size))
+        .and(with_max_task_length)
+        .and(json_body(max_body_size))
+        .and_then(
+            move |user, store, max_batch_size, max_task_length, new_todos| {
+                with_timeout(
+                    timeout,
+                    add_todos(
+                        user,
+                        store,
+                        max_batch_size,
+                        max_task_length,
+                        max_todos,
+                        new_todos,
+                    ),
+                )
+            },
+        );
 
     let update_todo_route = warp::patch()
         .and(warp::path!("todos" / Uuid))
         .and(warp::path::end())
-        .and(warp::body::json())
+        .and(json_body(max_body_size))
+        .and(warp::header::optional::<String>("if-match"))
         .and(with_jwt.clone())
         .and(with_store.clone())
-        .and_then(update_todo);
+        .and(with_max_task_length)
+        .and_then(move |id, update, if_match, user, store, max_task_length| {
+            with_timeout(
+                timeout,
+                update_todo(id, update, if_match, user, store, max_task_length),
+            )
+        });
 
     let delete_todo_route = warp::delete()
         .and(warp::path!("todos" / Uuid))
         .and(warp::path::end())
-        .and(with_jwt)
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |id, user, store| with_timeout(timeout, delete_todo(id, user, store)));
+
+    let share_todo_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "share"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and(json_body(max_body_size))
+        .and_then(move |id, user, store, share| {
+            with_timeout(timeout, share_todo(id, user, store, share))
+        });
+
+    let archive_todo_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "archive"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |id, user, store| with_timeout(timeout, archive_todo(id, user, store)));
+
+    let unarchive_todo_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "unarchive"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |id, user, store| with_timeout(timeout, unarchive_todo(id, user, store)));
+
+    let complete_todo_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "complete"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |id, user, store| with_timeout(timeout, complete_todo(id, user, store)));
+
+    let uncomplete_todo_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "uncomplete"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |id, user, store| with_timeout(timeout, uncomplete_todo(id, user, store)));
+
+    let add_subtask_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "subtasks"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and(with_max_task_length)
+        .and(json_body(max_body_size))
+        .and_then(move |id, user, store, max_task_length, new_subtask| {
+            with_timeout(
+                timeout,
+                add_subtask(id, user, store, max_task_length, new_subtask),
+            )
+        });
+
+    let auto_complete_subtasks = config.auto_complete_subtasks;
+    let toggle_subtask_route = warp::post()
+        .and(warp::path!("todos" / Uuid / "subtasks" / Uuid / "toggle"))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and(warp::any().map(move || auto_complete_subtasks))
+        .and_then(move |id, subtask_id, user, store, auto_complete| {
+            with_timeout(
+                timeout,
+                toggle_subtask(id, subtask_id, user, store, auto_complete),
+            )
+        });
+
+    let remove_subtask_route = warp::delete()
+        .and(warp::path!("todos" / Uuid / "subtasks" / Uuid))
+        .and(warp::path::end())
+        .and(with_jwt.clone())
+        .and(with_store.clone())
+        .and_then(move |id, subtask_id, user, store| {
+            with_timeout(timeout, remove_subtask(id, subtask_id, user, store))
+        });
+
+    let get_user_route = warp::get()
+        .and(warp::path!("users" / String))
+        .and(warp::path::end())
+        .and(with_role("admin", with_jwt.clone()))
+        .and(with_store.clone())
+        .and_then(move |id, admin, store| with_timeout(timeout, get_user(id, admin, store)));
+
+    let delete_user_route = warp::delete()
+        .and(warp::path!("users" / String))
+        .and(warp::path::end())
+        .and(with_role("admin", with_jwt.clone()))
+        .and(with_store.clone())
+        .and_then(move |id, admin, store| with_timeout(timeout, delete_user(id, admin, store)));
+
+    let export_user_route = warp::get()
+        .and(warp::path!("users" / String / "export"))
+        .and(warp::path::end())
+        .and(with_role("admin", with_jwt.clone()))
+        .and(with_store.clone())
+        .and_then(move |id, admin, store| with_timeout(timeout, export_user(id, admin, store)));
+
+    let list_users_route = warp::get()
+        .and(warp::path("users"))
+        .and(warp::path::end())
+        .and(with_role("admin", with_jwt.clone()))
+        .and(with_store.clone())
+        .and(warp::any().map(move || max_page_size))
+        .and(warp::query::<UsersQuery>())
+        .and_then(move |admin, store, max_page_size, query| {
+            with_timeout(timeout, list_users(admin, store, max_page_size, query))
+        });
+
+    let get_store_stats_route = warp::get()
+        .and(warp::path!("admin" / "stats"))
+        .and(warp::path::end())
+        .and(with_role("admin", with_jwt.clone()))
+        .and(with_store.clone())
+        .and_then(move |admin, store| with_timeout(timeout, get_store_stats(admin, store)));
+
+    let purge_deleted_route = warp::post()
+        .and(warp::path!("todos" / "purge"))
+        .and(warp::path::end())
+        .and(with_role("admin", with_jwt))
         .and(with_store.clone())
-        .and_then(delete_todo);
+        .and(warp::query::<PurgeQuery>())
+        .and_then(move |admin, store, query| {
+            with_timeout(timeout, purge_deleted(admin, store, query))
+        });
+
+    let metrics_config = config.metrics.clone();
+    let metrics_route = warp::get()
+        .and(warp::path("metrics"))
+        .and(warp::path::end())
+        .and(warp::any().map(move || metrics_config.clone()))
+        .and_then(metrics);
 
     let userinfor_route = warp::get()
         .and(warp::path("userinfo"))
         .and(warp::path::end())
         .and(with_decoded)
         .and(with_store)
-        .and_then(user_info);
+        .and_then(move |userinfo, store| with_timeout(timeout, user_info(userinfo, store)));
 
     get_todo_route
+        .or(todos_stream_route)
+        .or(ws_route)
+        .or(get_assigned_todos_route)
+        .or(get_archived_todos_route)
+        .or(get_changes_route)
         .or(get_todos_route)
         .or(add_todo_route)
+        .or(add_todos_route)
         .or(update_todo_route)
         .or(delete_todo_route)
+        .or(share_todo_route)
+        .or(archive_todo_route)
+        .or(unarchive_todo_route)
+        .or(complete_todo_route)
+        .or(uncomplete_todo_route)
+        .or(add_subtask_route)
+        .or(toggle_subtask_route)
+        .or(remove_subtask_route)
+        .or(get_user_route)
+        .or(delete_user_route)
+        .or(export_user_route)
+        .or(list_users_route)
+        .or(get_store_stats_route)
+        .or(purge_deleted_route)
         .or(userinfor_route)
+        .or(metrics_route)
         .with(cors)
         .recover(return_error)
+        .with(warp::log::custom(move |info| {
+            if let Some(metrics) = &config.metrics {
+                metrics.observe(info);
+            }
+        }))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::auth::UserInfo;
-    use crate::error::Error;
-    use crate::model::Todo;
-    use crate::storage::UserContext;
+    use crate::model::{Todo, User};
+    use crate::routes::test_harness::*;
+    use crate::storage::{TodoStore, UserContext};
     use std::sync::Arc;
-    use warp::{http::HeaderMap, reject, Filter, Rejection};
-
-    fn with_mock_jwt(
-        user_context: UserContext,
-        is_valid: bool,
-    ) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
-        warp::header::headers_cloned()
-            .map(move |headers: HeaderMap| (headers.clone(), user_context.clone(), is_valid))
-            .and_then(
-                |(_headers, user_context, is_valid): (HeaderMap, UserContext, bool)| async move {
-                    match is_valid {
-                        true => Ok(user_context),
-                        false => Err(reject::custom(Error::InvalidToken)),
-                    }
-                },
-            )
+
+    #[tokio::test]
+    async fn test_invalid_token() {
+        let route = invalid_token_router();
+        let id = uuid::Uuid::new_v4();
+        let requests = [
+            ("GET", "/todos".to_string()),
+            ("POST", "/todos".to_string()),
+            ("POST", "/todos/batch".to_string()),
+            ("GET", "/todos/assigned".to_string()),
+            ("GET", "/todos/archived".to_string()),
+            ("GET", format!("/todos/{}", id)),
+            ("PATCH", format!("/todos/{}", id)),
+            ("DELETE", format!("/todos/{}", id)),
+            ("POST", format!("/todos/{}/share", id)),
+            ("POST", format!("/todos/{}/archive", id)),
+            ("POST", format!("/todos/{}/subtasks", id)),
+        ];
+        for (method, path) in requests {
+            let resp = warp::test::request()
+                .method(method)
+                .path(&path)
+                .json(&serde_json::json!({}))
+                .reply(&route)
+                .await;
+            assert_error(&resp, 401, "INVALID_TOKEN");
+        }
     }
 
-    fn with_mock_decode(
-        userinfo: UserInfo,
-    ) -> impl Filter<Extract = (UserInfo,), Error = Rejection> + Clone {
-        warp::header::headers_cloned()
-            .map(move |headers: HeaderMap| (headers.clone(), userinfo.clone()))
-            .and_then(|(_headers, claims): (HeaderMap, UserInfo)| async move {
-                Ok::<UserInfo, Rejection>(claims)
-            })
+    #[tokio::test]
+    async fn test_cross_tenant() {
+        let owner = user_context("1", "1");
+        let store = new_store();
+        let todos = seed_todos(&store, &owner, &["test task 1"]).await;
+        let id = todos[0].id.clone();
+        // same user id, different tenant
+        let route = test_router_with(
+            store.clone(),
+            user_context("2", "1"),
+            super::RouterConfig::default(),
+        );
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let listed: Vec<Todo> = serde_json::from_slice(resp.body()).unwrap();
+        assert!(listed.is_empty());
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/todos/{}", id))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 401, "UNAUTHORIZED");
+
+        let resp = warp::test::request()
+            .method("PATCH")
+            .path(&format!("/todos/{}", id))
+            .header("if-match", "*")
+            .json(&serde_json::json!({ "task": "stolen" }))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 401, "UNAUTHORIZED");
+
+        for path in [
+            format!("/todos/{}/share", id),
+            format!("/todos/{}/archive", id),
+        ] {
+            let resp = warp::test::request()
+                .method("POST")
+                .path(&path)
+                .json(&serde_json::json!({ "user_id": "1" }))
+                .reply(&route)
+                .await;
+            assert_error(&resp, 404, "NOT_FOUND");
+        }
+
+        let resp = warp::test::request()
+            .method("DELETE")
+            .path(&format!("/todos/{}", id))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 404, "NOT_FOUND");
+
+        let todo = store.get_todo(&owner, id).await.unwrap().unwrap();
+        assert_eq!(todo, todos[0]);
     }
 
     #[tokio::test]
     async fn test_add_todo() {
-        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
-        let user_context = UserContext {
-            tenant_id: "1".to_string(),
-            user_id: "1".to_string(),
-        };
-        let route = super::router(
-            store,
-            with_mock_jwt(user_context, true),
-            with_mock_decode(UserInfo::default()),
-        );
+        let (_store, route) = test_router(user_context("1", "1"));
         let resp = warp::test::request()
             .method("POST")
             .path("/todos")
@@ -137,11 +470,14 @@ mod tests {
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
         );
 
         let resp = warp::test::request()
@@ -175,26 +511,261 @@ mod tests {
         let body = resp.body();
         let todos: Vec<Todo> = serde_json::from_slice(body).unwrap();
         assert_eq!(todos.len(), 2);
+        assert_eq!(resp.headers()["X-Total-Count"], "2");
     }
 
     #[tokio::test]
-    async fn test_get_todo_not_found() {
+    async fn test_get_todos_paginated() {
         let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+
+        for i in 1..=3 {
+            let resp = warp::test::request()
+                .method("POST")
+                .path("/todos")
+                .json(&serde_json::json!({
+                    "task": format!("test task {}", i),
+                    "completed": false
+                }))
+                .reply(&route)
+                .await;
+            assert_eq!(resp.status(), 201);
+        }
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?limit=2")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let page: super::TodosPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.data.len(), 2);
+        let cursor = page.next_cursor.unwrap();
+        assert_eq!(cursor, page.data[1].id);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/todos?limit=2&after={}", cursor))
+            .reply(&route)
+            .await;
+        let page: super::TodosPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.data.len(), 1);
+        assert!(page.data[0].id > cursor);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_todo_quota() {
+        let store = new_store();
+        let ctx = user_context("1", "1");
+        let config = super::RouterConfig {
+            max_todos_per_user: Some(2),
+            ..super::RouterConfig::default()
+        };
+        let route = test_router_with(store.clone(), ctx.clone(), config);
+        let todos = seed_todos(&store, &ctx, &["one"]).await;
+
+        let add = |task: &str| {
+            warp::test::request()
+                .method("POST")
+                .path("/todos")
+                .json(&serde_json::json!({ "task": task, "completed": false }))
+        };
+        let resp = add("two").reply(&route).await;
+        assert_eq!(resp.status(), 201);
+        let resp = add("three").reply(&route).await;
+        assert_error(&resp, 403, "QUOTA_EXCEEDED");
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos/batch")
+            .json(&serde_json::json!([{ "task": "three", "completed": false }]))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 403, "QUOTA_EXCEEDED");
+
+        // existing todos stay readable
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        let listed: Vec<Todo> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(listed.len(), 2);
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/todos/{}", todos[0].id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        // deleting one frees up room
+        store.delete_todo(&ctx, todos[0].id.clone()).await.unwrap();
+        let resp = add("three").reply(&route).await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_empty() {
+        let (_store, route) = test_router(user_context("1", "new user"));
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.body(), "[]");
+        assert_eq!(resp.headers()["X-Total-Count"], "0");
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?limit=10")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let page: super::TodosPage = serde_json::from_slice(resp.body()).unwrap();
+        assert!(page.data.is_empty());
+        assert!(page.next_cursor.is_none());
+
+        // a single missing todo is still a 404
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/todos/{}", uuid::Uuid::new_v4()))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 404, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_envelope() {
+        let store = new_store();
+        let ctx = user_context("1", "1");
+        let config = super::RouterConfig {
+            max_page_size: 2,
+            ..super::RouterConfig::default()
+        };
+        let route = test_router_with(store.clone(), ctx.clone(), config);
+        seed_todos(&store, &ctx, &["one", "two", "three"]).await;
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?envelope=true")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let page: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page["data"].as_array().unwrap().len(), 3);
+        assert_eq!(page["total"], 3);
+        assert!(page["next_cursor"].is_null());
+        assert!(page["limit"].is_null());
+        assert_eq!(page["offset"], 0);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?envelope=true&limit=10")
+            .reply(&route)
+            .await;
+        let page: super::TodosPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(
+            page.envelope,
+            Some(super::Envelope {
+                total: 3,
+                limit: Some(2),
+                offset: 0,
+            })
         );
+        assert_eq!(page.next_cursor.as_ref(), Some(&page.data[1].id));
+
+        // without it, responses keep their shape
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?limit=10")
+            .reply(&route)
+            .await;
+        let page: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert!(page.get("total").is_none());
+        assert!(page.get("offset").is_none());
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?envelope=false")
+            .reply(&route)
+            .await;
+        let todos: Vec<Todo> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(todos.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_page_size_capped() {
+        let store = new_store();
+        let ctx = user_context("1", "1");
+        let config = super::RouterConfig {
+            max_page_size: 2,
+            ..super::RouterConfig::default()
+        };
+        let route = test_router_with(store.clone(), ctx.clone(), config);
+        seed_todos(&store, &ctx, &["one", "two", "three"]).await;
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?limit=1000000")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let page: super::TodosPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(page.next_cursor.as_ref(), Some(&page.data[1].id));
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_csv() {
+        let (store, route) = test_router(user_context("1", "1"));
+        seed_todos(&store, &user_context("1", "1"), &["say \"hi\", then leave"]).await;
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .header("Accept", "text/csv")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()["Content-Type"], "text/csv; charset=utf-8");
+        let body = std::str::from_utf8(resp.body()).unwrap();
+        let mut lines = body.lines();
+        assert_eq!(lines.next(), Some("id,task,completed,created_at,due_date"));
+        assert!(lines
+            .next()
+            .unwrap()
+            .contains(r#","say ""hi"", then leave",false,"#));
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.headers()["Content-Type"], "application/json");
+    }
+
+    #[tokio::test]
+    async fn test_get_todo_not_found() {
+        let (_store, route) = test_router(user_context("1", "1"));
         let resp = warp::test::request()
             .method("GET")
             .path("/todos/00000000-0000-0000-0000-000000000000")
             .reply(&route)
             .await;
-        assert_eq!(resp.status(), 404);
+        assert_error(&resp, 404, "NOT_FOUND");
     }
 
     #[tokio::test]
@@ -203,11 +774,14 @@ mod tests {
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
         );
 
         let resp = warp::test::request()
@@ -261,22 +835,26 @@ mod tests {
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
         );
         let resp = warp::test::request()
             .method("PATCH")
             .path("/todos/00000000-0000-0000-0000-000000000000")
+            .header("if-match", "\"1\"")
             .json(&serde_json::json!({
                 "task": "test task 1",
                 "completed": false
             }))
             .reply(&route)
             .await;
-        assert_eq!(resp.status(), 404);
+        assert_error(&resp, 404, "NOT_FOUND");
     }
 
     #[tokio::test]
@@ -285,11 +863,14 @@ mod tests {
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
         );
 
         let resp = warp::test::request()
@@ -314,6 +895,14 @@ mod tests {
         assert_eq!(todos.len(), 1);
         let id = todos[0].id.clone();
 
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/todos/{}", id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let etag = resp.headers()["etag"].clone();
+
         let resp = warp::test::request()
             .method("PATCH")
             .path(&format!("/todos/{}", id))
@@ -323,12 +912,36 @@ mod tests {
             }))
             .reply(&route)
             .await;
-        assert_eq!(resp.status(), 200);
-        let body = resp.body();
-        let todo: Todo = serde_json::from_slice(body).unwrap();
-        assert_eq!(todo.id, id);
+        assert_error(&resp, 428, "PRECONDITION_REQUIRED");
+
+        let resp = warp::test::request()
+            .method("PATCH")
+            .path(&format!("/todos/{}", id))
+            .header("if-match", etag.clone())
+            .json(&serde_json::json!({
+                "task": "test task 1",
+                "completed": true
+            }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_ne!(resp.headers()["etag"], etag);
+        let body = resp.body();
+        let todo: Todo = serde_json::from_slice(body).unwrap();
+        assert_eq!(todo.id, id);
         assert_eq!(todo.task, "test task 1");
         assert!(todo.completed);
+
+        let resp = warp::test::request()
+            .method("PATCH")
+            .path(&format!("/todos/{}", id))
+            .header("if-match", etag)
+            .json(&serde_json::json!({
+                "completed": false
+            }))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 412, "PRECONDITION_FAILED");
     }
 
     #[tokio::test]
@@ -337,18 +950,251 @@ mod tests {
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
         );
         let resp = warp::test::request()
             .method("DELETE")
             .path("/todos/00000000-0000-0000-0000-000000000000")
             .reply(&route)
             .await;
-        assert_eq!(resp.status(), 404);
+        assert_error(&resp, 404, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_share_todo() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&serde_json::json!({
+                "id": id,
+                "task": "test task 1",
+                "completed": false
+            }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/share", id))
+            .json(&serde_json::json!({ "user_id": "2" }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let todo: Todo = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(todo.shared_with, vec!["2".to_string()]);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/share", uuid::Uuid::new_v4()))
+            .json(&serde_json::json!({ "user_id": "2" }))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 404, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_subtasks() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig {
+                auto_complete_subtasks: true,
+                ..super::RouterConfig::default()
+            },
+        );
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&serde_json::json!({
+                "id": id,
+                "task": "test task 1",
+                "completed": false
+            }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/subtasks", id))
+            .json(&serde_json::json!({ "text": "" }))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 422, "VALIDATION_FAILED");
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/subtasks", id))
+            .json(&serde_json::json!({ "text": "step 1" }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+        let todo: Todo = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(todo.subtasks.len(), 1);
+        let subtask_id = todo.subtasks[0].id.clone();
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/subtasks/{}/toggle", id, subtask_id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let todo: Todo = serde_json::from_slice(resp.body()).unwrap();
+        assert!(todo.subtasks[0].done);
+        assert!(todo.completed);
+
+        let resp = warp::test::request()
+            .method("DELETE")
+            .path(&format!("/todos/{}/subtasks/{}", id, subtask_id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let todo: Todo = serde_json::from_slice(resp.body()).unwrap();
+        assert!(todo.subtasks.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_archive_todo() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&serde_json::json!({
+                "id": id,
+                "task": "test task 1",
+                "completed": true
+            }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/archive", id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let list = |path: &'static str| {
+            let route = route.clone();
+            async move {
+                let resp = warp::test::request()
+                    .method("GET")
+                    .path(path)
+                    .reply(&route)
+                    .await;
+                assert_eq!(resp.status(), 200);
+                serde_json::from_slice::<Vec<Todo>>(resp.body()).unwrap()
+            }
+        };
+        assert!(list("/todos").await.is_empty());
+        assert_eq!(list("/todos?include_archived=true").await.len(), 1);
+        assert_eq!(list("/todos/archived").await.len(), 1);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/unarchive", id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(list("/todos").await.len(), 1);
+        assert!(list("/todos/archived").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_total_matches_filter() {
+        let store = new_store();
+        let ctx = user_context("1", "1");
+        let route = test_router_with(store.clone(), ctx.clone(), super::RouterConfig::default());
+        let todos = seed_todos(&store, &ctx, &["one", "two", "three"]).await;
+        store.archive_todo(&ctx, todos[0].id.clone()).await.unwrap();
+        let update = crate::model::UpdateTodo {
+            task: None,
+            completed: None,
+            tags: Some(vec!["work".to_string()]),
+            assigned_to: None,
+        };
+        store
+            .update_todo(&ctx, todos[1].id.clone(), update, None)
+            .await
+            .unwrap();
+
+        for (path, total) in [
+            ("/todos", 2),
+            ("/todos?include_archived=true", 3),
+            ("/todos?tag=work", 1),
+            ("/todos?limit=1", 2),
+        ] {
+            let resp = warp::test::request()
+                .method("GET")
+                .path(path)
+                .reply(&route)
+                .await;
+            assert_eq!(resp.status(), 200);
+            assert_eq!(
+                resp.headers()["X-Total-Count"],
+                total.to_string(),
+                "{}",
+                path
+            );
+        }
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?envelope=true")
+            .reply(&route)
+            .await;
+        let page: super::TodosPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            page.envelope.map(|envelope| envelope.total),
+            Some(page.data.len() as u64)
+        );
     }
 
     #[tokio::test]
@@ -357,11 +1203,14 @@ mod tests {
         let user_context = UserContext {
             tenant_id: "1".to_string(),
             user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let route = super::router(
             store,
             with_mock_jwt(user_context, true),
             with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
         );
 
         let resp = warp::test::request()
@@ -403,4 +1252,829 @@ mod tests {
         let todos: Vec<Todo> = serde_json::from_slice(body).unwrap();
         assert_eq!(todos.len(), 0);
     }
+
+    async fn seed_user(store: &crate::storage::MemStore, tenant_id: &str) -> User {
+        let user = User::new(
+            uuid::Uuid::new_v4().to_string(),
+            "name".to_string(),
+            "email".to_string(),
+            tenant_id.to_string(),
+        );
+        store
+            .users
+            .write()
+            .await
+            .insert(user.external_id.clone(), user.clone());
+        user
+    }
+
+    #[tokio::test]
+    async fn test_get_user() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user = seed_user(&store, "1").await;
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec!["admin".to_string()],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/users/{}", user.id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let found: User = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(found.id, user.id);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/users/missing")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 404, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_export_user() {
+        let store = new_store();
+        let user = seed_user(&store, "1").await;
+        let owner = user_context("1", &user.id);
+        let todos = seed_todos(&store, &owner, &["a", "b"]).await;
+        store
+            .delete_todo(&owner, todos[1].id.clone())
+            .await
+            .unwrap();
+        seed_todos(&store, &user_context("1", "someone else"), &["c"]).await;
+        let mut admin = user_context("1", "1");
+        admin.roles = vec!["admin".to_string()];
+        let route = test_router_with(store.clone(), admin, super::RouterConfig::default());
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/users/{}/export", user.id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let export: crate::storage::UserExport = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(export.user.id, user.id);
+        assert_eq!(export.user.email, user.email);
+        let mut ids: Vec<&str> = todos.iter().map(|todo| todo.id.as_str()).collect();
+        ids.sort();
+        let exported: Vec<&str> = export.todos.iter().map(|todo| todo.id.as_str()).collect();
+        assert_eq!(exported, ids);
+
+        // users in other tenants look missing
+        let other = seed_user(&store, "2").await;
+        for id in [other.id.as_str(), "missing"] {
+            let resp = warp::test::request()
+                .method("GET")
+                .path(&format!("/users/{}/export", id))
+                .reply(&route)
+                .await;
+            assert_error(&resp, 404, "NOT_FOUND");
+        }
+
+        let route = test_router_with(store, owner, super::RouterConfig::default());
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/users/{}/export", user.id))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 403, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let store = new_store();
+        let user = seed_user(&store, "1").await;
+        let owner = user_context("1", &user.id);
+        seed_todos(&store, &owner, &["a", "b"]).await;
+        let kept = seed_todos(&store, &user_context("1", "someone else"), &["c"]).await;
+        let other = seed_user(&store, "2").await;
+        let mut admin = user_context("1", "1");
+        admin.roles = vec!["admin".to_string()];
+        let route = test_router_with(store.clone(), admin, super::RouterConfig::default());
+
+        let resp = warp::test::request()
+            .method("DELETE")
+            .path(&format!("/users/{}", user.id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let deletion: crate::storage::UserDeletion = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(deletion.user_id, user.id);
+        assert_eq!(deletion.todos_deleted, 2);
+        assert!(store.users.read().await.get(&user.external_id).is_none());
+        let remaining: Vec<String> = store.objects.read().await.keys().cloned().collect();
+        assert_eq!(remaining, vec![kept[0].id.clone()]);
+
+        for id in [user.id.as_str(), other.id.as_str()] {
+            let resp = warp::test::request()
+                .method("DELETE")
+                .path(&format!("/users/{}", id))
+                .reply(&route)
+                .await;
+            assert_error(&resp, 404, "NOT_FOUND");
+        }
+        assert!(store.users.read().await.get(&other.external_id).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_get_user_other_tenant() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user = seed_user(&store, "2").await;
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec!["admin".to_string()],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/users/{}", user.id))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 404, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_forbidden() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user = seed_user(&store, "1").await;
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/users/{}", user.id))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 403, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_list_users() {
+        let store = new_store();
+        let mut users = vec![];
+        for _ in 0..3 {
+            users.push(seed_user(&store, "1").await);
+        }
+        seed_user(&store, "2").await;
+        users.sort_by(|a, b| a.id.cmp(&b.id));
+        let mut admin = user_context("1", "1");
+        admin.roles = vec!["admin".to_string()];
+        let route = test_router_with(store, admin, super::RouterConfig::default());
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/users?limit=2&offset=1")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let page: Vec<serde_json::Value> = serde_json::from_slice(resp.body()).unwrap();
+        let ids: Vec<&str> = page.iter().map(|u| u["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec![users[1].id.as_str(), users[2].id.as_str()]);
+        // the summary leaves out contact details
+        assert!(page[0].get("email").is_none());
+        assert!(page[0].get("external_id").is_none());
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/users")
+            .reply(&route)
+            .await;
+        let page: Vec<serde_json::Value> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.len(), 3);
+        assert!(page.iter().all(|u| u["tenant_id"] == "1"));
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/users?tenant_id=2")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 403, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_complete_todo() {
+        let ctx = user_context("1", "1");
+        let (store, route) = test_router(ctx.clone());
+        let todos = seed_todos(&store, &ctx, &["a"]).await;
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/complete", todos[0].id))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()["etag"], "\"2\"");
+        let todo: Todo = serde_json::from_slice(resp.body()).unwrap();
+        assert!(todo.completed);
+        assert_eq!(todo.task, "a");
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/uncomplete", todos[0].id))
+            .reply(&route)
+            .await;
+        let todo: Todo = serde_json::from_slice(resp.body()).unwrap();
+        assert!(!todo.completed);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path(&format!("/todos/{}/complete", uuid::Uuid::new_v4()))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 404, "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_get_changes() {
+        let ctx = user_context("1", "1");
+        let (store, route) = test_router(ctx.clone());
+        let todos = seed_todos(&store, &ctx, &["a", "b"]).await;
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos/changes")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let page: super::ChangesPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.changes.len(), 2);
+        let cursor = page.cursor.unwrap();
+
+        store.delete_todo(&ctx, todos[0].id.clone()).await.unwrap();
+        let path = format!("/todos/changes?since={}", cursor);
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&path)
+            .reply(&route)
+            .await;
+        let page: super::ChangesPage = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(page.changes.len(), 1);
+        assert_eq!(page.changes[0].id, todos[0].id);
+        assert!(page.changes[0].deleted_at.is_some());
+        let cursor = page.cursor.unwrap();
+
+        // nothing new: the cursor stays put
+        let resp = warp::test::request()
+            .method("GET")
+            .path(&format!("/todos/changes?since={}", cursor))
+            .reply(&route)
+            .await;
+        let page: super::ChangesPage = serde_json::from_slice(resp.body()).unwrap();
+        assert!(page.changes.is_empty());
+        assert_eq!(page.cursor, Some(cursor));
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos/changes?since=yesterday")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 422, "VALIDATION_FAILED");
+    }
+
+    #[tokio::test]
+    async fn test_get_store_stats() {
+        let store = new_store();
+        seed_user(&store, "1").await;
+        seed_todos(&store, &user_context("1", "1"), &["a", "b"]).await;
+        seed_todos(&store, &user_context("2", "2"), &["c"]).await;
+        let mut admin = user_context("1", "1");
+        admin.roles = vec!["admin".to_string()];
+        let route = test_router_with(store.clone(), admin, super::RouterConfig::default());
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/admin/stats")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let stats: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(
+            stats,
+            serde_json::json!({
+                "todos": 3,
+                "users": 1,
+                "tenants": {
+                    "1": { "todos": 2, "users": 1 },
+                    "2": { "todos": 1, "users": 0 },
+                },
+            })
+        );
+
+        let route = test_router_with(
+            store,
+            user_context("1", "1"),
+            super::RouterConfig::default(),
+        );
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/admin/stats")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 403, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted() {
+        let ctx = user_context("1", "1");
+        let store = new_store();
+        let todos = seed_todos(&store, &ctx, &["a", "b"]).await;
+        store.delete_todo(&ctx, todos[0].id.clone()).await.unwrap();
+        let mut admin = ctx.clone();
+        admin.roles = vec!["admin".to_string()];
+        let route = test_router_with(store.clone(), admin, super::RouterConfig::default());
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos/purge")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let result: super::PurgeResult = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(result.purged, 0);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos/purge?older_than_days=0")
+            .reply(&route)
+            .await;
+        let result: super::PurgeResult = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(result.purged, 1);
+
+        let route = test_router_with(store, ctx, super::RouterConfig::default());
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos/purge?older_than_days=0")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 403, "FORBIDDEN");
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_invalid_task() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig {
+                max_task_length: 10,
+                ..super::RouterConfig::default()
+            },
+        );
+
+        for task in ["", "   ", "a task that is too long"] {
+            let resp = warp::test::request()
+                .method("POST")
+                .path("/todos")
+                .json(&serde_json::json!({
+                    "task": task,
+                    "completed": false
+                }))
+                .reply(&route)
+                .await;
+            assert_error(&resp, 422, "VALIDATION_FAILED");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store.clone(),
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig {
+                request_timeout: std::time::Duration::from_millis(10),
+                ..super::RouterConfig::default()
+            },
+        );
+
+        // Holding the write lock stalls the store until the timeout fires.
+        let _guard = store.objects.write().await;
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 504, "TIMEOUT");
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_conflict() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        let new_todo = serde_json::json!({
+            "id": "00000000-0000-0000-0000-000000000001",
+            "task": "test task 1",
+            "completed": false
+        });
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&new_todo)
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&new_todo)
+            .reply(&route)
+            .await;
+        assert_error(&resp, 409, "CONFLICT");
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_by_tag() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        for (task, tag) in [("test task 1", "work"), ("test task 2", "home")] {
+            let resp = warp::test::request()
+                .method("POST")
+                .path("/todos")
+                .json(&serde_json::json!({
+                    "task": task,
+                    "completed": false,
+                    "tags": [tag]
+                }))
+                .reply(&route)
+                .await;
+            assert_eq!(resp.status(), 201);
+        }
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos?tag=work")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let todos: Vec<Todo> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].tags, vec!["work".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let config = super::RouterConfig {
+            rate_limiter: Some(crate::rate_limit::RateLimiter::new(
+                1,
+                std::time::Duration::from_secs(60),
+            )),
+            ..Default::default()
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            config,
+        );
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos")
+            .reply(&route)
+            .await;
+        assert_error(&resp, 429, "RATE_LIMITED");
+        assert_eq!(resp.headers()["retry-after"], "60");
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_idempotency_key() {
+        let (store, route) = test_router(user_context("1", "1"));
+        let request = || {
+            warp::test::request()
+                .method("POST")
+                .path("/todos")
+                .header("idempotency-key", "add-milk")
+                .json(&serde_json::json!({ "task": "buy milk", "completed": false }))
+        };
+        assert_eq!(request().reply(&route).await.status(), 201);
+        assert_eq!(request().reply(&route).await.status(), 201);
+        assert_eq!(store.objects.read().await.len(), 1);
+
+        // the same key from another user adds their own todo
+        let other = test_router_with(
+            store.clone(),
+            user_context("1", "2"),
+            super::RouterConfig::default(),
+        );
+        assert_eq!(request().reply(&other).await.status(), 201);
+        assert_eq!(store.objects.read().await.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_body_too_large() {
+        let config = super::RouterConfig {
+            max_body_size: 64,
+            ..Default::default()
+        };
+        let route = test_router_with(new_store(), user_context("1", "1"), config);
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&serde_json::json!({ "task": "x".repeat(100), "completed": false }))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 413, "PAYLOAD_TOO_LARGE");
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos")
+            .json(&serde_json::json!({ "task": "x", "completed": false }))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+    }
+
+    #[tokio::test]
+    async fn test_cors_allowed_origins() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let config = super::RouterConfig {
+            cors: super::CorsConfig {
+                allowed_origins: Some(vec!["https://todos.example.com".to_string()]),
+                allow_credentials: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            config,
+        );
+        let resp = warp::test::request()
+            .method("OPTIONS")
+            .path("/todos")
+            .header("origin", "https://todos.example.com")
+            .header("access-control-request-method", "GET")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        assert_eq!(
+            resp.headers()["access-control-allow-origin"],
+            "https://todos.example.com"
+        );
+        assert_eq!(resp.headers()["access-control-allow-credentials"], "true");
+
+        let resp = warp::test::request()
+            .method("OPTIONS")
+            .path("/todos")
+            .header("origin", "https://evil.example.com")
+            .header("access-control-request-method", "GET")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn test_ws() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        let mut client = warp::test::ws().path("/ws").handshake(route).await.unwrap();
+
+        client
+            .send_text(r#"{"type": "add", "todo": {"task": "ws task", "completed": false}}"#)
+            .await;
+        let msg: serde_json::Value =
+            serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+        assert_eq!(msg["type"], "event");
+        assert_eq!(msg["event"]["type"], "added");
+        assert_eq!(msg["event"]["todo"]["task"], "ws task");
+        let id = msg["event"]["todo"]["id"].as_str().unwrap().to_string();
+
+        client
+            .send_text(format!(r#"{{"type": "complete", "id": "{}"}}"#, id))
+            .await;
+        let msg: serde_json::Value =
+            serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+        assert_eq!(msg["event"]["type"], "updated");
+        assert_eq!(msg["event"]["todo"]["completed"], true);
+
+        client
+            .send_text(r#"{"type": "delete", "id": "00000000-0000-0000-0000-000000000000"}"#)
+            .await;
+        let msg: serde_json::Value =
+            serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+        assert_eq!(msg["type"], "error");
+        assert_eq!(msg["code"], "NOT_FOUND");
+    }
+
+    #[tokio::test]
+    async fn test_ws_todo_quota() {
+        let store = new_store();
+        let ctx = user_context("1", "1");
+        let config = super::RouterConfig {
+            max_todos_per_user: Some(1),
+            ..super::RouterConfig::default()
+        };
+        let route = test_router_with(store.clone(), ctx.clone(), config);
+        seed_todos(&store, &ctx, &["one"]).await;
+        let mut client = warp::test::ws().path("/ws").handshake(route).await.unwrap();
+
+        client
+            .send_text(r#"{"type": "add", "todo": {"task": "two", "completed": false}}"#)
+            .await;
+        let msg: serde_json::Value =
+            serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+        assert_eq!(msg["type"], "error");
+        assert_eq!(msg["code"], "QUOTA_EXCEEDED");
+        assert_eq!(store.count_todos(&ctx, None).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_ws_invalid_token() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, false),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig::default(),
+        );
+        let mut client = warp::test::ws().path("/ws").handshake(route).await.unwrap();
+        // the test client hides the close frame, so only the closure is observable
+        client.recv_closed().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_add_todos() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig {
+                max_batch_size: 2,
+                ..super::RouterConfig::default()
+            },
+        );
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos/batch")
+            .json(&serde_json::json!([
+                { "task": "test task 1", "completed": false },
+                { "task": "test task 2", "completed": true }
+            ]))
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 201);
+        let todos: Vec<Todo> = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(todos.len(), 2);
+        assert!(!todos[0].id.is_empty());
+        assert_eq!(todos[1].task, "test task 2");
+
+        let resp = warp::test::request()
+            .method("POST")
+            .path("/todos/batch")
+            .json(&serde_json::json!([
+                { "task": "test task 3", "completed": false },
+                { "task": "test task 4", "completed": false },
+                { "task": "test task 5", "completed": false }
+            ]))
+            .reply(&route)
+            .await;
+        assert_error(&resp, 413, "PAYLOAD_TOO_LARGE");
+    }
+
+    #[tokio::test]
+    async fn test_metrics() {
+        let store = Arc::new(crate::storage::MemStore::new("test.json".to_string()));
+        let user_context = UserContext {
+            tenant_id: "1".to_string(),
+            user_id: "1".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let route = super::router(
+            store,
+            with_mock_jwt(user_context, true),
+            with_mock_decode(UserInfo::default()),
+            super::RouterConfig {
+                metrics: Some(crate::metrics::Metrics::new()),
+                ..super::RouterConfig::default()
+            },
+        );
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/todos/00000000-0000-0000-0000-000000000000")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 404);
+
+        let resp = warp::test::request()
+            .method("GET")
+            .path("/metrics")
+            .reply(&route)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let body = String::from_utf8(resp.body().to_vec()).unwrap();
+        assert!(
+            body.contains(r#"http_requests_total{method="GET",route="/todos/:id",status="404"} 1"#)
+        );
+        assert!(body.contains("http_request_duration_seconds_bucket"));
+    }
 }
@@ -0,0 +1,90 @@
+use crate::idempotency::IdempotencyKeys;
+use crate::metrics::Metrics;
+use crate::rate_limit::RateLimiter;
+use std::time::Duration;
+use warp::http::Method;
+
+/// CORS settings; the default allows any origin, matching the original behavior.
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    /// Allowed origins; `None` allows any origin.
+    pub allowed_origins: Option<Vec<String>>,
+    pub allowed_methods: Vec<Method>,
+    pub allowed_headers: Vec<String>,
+    /// Only honored when `allowed_origins` is set.
+    pub allow_credentials: bool,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: None,
+            allowed_methods: vec![Method::GET, Method::POST, Method::DELETE, Method::PATCH],
+            allowed_headers: vec![
+                "User-Agent".to_string(),
+                "Content-Type".to_string(),
+                "Authorization".to_string(),
+                "Idempotency-Key".to_string(),
+            ],
+            allow_credentials: false,
+        }
+    }
+}
+
+impl CorsConfig {
+    pub fn builder(&self) -> warp::cors::Builder {
+        let cors = warp::cors()
+            .allow_headers(self.allowed_headers.iter().map(String::as_str))
+            .allow_methods(self.allowed_methods.clone());
+        match &self.allowed_origins {
+            Some(origins) => cors
+                .allow_origins(origins.iter().map(String::as_str))
+                .allow_credentials(self.allow_credentials),
+            None => cors.allow_any_origin(),
+        }
+    }
+}
+
+/// Settings applied by `router()` on top of the individual routes.
+#[derive(Clone)]
+pub struct RouterConfig {
+    /// Per-user rate limiter; `None` disables rate limiting.
+    pub rate_limiter: Option<RateLimiter>,
+    pub cors: CorsConfig,
+    /// Largest number of todos accepted by `POST /todos/batch`.
+    pub max_batch_size: usize,
+    /// Largest `limit` honored by `GET /todos`; bigger requests are capped.
+    pub max_page_size: u64,
+    /// `Idempotency-Key` values recently used with `POST /todos`.
+    pub idempotency_keys: IdempotencyKeys,
+    /// Largest JSON request body, in bytes.
+    pub max_body_size: u64,
+    /// Longest task, in characters, accepted when adding or updating todos.
+    pub max_task_length: usize,
+    /// Request metrics served at `/metrics`; `None` disables the route.
+    pub metrics: Option<Metrics>,
+    /// Upper bound on handler time; streaming routes are exempt.
+    pub request_timeout: Duration,
+    /// Complete a todo when its last open subtask is checked off.
+    pub auto_complete_subtasks: bool,
+    /// Most todos a user may have; `None` means no limit.
+    pub max_todos_per_user: Option<u64>,
+}
+
+impl Default for RouterConfig {
+    fn default() -> Self {
+        Self {
+            rate_limiter: None,
+            cors: CorsConfig::default(),
+            max_batch_size: 100,
+            max_page_size: 100,
+            idempotency_keys: IdempotencyKeys::new(Duration::from_secs(24 * 60 * 60)),
+            max_body_size: 64 * 1024,
+            max_task_length: 500,
+            metrics: None,
+            request_timeout: Duration::from_secs(30),
+            auto_complete_subtasks: false,
+            max_todos_per_user: None,
+        }
+    }
+}
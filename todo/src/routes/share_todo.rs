@@ -0,0 +1,21 @@
+use crate::storage::store::{TodoStore, UserContext};
+use serde::Deserialize;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ShareTodo {
+    pub user_id: String,
+}
+
+pub async fn share_todo(
+    id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+    share: ShareTodo,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let todo = store
+        .share_todo(&user, id.to_string(), share.user_id)
+        .await?;
+    Ok(warp::reply::json(&todo))
+}
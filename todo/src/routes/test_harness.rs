@@ -0,0 +1,116 @@
+//! Helpers for driving `router()` in tests with a real `MemStore` behind
+//! mock authentication filters.
+use super::{router, RouterConfig};
+use crate::auth::UserInfo;
+use crate::error::{Error, ErrorResponse};
+use crate::model::{NewTodo, Todo};
+use crate::storage::{MemStore, TodoStore, UserContext};
+use std::sync::Arc;
+use warp::{http::HeaderMap, reject, Filter, Rejection};
+
+pub fn user_context(tenant_id: &str, user_id: &str) -> UserContext {
+    UserContext {
+        tenant_id: tenant_id.to_string(),
+        user_id: user_id.to_string(),
+        roles: vec![],
+        scopes: vec![],
+    }
+}
+
+/// Stands in for `with_jwt`: yields `user_context`, or rejects with
+/// `InvalidToken` when `is_valid` is false.
+pub fn with_mock_jwt(
+    user_context: UserContext,
+    is_valid: bool,
+) -> impl Filter<Extract = (UserContext,), Error = Rejection> + Clone {
+    warp::header::headers_cloned()
+        .map(move |headers: HeaderMap| (headers.clone(), user_context.clone(), is_valid))
+        .and_then(
+            |(_headers, user_context, is_valid): (HeaderMap, UserContext, bool)| async move {
+                match is_valid {
+                    true => Ok(user_context),
+                    false => Err(reject::custom(Error::InvalidToken)),
+                }
+            },
+        )
+}
+
+pub fn with_mock_decode(
+    userinfo: UserInfo,
+) -> impl Filter<Extract = (UserInfo,), Error = Rejection> + Clone {
+    warp::header::headers_cloned()
+        .map(move |headers: HeaderMap| (headers.clone(), userinfo.clone()))
+        .and_then(|(_headers, claims): (HeaderMap, UserInfo)| async move {
+            Ok::<UserInfo, Rejection>(claims)
+        })
+}
+
+pub fn new_store() -> Arc<MemStore> {
+    Arc::new(MemStore::new("test.json".to_string()))
+}
+
+/// Router over `store` that authenticates every request as `user_context`.
+/// Build several over one store to exercise cross-user and cross-tenant access.
+pub fn test_router_with(
+    store: Arc<MemStore>,
+    user_context: UserContext,
+    config: RouterConfig,
+) -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone {
+    router(
+        store,
+        with_mock_jwt(user_context, true),
+        with_mock_decode(UserInfo::default()),
+        config,
+    )
+}
+
+/// Router over a fresh store, returned so tests can seed or inspect it.
+pub fn test_router(
+    user_context: UserContext,
+) -> (
+    Arc<MemStore>,
+    impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone,
+) {
+    let store = new_store();
+    let route = test_router_with(store.clone(), user_context, RouterConfig::default());
+    (store, route)
+}
+
+/// Router whose authentication always fails as if the token were invalid.
+pub fn invalid_token_router() -> impl Filter<Extract = impl warp::Reply, Error = Rejection> + Clone
+{
+    router(
+        new_store(),
+        with_mock_jwt(user_context("1", "1"), false),
+        with_mock_decode(UserInfo::default()),
+        RouterConfig::default(),
+    )
+}
+
+/// Adds a todo per task for `ctx`, returning them in insertion order.
+pub async fn seed_todos(store: &MemStore, ctx: &UserContext, tasks: &[&str]) -> Vec<Todo> {
+    let new_todos = tasks
+        .iter()
+        .map(|task| NewTodo {
+            id: None,
+            task: task.to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        })
+        .collect();
+    store.add_todos(ctx, new_todos).await.unwrap()
+}
+
+pub fn assert_error(
+    resp: &warp::http::Response<warp::hyper::body::Bytes>,
+    status: u16,
+    code: &str,
+) {
+    assert_eq!(resp.status(), status);
+    assert_eq!(resp.headers()["content-type"], "application/json");
+    let error: ErrorResponse = serde_json::from_slice(resp.body()).unwrap();
+    assert_eq!(error.code, code);
+    assert!(!error.message.is_empty());
+}
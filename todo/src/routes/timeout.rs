@@ -0,0 +1,17 @@
+use crate::error::Error;
+use std::future::Future;
+use std::time::Duration;
+use warp::{reject, Rejection};
+
+/// Races a handler against `timeout`, failing with `Error::Timeout` so a slow
+/// store call can't hold the connection indefinitely. Long-lived routes such
+/// as the event stream must not be wrapped.
+pub async fn with_timeout<T>(
+    timeout: Duration,
+    handler: impl Future<Output = Result<T, Rejection>>,
+) -> Result<T, Rejection> {
+    match tokio::time::timeout(timeout, handler).await {
+        Ok(result) => result,
+        Err(_) => Err(reject::custom(Error::Timeout)),
+    }
+}
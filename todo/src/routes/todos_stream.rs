@@ -0,0 +1,13 @@
+use crate::storage::store::{TodoStore, UserContext};
+use futures::StreamExt;
+use std::sync::Arc;
+use warp::sse::Event;
+
+pub async fn todos_stream(
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let events = store.watch(&user).await?;
+    let stream = events.map(|event| Event::default().event(event.name()).json_data(event.todo()));
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(stream)))
+}
@@ -0,0 +1,16 @@
+use crate::storage::store::{TodoStore, UserContext};
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub async fn toggle_subtask(
+    id: Uuid,
+    subtask_id: Uuid,
+    user: UserContext,
+    store: Arc<dyn TodoStore>,
+    auto_complete: bool,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let todo = store
+        .toggle_subtask(&user, id.to_string(), subtask_id.to_string(), auto_complete)
+        .await?;
+    Ok(warp::reply::json(&todo))
+}
@@ -1,14 +1,59 @@
-use crate::storage::store::{TodoStore, UserContext};
+use crate::error::Error;
 use crate::model::todo::UpdateTodo;
+use crate::storage::store::{TodoStore, UserContext};
 use std::sync::Arc;
 use uuid::Uuid;
+use warp::http::header::ETAG;
+
+/// Parses an `If-Match` header value into the expected todo version.
+/// `*` matches any version and yields `None`.
+fn version_from_if_match(if_match: &str) -> Result<Option<u64>, Error> {
+    let value = if_match.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse::<u64>()
+        .map(Some)
+        .map_err(|_| Error::PreconditionFailed)
+}
 
 pub async fn update_todo(
     id: Uuid,
     update_todo: UpdateTodo,
+    if_match: Option<String>,
     user: UserContext,
     store: Arc<dyn TodoStore>,
+    max_task_length: usize,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let todo = store.update_todo(&user, id.to_string(), update_todo).await?;
-    Ok(warp::reply::json(&todo))
-}
\ No newline at end of file
+    update_todo.validate(max_task_length)?;
+    let if_match = if_match.ok_or(Error::PreconditionRequired)?;
+    let version = version_from_if_match(&if_match)?;
+    let updated = store
+        .update_todo(&user, id.to_string(), update_todo, version)
+        .await?;
+    let etag = format!("\"{}\"", updated.todo.version);
+    Ok(warp::reply::with_header(
+        warp::reply::json(&updated),
+        ETAG,
+        etag,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_from_if_match() {
+        assert_eq!(version_from_if_match("\"3\""), Ok(Some(3)));
+        assert_eq!(version_from_if_match("W/\"3\""), Ok(Some(3)));
+        assert_eq!(version_from_if_match("*"), Ok(None));
+        assert_eq!(
+            version_from_if_match("\"abc\""),
+            Err(Error::PreconditionFailed)
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::error::Error;
+use crate::model::todo::{NewTodo, UpdateTodo};
+use crate::routes::add_todo::check_quota;
+use crate::storage::store::{TodoEvent, TodoStore, UserContext};
+use futures::{SinkExt, StreamExt};
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use warp::ws::{Message, WebSocket, Ws};
+
+/// Close code for connections opened without a valid token (RFC 6455).
+const POLICY_VIOLATION: u16 = 1008;
+
+/// Mutations a client can send over the socket.
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMsg {
+    Add { todo: NewTodo },
+    Complete { id: String },
+    Delete { id: String },
+}
+
+/// Messages pushed to the client: todo changes and failed mutations.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMsg {
+    Event { event: Box<TodoEvent> },
+    Error { code: String, message: String },
+}
+
+impl From<Error> for ServerMsg {
+    fn from(err: Error) -> Self {
+        ServerMsg::Error {
+            code: err.code().to_string(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl ServerMsg {
+    fn to_message(&self) -> Message {
+        Message::text(serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+async fn apply(
+    user: &UserContext,
+    store: &Arc<dyn TodoStore>,
+    max_task_length: usize,
+    max_todos: Option<u64>,
+    msg: ClientMsg,
+) -> Result<(), Error> {
+    match msg {
+        ClientMsg::Add { todo } => {
+            todo.validate(max_task_length)?;
+            check_quota(user, store, max_todos, 1).await?;
+            store.add_todo(user, todo).await
+        }
+        ClientMsg::Complete { id } => {
+            let update = UpdateTodo {
+                task: None,
+                completed: Some(true),
+                tags: None,
+                assigned_to: None,
+            };
+            store.update_todo(user, id, update, None).await.map(|_| ())
+        }
+        ClientMsg::Delete { id } => store.delete_todo(user, id).await.map(|_| ()),
+    }
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    user: Option<UserContext>,
+    store: Arc<dyn TodoStore>,
+    max_task_length: usize,
+    max_todos: Option<u64>,
+) {
+    let (mut tx, mut rx) = socket.split();
+    let user = match user {
+        Some(user) => user,
+        None => {
+            let _ = tx
+                .send(Message::close_with(POLICY_VIOLATION, "invalid token"))
+                .await;
+            return;
+        }
+    };
+    let mut events = match store.watch(&user).await {
+        Ok(events) => events,
+        Err(e) => {
+            let _ = tx.send(ServerMsg::from(e).to_message()).await;
+            let _ = tx.send(Message::close()).await;
+            return;
+        }
+    };
+
+    loop {
+        let reply = tokio::select! {
+            event = events.next() => match event {
+                Some(event) => ServerMsg::Event { event: Box::new(event) },
+                None => break,
+            },
+            msg = rx.next() => match msg {
+                Some(Ok(msg)) if msg.is_text() => {
+                    match serde_json::from_slice::<ClientMsg>(msg.as_bytes()) {
+                        Ok(msg) => match apply(&user, &store, max_task_length, max_todos, msg).await {
+                            Ok(()) => continue,
+                            Err(e) => ServerMsg::from(e),
+                        },
+                        Err(e) => {
+                            warn!("Invalid websocket message: {}", e);
+                            ServerMsg::Error {
+                                code: "INVALID_MESSAGE".to_string(),
+                                message: e.to_string(),
+                            }
+                        }
+                    }
+                }
+                Some(Ok(msg)) if msg.is_close() => break,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    error!("Websocket error: {}", e);
+                    break;
+                }
+                None => break,
+            },
+        };
+        if tx.send(reply.to_message()).await.is_err() {
+            break;
+        }
+    }
+}
+
+pub async fn ws(
+    ws: Ws,
+    user: Option<UserContext>,
+    store: Arc<dyn TodoStore>,
+    max_task_length: usize,
+    max_todos: Option<u64>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, user, store, max_task_length, max_todos)))
+}
@@ -0,0 +1,469 @@
+use crate::error::Error;
+use crate::model::{NewTodo, Subtask, Todo, UpdateTodo, User};
+use crate::storage::store::{
+    ChangeCursor, StoreStats, TodoEvent, TodoFilter, TodoStore, UpdatedTodo, UserContext,
+    UserDeletion, UserExport,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::BoxStream;
+use log::{error, info};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+/// One line of the audit trail.
+#[derive(Debug, Serialize)]
+struct AuditRecord<'a> {
+    timestamp: DateTime<Utc>,
+    tenant_id: &'a str,
+    user_id: &'a str,
+    /// Scopes of the token the change was made with; empty for API keys.
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    scopes: &'a [String],
+    operation: &'a str,
+    todo_id: Option<&'a str>,
+    outcome: &'a str,
+    /// User removed by `delete_user`, and how many todos went with them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    deleted_user_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    todos_deleted: Option<u64>,
+}
+
+fn outcome<T>(result: &Result<T, Error>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(e) => e.code(),
+    }
+}
+
+/// Wraps a store and records every mutation: logged under the `audit` target
+/// and, when a file is configured, appended to it as JSON lines.
+pub struct AuditStore<S> {
+    inner: S,
+    sink: Option<Arc<Mutex<File>>>,
+}
+
+impl<S: TodoStore> AuditStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner, sink: None }
+    }
+
+    pub fn with_file(mut self, path: &str) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.sink = Some(Arc::new(Mutex::new(file)));
+        Ok(self)
+    }
+
+    fn record<T>(
+        &self,
+        ctx: &UserContext,
+        operation: &str,
+        todo_id: Option<&str>,
+        result: &Result<T, Error>,
+    ) {
+        self.write(&AuditRecord {
+            timestamp: Utc::now(),
+            tenant_id: &ctx.tenant_id,
+            user_id: &ctx.user_id,
+            scopes: &ctx.scopes,
+            operation,
+            todo_id,
+            outcome: outcome(result),
+            deleted_user_id: None,
+            todos_deleted: None,
+        });
+    }
+
+    fn write(&self, record: &AuditRecord) {
+        let line = serde_json::to_string(record).unwrap_or_default();
+        info!(target: "audit", "{}", line);
+        if let Some(sink) = &self.sink {
+            let mut file = sink.lock().unwrap();
+            if let Err(e) = writeln!(file, "{}", line) {
+                error!("Failed to write audit record: {}", e);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<S: TodoStore> TodoStore for AuditStore<S> {
+    async fn add_todo(&self, ctx: &UserContext, new_todo: NewTodo) -> Result<(), Error> {
+        // add_todos hands back the created todo, so server generated ids
+        // are recorded too
+        let client_id = new_todo.id.clone();
+        let result = self.inner.add_todos(ctx, vec![new_todo]).await;
+        let id = match &result {
+            Ok(todos) => todos.first().map(|todo| todo.id.as_str()),
+            Err(_) => client_id.as_deref(),
+        };
+        self.record(ctx, "add_todo", id, &result);
+        result.map(|_| ())
+    }
+
+    async fn add_todos(
+        &self,
+        ctx: &UserContext,
+        new_todos: Vec<NewTodo>,
+    ) -> Result<Vec<Todo>, Error> {
+        let result = self.inner.add_todos(ctx, new_todos).await;
+        match &result {
+            Ok(todos) => todos
+                .iter()
+                .for_each(|todo| self.record(ctx, "add_todo", Some(&todo.id), &result)),
+            Err(_) => self.record(ctx, "add_todos", None, &result),
+        }
+        result
+    }
+
+    async fn get_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        self.inner.get_todo(ctx, id).await
+    }
+
+    async fn get_todos(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<Vec<Todo>, Error> {
+        self.inner.get_todos(ctx, filter).await
+    }
+
+    async fn get_assigned_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
+        self.inner.get_assigned_todos(ctx).await
+    }
+
+    async fn count_todos(&self, ctx: &UserContext, completed: Option<bool>) -> Result<u64, Error> {
+        self.inner.count_todos(ctx, completed).await
+    }
+
+    async fn count_matching(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<u64, Error> {
+        self.inner.count_matching(ctx, filter).await
+    }
+
+    async fn update_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        update_todo: UpdateTodo,
+        version: Option<u64>,
+    ) -> Result<UpdatedTodo, Error> {
+        let result = self
+            .inner
+            .update_todo(ctx, id.clone(), update_todo, version)
+            .await;
+        self.record(ctx, "update_todo", Some(&id), &result);
+        if let Ok(UpdatedTodo {
+            next_occurrence: Some(next),
+            ..
+        }) = &result
+        {
+            self.record(ctx, "add_todo", Some(&next.id), &result);
+        }
+        result
+    }
+
+    async fn set_completed(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        completed: bool,
+    ) -> Result<UpdatedTodo, Error> {
+        let result = self.inner.set_completed(ctx, id.clone(), completed).await;
+        self.record(ctx, "set_completed", Some(&id), &result);
+        result
+    }
+
+    async fn delete_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        let result = self.inner.delete_todo(ctx, id.clone()).await;
+        self.record(ctx, "delete_todo", Some(&id), &result);
+        result
+    }
+
+    async fn share_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        user_id: String,
+    ) -> Result<Todo, Error> {
+        let result = self.inner.share_todo(ctx, id.clone(), user_id).await;
+        self.record(ctx, "share_todo", Some(&id), &result);
+        result
+    }
+
+    async fn archive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        let result = self.inner.archive_todo(ctx, id.clone()).await;
+        self.record(ctx, "archive_todo", Some(&id), &result);
+        result
+    }
+
+    async fn unarchive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        let result = self.inner.unarchive_todo(ctx, id.clone()).await;
+        self.record(ctx, "unarchive_todo", Some(&id), &result);
+        result
+    }
+
+    async fn add_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask: Subtask,
+    ) -> Result<Todo, Error> {
+        let result = self.inner.add_subtask(ctx, id.clone(), subtask).await;
+        self.record(ctx, "add_subtask", Some(&id), &result);
+        result
+    }
+
+    async fn toggle_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+        auto_complete: bool,
+    ) -> Result<Todo, Error> {
+        let result = self
+            .inner
+            .toggle_subtask(ctx, id.clone(), subtask_id, auto_complete)
+            .await;
+        self.record(ctx, "toggle_subtask", Some(&id), &result);
+        result
+    }
+
+    async fn remove_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+    ) -> Result<Todo, Error> {
+        let result = self.inner.remove_subtask(ctx, id.clone(), subtask_id).await;
+        self.record(ctx, "remove_subtask", Some(&id), &result);
+        result
+    }
+
+    async fn create_user(
+        &self,
+        external_id: String,
+        name: String,
+        email: String,
+    ) -> Result<User, Error> {
+        self.inner.create_user(external_id, name, email).await
+    }
+
+    async fn get_user(&self, external_user_id: String) -> Result<Option<User>, Error> {
+        self.inner.get_user(external_user_id).await
+    }
+
+    async fn get_user_by_id(&self, id: String) -> Result<Option<User>, Error> {
+        self.inner.get_user_by_id(id).await
+    }
+
+    async fn list_users(
+        &self,
+        tenant_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<User>, Error> {
+        self.inner.list_users(tenant_id, limit, offset).await
+    }
+
+    async fn export_user_data(
+        &self,
+        tenant_id: String,
+        user_id: String,
+    ) -> Result<UserExport, Error> {
+        self.inner.export_user_data(tenant_id, user_id).await
+    }
+
+    async fn delete_user(
+        &self,
+        admin: &UserContext,
+        user_id: String,
+    ) -> Result<UserDeletion, Error> {
+        let result = self.inner.delete_user(admin, user_id.clone()).await;
+        self.write(&AuditRecord {
+            timestamp: Utc::now(),
+            tenant_id: &admin.tenant_id,
+            user_id: &admin.user_id,
+            scopes: &admin.scopes,
+            operation: "delete_user",
+            todo_id: None,
+            outcome: outcome(&result),
+            deleted_user_id: Some(&user_id),
+            todos_deleted: result.as_ref().ok().map(|deletion| deletion.todos_deleted),
+        });
+        result
+    }
+
+    async fn changes_since(
+        &self,
+        ctx: &UserContext,
+        since: Option<ChangeCursor>,
+        limit: u64,
+    ) -> Result<Vec<Todo>, Error> {
+        self.inner.changes_since(ctx, since, limit).await
+    }
+
+    async fn purge_deleted(&self, ctx: &UserContext, older_than: Duration) -> Result<u64, Error> {
+        let result = self.inner.purge_deleted(ctx, older_than).await;
+        self.record(ctx, "purge_deleted", None, &result);
+        result
+    }
+
+    async fn stats(&self) -> Result<StoreStats, Error> {
+        self.inner.stats().await
+    }
+
+    async fn watch(&self, ctx: &UserContext) -> Result<BoxStream<'static, TodoEvent>, Error> {
+        self.inner.watch(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemStore;
+
+    #[tokio::test]
+    async fn test_audit_file() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", uuid::Uuid::new_v4()));
+        let store = AuditStore::new(MemStore::new("test.json".to_string()))
+            .with_file(path.to_str().unwrap())
+            .unwrap();
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec!["write:todos".to_string()],
+        };
+        let id = uuid::Uuid::new_v4().to_string();
+        let new_todo = NewTodo {
+            id: Some(id.clone()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        store.get_todo(&ctx, id.clone()).await.unwrap();
+        store.delete_todo(&ctx, id.clone()).await.unwrap();
+        assert!(store.delete_todo(&ctx, id.clone()).await.is_err());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records = contents
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        let summary = records
+            .iter()
+            .map(|r| {
+                (
+                    r["operation"].as_str().unwrap(),
+                    r["outcome"].as_str().unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("add_todo", "ok"),
+                ("delete_todo", "ok"),
+                ("delete_todo", "NOT_FOUND"),
+            ]
+        );
+        assert!(records
+            .iter()
+            .all(|r| r["tenant_id"] == "tenant" && r["user_id"] == "user" && r["todo_id"] == id));
+        assert!(records
+            .iter()
+            .all(|r| r["scopes"] == serde_json::json!(["write:todos"])));
+    }
+
+    #[tokio::test]
+    async fn test_audit_generated_id() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", uuid::Uuid::new_v4()));
+        let store = AuditStore::new(MemStore::new("test.json".to_string()))
+            .with_file(path.to_str().unwrap())
+            .unwrap();
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec!["write:todos".to_string()],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let record = serde_json::from_str::<serde_json::Value>(&contents).unwrap();
+        assert_eq!(record["operation"], "add_todo");
+        assert_eq!(record["todo_id"], todos[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_audit_delete_user() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", uuid::Uuid::new_v4()));
+        let inner = MemStore::new("test.json".to_string());
+        let store = AuditStore::new(inner.clone())
+            .with_file(path.to_str().unwrap())
+            .unwrap();
+        let account = User::new(
+            "external".to_string(),
+            "name".to_string(),
+            "email".to_string(),
+            "tenant".to_string(),
+        );
+        inner
+            .users
+            .write()
+            .await
+            .insert(account.external_id.clone(), account.clone());
+        let owner = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: account.id.clone(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        inner.add_todo(&owner, new_todo).await.unwrap();
+        let admin = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "admin".to_string(),
+            roles: vec!["admin".to_string()],
+            scopes: vec![],
+        };
+        store.delete_user(&admin, account.id.clone()).await.unwrap();
+        assert!(store.delete_user(&admin, account.id.clone()).await.is_err());
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records = contents
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r["operation"] == "delete_user"
+            && r["tenant_id"] == "tenant"
+            && r["user_id"] == "admin"
+            && r["deleted_user_id"] == account.id.as_str()));
+        assert_eq!(records[0]["outcome"], "ok");
+        assert_eq!(records[0]["todos_deleted"], 1);
+        assert_eq!(records[1]["outcome"], "NOT_FOUND");
+        assert!(records[1].get("todos_deleted").is_none());
+    }
+}
@@ -0,0 +1,445 @@
+use crate::error::Error;
+use crate::model::{NewTodo, Subtask, Todo, UpdateTodo, User};
+use crate::storage::store::{
+    ChangeCursor, StoreStats, TodoEvent, TodoFilter, TodoStore, UpdatedTodo, UserContext,
+    UserDeletion, UserExport,
+};
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use lru::LruCache;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Reader's tenant, reader's user id and todo id.
+type CacheKey = (String, String, String);
+
+/// Wraps a store and caches `get_todo` per reader. Any write to a todo drops
+/// its entries for every reader, so users it is shared with don't see stale
+/// data. Writes made through another instance are only seen after `ttl`.
+pub struct CachingStore<S> {
+    inner: S,
+    cache: Mutex<LruCache<CacheKey, (Option<Todo>, Instant)>>,
+    ttl: Duration,
+}
+
+impl<S: TodoStore> CachingStore<S> {
+    pub fn new(inner: S, capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    fn key(ctx: &UserContext, id: &str) -> CacheKey {
+        (ctx.tenant_id.clone(), ctx.user_id.clone(), id.to_string())
+    }
+
+    fn cached(&self, key: &CacheKey) -> Option<Option<Todo>> {
+        let mut cache = self.cache.lock().unwrap();
+        match cache.get(key) {
+            Some((todo, cached_at)) if cached_at.elapsed() < self.ttl => Some(todo.clone()),
+            Some(_) => {
+                cache.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn invalidate(&self, id: &str) {
+        let mut cache = self.cache.lock().unwrap();
+        let stale = cache
+            .iter()
+            .filter(|((_, _, cached_id), _)| cached_id == id)
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<CacheKey>>();
+        for key in stale {
+            cache.pop(&key);
+        }
+    }
+
+    /// Drops every cached todo owned by `user_id`, whoever read it.
+    fn invalidate_owner(&self, tenant_id: &str, user_id: &str) {
+        let mut cache = self.cache.lock().unwrap();
+        let stale = cache
+            .iter()
+            .filter(|(_, (todo, _))| {
+                todo.as_ref()
+                    .is_some_and(|todo| todo.tenant_id == tenant_id && todo.user_id == user_id)
+            })
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<CacheKey>>();
+        for key in stale {
+            cache.pop(&key);
+        }
+    }
+}
+
+#[async_trait]
+impl<S: TodoStore> TodoStore for CachingStore<S> {
+    async fn add_todo(&self, ctx: &UserContext, new_todo: NewTodo) -> Result<(), Error> {
+        // a client-chosen id may have been cached as missing
+        if let Some(id) = &new_todo.id {
+            self.invalidate(id);
+        }
+        self.inner.add_todo(ctx, new_todo).await
+    }
+
+    async fn add_todos(
+        &self,
+        ctx: &UserContext,
+        new_todos: Vec<NewTodo>,
+    ) -> Result<Vec<Todo>, Error> {
+        for id in new_todos.iter().filter_map(|new_todo| new_todo.id.as_ref()) {
+            self.invalidate(id);
+        }
+        self.inner.add_todos(ctx, new_todos).await
+    }
+
+    async fn get_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        let key = Self::key(ctx, &id);
+        if let Some(todo) = self.cached(&key) {
+            return Ok(todo);
+        }
+        let todo = self.inner.get_todo(ctx, id).await?;
+        self.cache
+            .lock()
+            .unwrap()
+            .put(key, (todo.clone(), Instant::now()));
+        Ok(todo)
+    }
+
+    async fn get_todos(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<Vec<Todo>, Error> {
+        self.inner.get_todos(ctx, filter).await
+    }
+
+    async fn get_assigned_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
+        self.inner.get_assigned_todos(ctx).await
+    }
+
+    async fn count_todos(&self, ctx: &UserContext, completed: Option<bool>) -> Result<u64, Error> {
+        self.inner.count_todos(ctx, completed).await
+    }
+
+    async fn count_matching(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<u64, Error> {
+        self.inner.count_matching(ctx, filter).await
+    }
+
+    async fn update_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        update_todo: UpdateTodo,
+        version: Option<u64>,
+    ) -> Result<UpdatedTodo, Error> {
+        let result = self
+            .inner
+            .update_todo(ctx, id.clone(), update_todo, version)
+            .await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn set_completed(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        completed: bool,
+    ) -> Result<UpdatedTodo, Error> {
+        let result = self.inner.set_completed(ctx, id.clone(), completed).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn delete_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        let result = self.inner.delete_todo(ctx, id.clone()).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn share_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        user_id: String,
+    ) -> Result<Todo, Error> {
+        let result = self.inner.share_todo(ctx, id.clone(), user_id).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn archive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        let result = self.inner.archive_todo(ctx, id.clone()).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn unarchive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        let result = self.inner.unarchive_todo(ctx, id.clone()).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn add_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask: Subtask,
+    ) -> Result<Todo, Error> {
+        let result = self.inner.add_subtask(ctx, id.clone(), subtask).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn toggle_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+        auto_complete: bool,
+    ) -> Result<Todo, Error> {
+        let result = self
+            .inner
+            .toggle_subtask(ctx, id.clone(), subtask_id, auto_complete)
+            .await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn remove_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+    ) -> Result<Todo, Error> {
+        let result = self.inner.remove_subtask(ctx, id.clone(), subtask_id).await;
+        self.invalidate(&id);
+        result
+    }
+
+    async fn create_user(
+        &self,
+        external_id: String,
+        name: String,
+        email: String,
+    ) -> Result<User, Error> {
+        self.inner.create_user(external_id, name, email).await
+    }
+
+    async fn get_user(&self, external_user_id: String) -> Result<Option<User>, Error> {
+        self.inner.get_user(external_user_id).await
+    }
+
+    async fn get_user_by_id(&self, id: String) -> Result<Option<User>, Error> {
+        self.inner.get_user_by_id(id).await
+    }
+
+    async fn list_users(
+        &self,
+        tenant_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<User>, Error> {
+        self.inner.list_users(tenant_id, limit, offset).await
+    }
+
+    async fn export_user_data(
+        &self,
+        tenant_id: String,
+        user_id: String,
+    ) -> Result<UserExport, Error> {
+        self.inner.export_user_data(tenant_id, user_id).await
+    }
+
+    async fn delete_user(
+        &self,
+        admin: &UserContext,
+        user_id: String,
+    ) -> Result<UserDeletion, Error> {
+        let result = self.inner.delete_user(admin, user_id.clone()).await;
+        self.invalidate_owner(&admin.tenant_id, &user_id);
+        result
+    }
+
+    async fn changes_since(
+        &self,
+        ctx: &UserContext,
+        since: Option<ChangeCursor>,
+        limit: u64,
+    ) -> Result<Vec<Todo>, Error> {
+        self.inner.changes_since(ctx, since, limit).await
+    }
+
+    async fn purge_deleted(
+        &self,
+        ctx: &UserContext,
+        older_than: chrono::Duration,
+    ) -> Result<u64, Error> {
+        // purged todos were already deleted, so any cached entry is a miss
+        self.inner.purge_deleted(ctx, older_than).await
+    }
+
+    async fn stats(&self) -> Result<StoreStats, Error> {
+        self.inner.stats().await
+    }
+
+    async fn watch(&self, ctx: &UserContext) -> Result<BoxStream<'static, TodoEvent>, Error> {
+        self.inner.watch(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemStore;
+
+    fn user(user_id: &str) -> UserContext {
+        UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: user_id.to_string(),
+            roles: vec![],
+            scopes: vec![],
+        }
+    }
+
+    fn new_todo(id: &str) -> NewTodo {
+        NewTodo {
+            id: Some(id.to_string()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        }
+    }
+
+    fn caching_store(inner: MemStore) -> CachingStore<MemStore> {
+        CachingStore::new(
+            inner,
+            NonZeroUsize::new(10).unwrap(),
+            Duration::from_secs(60),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit() {
+        let inner = MemStore::new("test.json".to_string());
+        let store = caching_store(inner.clone());
+        let ctx = user("user");
+        let id = uuid::Uuid::new_v4().to_string();
+        store.add_todo(&ctx, new_todo(&id)).await.unwrap();
+        assert!(store.get_todo(&ctx, id.clone()).await.unwrap().is_some());
+
+        // served from the cache even though the inner store changed behind it
+        inner.objects.write().await.clear();
+        assert!(store.get_todo(&ctx, id).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_invalidated_by_delete_user() {
+        let inner = MemStore::new("test.json".to_string());
+        let store = caching_store(inner.clone());
+        let account = User::new(
+            "external".to_string(),
+            "name".to_string(),
+            "email".to_string(),
+            "tenant".to_string(),
+        );
+        inner
+            .users
+            .write()
+            .await
+            .insert(account.external_id.clone(), account.clone());
+        let owner = user(&account.id);
+        let friend = user("friend");
+        let id = uuid::Uuid::new_v4().to_string();
+        store.add_todo(&owner, new_todo(&id)).await.unwrap();
+        store
+            .share_todo(&owner, id.clone(), "friend".to_string())
+            .await
+            .unwrap();
+        assert!(store.get_todo(&friend, id.clone()).await.unwrap().is_some());
+
+        store
+            .delete_user(&user("admin"), account.id.clone())
+            .await
+            .unwrap();
+        assert_eq!(store.get_todo(&friend, id).await, Err(Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_invalidated_by_writes() {
+        let store = caching_store(MemStore::new("test.json".to_string()));
+        let owner = user("owner");
+        let friend = user("friend");
+        let id = uuid::Uuid::new_v4().to_string();
+
+        // a lookup before the todo exists doesn't hide it afterwards
+        assert_eq!(
+            store.get_todo(&owner, id.clone()).await,
+            Err(Error::NotFound)
+        );
+        store.add_todo(&owner, new_todo(&id)).await.unwrap();
+        assert!(store.get_todo(&owner, id.clone()).await.unwrap().is_some());
+
+        store
+            .share_todo(&owner, id.clone(), "friend".to_string())
+            .await
+            .unwrap();
+        let todo = store.get_todo(&friend, id.clone()).await.unwrap().unwrap();
+        assert!(!todo.completed);
+
+        let update = UpdateTodo {
+            task: None,
+            completed: Some(true),
+            tags: None,
+            assigned_to: None,
+        };
+        store
+            .update_todo(&owner, id.clone(), update, None)
+            .await
+            .unwrap();
+        assert!(
+            store
+                .get_todo(&owner, id.clone())
+                .await
+                .unwrap()
+                .unwrap()
+                .completed
+        );
+        assert!(
+            store
+                .get_todo(&friend, id.clone())
+                .await
+                .unwrap()
+                .unwrap()
+                .completed
+        );
+
+        store.delete_todo(&owner, id.clone()).await.unwrap();
+        assert_eq!(
+            store.get_todo(&owner, id.clone()).await,
+            Err(Error::NotFound)
+        );
+        assert_eq!(store.get_todo(&friend, id).await, Err(Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_reloaded() {
+        let inner = MemStore::new("test.json".to_string());
+        let store = CachingStore::new(
+            inner.clone(),
+            NonZeroUsize::new(10).unwrap(),
+            Duration::ZERO,
+        );
+        let ctx = user("user");
+        let id = uuid::Uuid::new_v4().to_string();
+        store.add_todo(&ctx, new_todo(&id)).await.unwrap();
+        assert!(store.get_todo(&ctx, id.clone()).await.unwrap().is_some());
+
+        inner.objects.write().await.clear();
+        assert_eq!(store.get_todo(&ctx, id).await, Err(Error::NotFound));
+    }
+}
@@ -1,36 +1,114 @@
 use crate::error::Error;
-use crate::model::{NewTodo, Todo, UpdateTodo, User};
-use crate::storage::store::{TodoStore, UserContext};
+use crate::model::{
+    next_occurrence, nothing_to_update, spawns_occurrence, validate_id, NewTodo, Subtask, Todo,
+    UpdateTodo, User,
+};
+use crate::storage::store::{
+    ChangeCursor, StoreStats, TodoEvent, TodoFilter, TodoStore, UpdatedTodo, UserContext,
+    UserDeletion, UserExport,
+};
 use async_trait::async_trait;
+use chrono::{Duration, SubsecRound, Utc};
+use futures::stream::{BoxStream, StreamExt};
+use log::warn;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::process;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, broadcast::error::RecvError, RwLock};
+
+const EVENTS_CAPACITY: usize = 64;
+
+/// What the JSON file holds. Users are kept with the todos since todos
+/// reference them by id.
+#[derive(Default, Serialize, Deserialize)]
+struct Snapshot {
+    todos: HashMap<String, Todo>,
+    users: HashMap<String, User>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredFile {
+    Snapshot(Snapshot),
+    /// Files written before users were persisted hold only the todos.
+    Todos(HashMap<String, Todo>),
+}
+
+fn owned_by<'a>(todo: Option<&'a mut Todo>, ctx: &UserContext) -> Result<&'a mut Todo, Error> {
+    match todo {
+        Some(todo)
+            if todo.tenant_id == ctx.tenant_id
+                && todo.user_id == ctx.user_id
+                && todo.deleted_at.is_none() =>
+        {
+            Ok(todo)
+        }
+        _ => Err(Error::NotFound),
+    }
+}
+
+/// Owners and the users a todo is shared with can read it, tombstones included.
+fn readable_by(todo: &Todo, ctx: &UserContext) -> bool {
+    todo.tenant_id == ctx.tenant_id
+        && (todo.user_id == ctx.user_id || todo.shared_with.contains(&ctx.user_id))
+}
+
+fn visible_to(todo: &Todo, ctx: &UserContext) -> bool {
+    todo.deleted_at.is_none() && readable_by(todo, ctx)
+}
+
+/// Whether `todo` passes the tag and archive filters; `after` and `limit`
+/// are left to the caller.
+fn matches(todo: &Todo, filter: &TodoFilter) -> bool {
+    (filter.tags.is_empty() || todo.tags.iter().any(|t| filter.tags.contains(t)))
+        && match filter.archived {
+            Some(archived) => todo.archived == archived,
+            None => true,
+        }
+}
 
 #[derive(Clone)]
 pub struct MemStore {
     pub objects: Arc<RwLock<HashMap<String, Todo>>>,
-    #[allow(dead_code)]
+    pub users: Arc<RwLock<HashMap<String, User>>>,
+    events: broadcast::Sender<TodoEvent>,
     file_path: String,
 }
 
 impl MemStore {
     pub fn new(file_path: String) -> Self {
+        let snapshot = Self::load(&file_path);
         MemStore {
-            objects: Arc::new(RwLock::new(Self::load(&file_path))),
+            objects: Arc::new(RwLock::new(snapshot.todos)),
+            users: Arc::new(RwLock::new(snapshot.users)),
+            events: broadcast::channel(EVENTS_CAPACITY).0,
             file_path,
         }
     }
 
-    fn load(file_path: &str) -> HashMap<String, Todo> {
+    fn load(file_path: &str) -> Snapshot {
         match std::fs::read_to_string(file_path) {
-            Ok(file) => serde_json::from_str(&file).unwrap_or_else(|_| {
-                eprintln!("Failed to parse the JSON. Exiting...");
-                process::exit(1);
-            }),
+            Ok(file) => match serde_json::from_str(&file) {
+                Ok(StoredFile::Snapshot(snapshot)) => snapshot,
+                Ok(StoredFile::Todos(todos)) => {
+                    warn!(
+                        "{} holds no users, their todos stay unreachable until recreated",
+                        file_path
+                    );
+                    Snapshot {
+                        todos,
+                        users: HashMap::new(),
+                    }
+                }
+                Err(_) => {
+                    eprintln!("Failed to parse the JSON. Exiting...");
+                    process::exit(1);
+                }
+            },
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 // File not found, continue
-                HashMap::new()
+                Snapshot::default()
             }
             Err(e) => {
                 eprintln!("An error occurred while reading the file: {}...", e);
@@ -39,12 +117,31 @@ impl MemStore {
         }
     }
 
-    #[allow(dead_code)]
+    /// Writes the todos and users back to the JSON file.
     pub async fn shutdown(&self) -> std::io::Result<()> {
-        let data = self.objects.read().await;
-        let json = serde_json::to_string(&*data).expect("Failed to save data!");
+        let snapshot = Snapshot {
+            todos: self.objects.read().await.clone(),
+            users: self.users.read().await.clone(),
+        };
+        let json = serde_json::to_string(&snapshot).expect("Failed to save data!");
         tokio::fs::write(&self.file_path, json).await
     }
+
+    async fn set_archived(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        archived: bool,
+    ) -> Result<Todo, Error> {
+        let mut data = self.objects.write().await;
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        if todo.archived != archived {
+            todo.archived = archived;
+            todo.touch(Utc::now());
+            let _ = self.events.send(TodoEvent::Updated(todo.clone()));
+        }
+        Ok(todo.clone())
+    }
 }
 
 #[async_trait]
@@ -52,14 +149,44 @@ impl TodoStore for MemStore {
     async fn add_todo(&self, ctx: &UserContext, new_todo: NewTodo) -> Result<(), Error> {
         let mut data = self.objects.write().await;
         let todo = Todo::new(ctx.tenant_id.clone(), ctx.user_id.clone(), new_todo);
-        data.insert(todo.id.clone(), todo);
+        if data.contains_key(&todo.id) {
+            return Err(Error::Conflict);
+        }
+        data.insert(todo.id.clone(), todo.clone());
+        // sending only fails when nobody is subscribed
+        let _ = self.events.send(TodoEvent::Added(todo));
         Ok(())
     }
 
+    async fn add_todos(
+        &self,
+        ctx: &UserContext,
+        new_todos: Vec<NewTodo>,
+    ) -> Result<Vec<Todo>, Error> {
+        let mut data = self.objects.write().await;
+        let todos = new_todos
+            .into_iter()
+            .map(|new_todo| Todo::new(ctx.tenant_id.clone(), ctx.user_id.clone(), new_todo))
+            .collect::<Vec<Todo>>();
+        let mut ids = std::collections::HashSet::new();
+        if todos
+            .iter()
+            .any(|todo| data.contains_key(&todo.id) || !ids.insert(&todo.id))
+        {
+            return Err(Error::Conflict);
+        }
+        for todo in &todos {
+            data.insert(todo.id.clone(), todo.clone());
+            let _ = self.events.send(TodoEvent::Added(todo.clone()));
+        }
+        Ok(todos)
+    }
+
     async fn get_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        validate_id(&id)?;
         let data = self.objects.read().await;
-        if let Some(todo) = data.get(&id) {
-            if todo.user_id != ctx.user_id || todo.tenant_id != ctx.tenant_id {
+        if let Some(todo) = data.get(&id).filter(|todo| todo.deleted_at.is_none()) {
+            if !visible_to(todo, ctx) {
                 return Err(Error::Unauthorized);
             }
             return Ok(Some(todo.clone()));
@@ -67,62 +194,420 @@ impl TodoStore for MemStore {
         Err(Error::NotFound)
     }
 
-    async fn get_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
+    async fn get_todos(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<Vec<Todo>, Error> {
         let data = self.objects.read().await;
-        let filtered_todos = data
+        let mut filtered_todos = data
             .values()
-            .filter(|todo| todo.tenant_id == ctx.tenant_id && todo.user_id == ctx.user_id)
+            .filter(|todo| visible_to(todo, ctx) && matches(todo, filter))
+            .filter(|todo| match &filter.after {
+                Some(after) => todo.id > *after,
+                None => true,
+            })
             .cloned()
             .collect::<Vec<Todo>>();
+        filtered_todos.sort_by(|a, b| a.id.cmp(&b.id));
+        if let Some(limit) = filter.limit {
+            filtered_todos.truncate(limit as usize);
+        }
         Ok(filtered_todos)
     }
 
+    async fn get_assigned_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
+        let data = self.objects.read().await;
+        let mut todos = data
+            .values()
+            .filter(|todo| {
+                todo.tenant_id == ctx.tenant_id
+                    && todo.deleted_at.is_none()
+                    && todo.assigned_to.as_deref() == Some(ctx.user_id.as_str())
+            })
+            .cloned()
+            .collect::<Vec<Todo>>();
+        todos.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(todos)
+    }
+
+    async fn count_todos(&self, ctx: &UserContext, completed: Option<bool>) -> Result<u64, Error> {
+        let data = self.objects.read().await;
+        let count = data
+            .values()
+            .filter(|todo| visible_to(todo, ctx))
+            .filter(|todo| match completed {
+                Some(completed) => todo.completed == completed,
+                None => true,
+            })
+            .count();
+        Ok(count as u64)
+    }
+
+    async fn count_matching(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<u64, Error> {
+        let data = self.objects.read().await;
+        let count = data
+            .values()
+            .filter(|todo| visible_to(todo, ctx) && matches(todo, filter))
+            .count();
+        Ok(count as u64)
+    }
+
     async fn update_todo(
         &self,
         ctx: &UserContext,
         id: String,
         update_todo: UpdateTodo,
-    ) -> Result<Option<Todo>, Error> {
+        version: Option<u64>,
+    ) -> Result<UpdatedTodo, Error> {
+        validate_id(&id)?;
+        if update_todo.is_empty() {
+            return Err(nothing_to_update());
+        }
         let mut data = self.objects.write().await;
-        if let Some(todo) = data.get_mut(&id) {
-            if todo.user_id != ctx.user_id || todo.tenant_id != ctx.tenant_id {
-                return Err(Error::Unauthorized);
-            }
-            todo.completed = match update_todo.completed {
-                Some(completed) => completed,
-                None => todo.completed,
-            };
-            todo.task = match update_todo.task {
-                Some(task) => task,
-                None => todo.task.clone(),
-            };
-            Ok(Some(todo.clone()))
+        let todo = match data.get_mut(&id) {
+            Some(todo) if todo.deleted_at.is_none() => todo,
+            _ => return Err(Error::NotFound),
+        };
+        if todo.user_id != ctx.user_id || todo.tenant_id != ctx.tenant_id {
+            return Err(Error::Unauthorized);
+        }
+        if version.is_some_and(|version| version != todo.version) {
+            return Err(Error::PreconditionFailed);
+        }
+        let before = todo.clone();
+        let now = Utc::now();
+        update_todo.apply(todo, now);
+        todo.touch(now);
+        let todo = todo.clone();
+        let _ = self.events.send(TodoEvent::Updated(todo.clone()));
+        let next_occurrence = if spawns_occurrence(&before, &todo) {
+            next_occurrence(&todo)
         } else {
-            Err(Error::NotFound)
+            None
+        };
+        if let Some(next) = &next_occurrence {
+            data.insert(next.id.clone(), next.clone());
+            let _ = self.events.send(TodoEvent::Added(next.clone()));
+        }
+        Ok(UpdatedTodo {
+            todo,
+            next_occurrence,
+        })
+    }
+
+    async fn set_completed(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        completed: bool,
+    ) -> Result<UpdatedTodo, Error> {
+        validate_id(&id)?;
+        let mut data = self.objects.write().await;
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        if todo.completed == completed {
+            return Ok(UpdatedTodo {
+                todo: todo.clone(),
+                next_occurrence: None,
+            });
+        }
+        let before = todo.clone();
+        let now = Utc::now();
+        todo.set_completed(completed, now);
+        todo.touch(now);
+        let todo = todo.clone();
+        let _ = self.events.send(TodoEvent::Updated(todo.clone()));
+        let next_occurrence = if spawns_occurrence(&before, &todo) {
+            next_occurrence(&todo)
+        } else {
+            None
+        };
+        if let Some(next) = &next_occurrence {
+            data.insert(next.id.clone(), next.clone());
+            let _ = self.events.send(TodoEvent::Added(next.clone()));
         }
+        Ok(UpdatedTodo {
+            todo,
+            next_occurrence,
+        })
     }
 
     async fn delete_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        validate_id(&id)?;
         let mut data = self.objects.write().await;
-        if let Some(todo) = data.get(&id) {
-            if todo.tenant_id == ctx.tenant_id && todo.user_id == ctx.user_id {
-                return Ok(data.remove(&id));
-            }
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        let now = Utc::now();
+        todo.deleted_at = Some(now);
+        todo.touch(now);
+        let _ = self.events.send(TodoEvent::Deleted(todo.clone()));
+        Ok(Some(todo.clone()))
+    }
+
+    async fn share_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        user_id: String,
+    ) -> Result<Todo, Error> {
+        let mut data = self.objects.write().await;
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        if !todo.shared_with.contains(&user_id) {
+            todo.shared_with.push(user_id);
+            todo.touch(Utc::now());
+            let _ = self.events.send(TodoEvent::Updated(todo.clone()));
         }
-        Err(Error::NotFound)
+        Ok(todo.clone())
+    }
+
+    async fn archive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        self.set_archived(ctx, id, true).await
+    }
+
+    async fn unarchive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        self.set_archived(ctx, id, false).await
+    }
+
+    async fn add_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask: Subtask,
+    ) -> Result<Todo, Error> {
+        let mut data = self.objects.write().await;
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        todo.subtasks.push(subtask);
+        todo.touch(Utc::now());
+        let _ = self.events.send(TodoEvent::Updated(todo.clone()));
+        Ok(todo.clone())
+    }
+
+    async fn toggle_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+        auto_complete: bool,
+    ) -> Result<Todo, Error> {
+        let mut data = self.objects.write().await;
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        let subtask = todo
+            .subtasks
+            .iter_mut()
+            .find(|subtask| subtask.id == subtask_id)
+            .ok_or(Error::NotFound)?;
+        subtask.done = !subtask.done;
+        if auto_complete {
+            todo.complete_if_subtasks_done();
+        }
+        todo.touch(Utc::now());
+        let _ = self.events.send(TodoEvent::Updated(todo.clone()));
+        Ok(todo.clone())
+    }
+
+    async fn remove_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+    ) -> Result<Todo, Error> {
+        let mut data = self.objects.write().await;
+        let todo = owned_by(data.get_mut(&id), ctx)?;
+        let len = todo.subtasks.len();
+        todo.subtasks.retain(|subtask| subtask.id != subtask_id);
+        if todo.subtasks.len() == len {
+            return Err(Error::NotFound);
+        }
+        todo.touch(Utc::now());
+        let _ = self.events.send(TodoEvent::Updated(todo.clone()));
+        Ok(todo.clone())
     }
 
     async fn create_user(
         &self,
-        _external_id: String,
-        _name: String,
-        _email: String,
+        external_id: String,
+        name: String,
+        email: String,
     ) -> Result<User, Error> {
-        Err(Error::NotFound)
+        let mut users = self.users.write().await;
+        if users.contains_key(&external_id) {
+            return Err(Error::Conflict);
+        }
+        let user = User::new(external_id, name, email, uuid::Uuid::new_v4().to_string());
+        users.insert(user.external_id.clone(), user.clone());
+        Ok(user)
     }
 
-    async fn get_user(&self, _external_user_id: String) -> Result<Option<User>, Error> {
-        Err(Error::NotFound)
+    async fn get_user(&self, external_user_id: String) -> Result<Option<User>, Error> {
+        let users = self.users.read().await;
+        match users.get(&external_user_id) {
+            Some(user) => Ok(Some(user.clone())),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    async fn get_user_by_id(&self, id: String) -> Result<Option<User>, Error> {
+        let users = self.users.read().await;
+        match users.values().find(|user| user.id == id) {
+            Some(user) => Ok(Some(user.clone())),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    async fn list_users(
+        &self,
+        tenant_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<User>, Error> {
+        let users = self.users.read().await;
+        let mut users: Vec<User> = users
+            .values()
+            .filter(|user| user.tenant_id == tenant_id)
+            .cloned()
+            .collect();
+        users.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(users
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn export_user_data(
+        &self,
+        tenant_id: String,
+        user_id: String,
+    ) -> Result<UserExport, Error> {
+        let user = self
+            .users
+            .read()
+            .await
+            .values()
+            .find(|user| user.id == user_id && user.tenant_id == tenant_id)
+            .cloned()
+            .ok_or(Error::NotFound)?;
+        let mut todos: Vec<Todo> = self
+            .objects
+            .read()
+            .await
+            .values()
+            .filter(|todo| todo.tenant_id == tenant_id && todo.user_id == user_id)
+            .cloned()
+            .collect();
+        todos.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(UserExport {
+            exported_at: Utc::now(),
+            user,
+            todos,
+        })
+    }
+
+    async fn delete_user(
+        &self,
+        admin: &UserContext,
+        user_id: String,
+    ) -> Result<UserDeletion, Error> {
+        let tenant_id = &admin.tenant_id;
+        // holding both locks makes the cascade atomic
+        let mut users = self.users.write().await;
+        let external_id = users
+            .values()
+            .find(|user| user.id == user_id && user.tenant_id == *tenant_id)
+            .map(|user| user.external_id.clone())
+            .ok_or(Error::NotFound)?;
+        let mut data = self.objects.write().await;
+        let before = data.len();
+        data.retain(|_, todo| !(todo.tenant_id == *tenant_id && todo.user_id == user_id));
+        let todos_deleted = (before - data.len()) as u64;
+        users.remove(&external_id);
+        Ok(UserDeletion {
+            user_id,
+            todos_deleted,
+        })
+    }
+
+    async fn changes_since(
+        &self,
+        ctx: &UserContext,
+        since: Option<ChangeCursor>,
+        limit: u64,
+    ) -> Result<Vec<Todo>, Error> {
+        // compared at the microsecond precision cursors are formatted with
+        let position = |todo: &Todo| (todo.updated_at.map(|t| t.trunc_subsecs(6)), todo.id.clone());
+        let data = self.objects.read().await;
+        let mut changes = data
+            .values()
+            .filter(|todo| readable_by(todo, ctx))
+            .filter(|todo| match &since {
+                Some(since) => todo.updated_at.is_some_and(|updated_at| {
+                    let updated_at = updated_at.trunc_subsecs(6);
+                    updated_at > since.updated_at
+                        || (updated_at == since.updated_at
+                            && since.id.as_ref().is_some_and(|id| todo.id > *id))
+                }),
+                None => true,
+            })
+            .cloned()
+            .collect::<Vec<Todo>>();
+        changes.sort_by_key(position);
+        changes.truncate(limit as usize);
+        Ok(changes)
+    }
+
+    async fn purge_deleted(&self, ctx: &UserContext, older_than: Duration) -> Result<u64, Error> {
+        let cutoff = Utc::now() - older_than;
+        let mut data = self.objects.write().await;
+        let before = data.len();
+        data.retain(|_, todo| {
+            let expired = todo.tenant_id == ctx.tenant_id
+                && todo
+                    .deleted_at
+                    .is_some_and(|deleted_at| deleted_at < cutoff);
+            !expired
+        });
+        Ok((before - data.len()) as u64)
+    }
+
+    async fn stats(&self) -> Result<StoreStats, Error> {
+        let mut stats = StoreStats::default();
+        for todo in self.objects.read().await.values() {
+            if todo.deleted_at.is_some() {
+                continue;
+            }
+            stats.todos += 1;
+            stats
+                .tenants
+                .entry(todo.tenant_id.clone())
+                .or_default()
+                .todos += 1;
+        }
+        for user in self.users.read().await.values() {
+            stats.users += 1;
+            stats
+                .tenants
+                .entry(user.tenant_id.clone())
+                .or_default()
+                .users += 1;
+        }
+        Ok(stats)
+    }
+
+    async fn watch(&self, ctx: &UserContext) -> Result<BoxStream<'static, TodoEvent>, Error> {
+        let events = self.events.subscribe();
+        let stream =
+            futures::stream::unfold((ctx.clone(), events), |(ctx, mut events)| async move {
+                loop {
+                    match events.recv().await {
+                        Ok(event) => {
+                            let todo = event.todo();
+                            if todo.tenant_id == ctx.tenant_id && todo.user_id == ctx.user_id {
+                                return Some((event, (ctx, events)));
+                            }
+                        }
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("Watch for {} skipped {} events", ctx.user_id, skipped);
+                        }
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            });
+        Ok(stream.boxed())
     }
 }
 
@@ -135,13 +620,19 @@ mod tests {
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
+            id: None,
             task: "test".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
         assert_eq!(todos.len(), 1);
         assert_eq!(todos[0].task, "test");
         assert!(!todos[0].completed);
@@ -156,13 +647,19 @@ mod tests {
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
+            id: None,
             task: "test".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
         assert_eq!(todos.len(), 1);
         let todo = store.get_todo(&ctx, todos[0].id.clone()).await.unwrap();
         assert_eq!(todo.as_ref().unwrap().task, "test");
@@ -178,28 +675,43 @@ mod tests {
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
+            id: None,
             task: "test".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
         let ctx2 = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo2 = NewTodo {
+            id: None,
             task: "test2".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx2, new_todo2).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
         assert_eq!(todos.len(), 1);
         assert_eq!(todos[0].task, "test");
         assert!(!todos[0].completed);
         assert_eq!(todos[0].user_id, "user");
         assert_eq!(todos[0].tenant_id, "tenant");
-        let todos2 = store.get_todos(&ctx2).await.unwrap();
+        let todos2 = store
+            .get_todos(&ctx2, &TodoFilter::default())
+            .await
+            .unwrap();
         assert_eq!(todos2.len(), 1);
         assert_eq!(todos2[0].task, "test2");
         assert!(!todos2[0].completed);
@@ -214,138 +726,1222 @@ mod tests {
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
+            id: None,
             task: "test".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
         assert_eq!(todos.len(), 1);
         let update_todo = UpdateTodo {
             task: Some("test2".to_string()),
             completed: Some(true),
+            tags: None,
+            assigned_to: None,
         };
         let todo = store
-            .update_todo(&ctx, todos[0].id.clone(), update_todo)
+            .update_todo(&ctx, todos[0].id.clone(), update_todo, None)
             .await
             .unwrap();
-        assert_eq!(todo.as_ref().unwrap().task, "test2");
-        assert!(todo.as_ref().unwrap().completed);
-        assert_eq!(todo.as_ref().unwrap().user_id, "user");
-        assert_eq!(todo.as_ref().unwrap().tenant_id, "tenant");
+        assert_eq!(todo.todo.task, "test2");
+        assert!(todo.todo.completed);
+        assert_eq!(todo.todo.user_id, "user");
+        assert_eq!(todo.todo.tenant_id, "tenant");
+        assert!(todo.next_occurrence.is_none());
     }
 
     #[tokio::test]
-    async fn test_delete_todo() {
+    async fn test_update_todo_recurring() {
         use super::*;
+        use crate::model::Recurrence;
+        use chrono::NaiveDate;
         let store = MemStore::new("test.json".to_string());
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
-            task: "test".to_string(),
+            id: None,
+            task: "water plants".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: NaiveDate::from_ymd_opt(2023, 1, 31),
+            recurrence: Some(Recurrence::Monthly),
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
-        assert_eq!(todos.len(), 1);
-        let todo = store.delete_todo(&ctx, todos[0].id.clone()).await.unwrap();
-        assert_eq!(todo.as_ref().unwrap().task, "test");
-        assert!(!todo.as_ref().unwrap().completed);
-        assert_eq!(todo.as_ref().unwrap().user_id, "user");
-        assert_eq!(todo.as_ref().unwrap().tenant_id, "tenant");
-        let todos = store.get_todos(&ctx).await.unwrap();
-        assert_eq!(todos.len(), 0);
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        let complete = UpdateTodo {
+            task: None,
+            completed: Some(true),
+            tags: None,
+            assigned_to: None,
+        };
+        let updated = store
+            .update_todo(&ctx, todos[0].id.clone(), complete.clone(), None)
+            .await
+            .unwrap();
+        assert!(updated.todo.completed);
+        let next = updated.next_occurrence.unwrap();
+        assert!(!next.completed);
+        assert_eq!(next.due_date, NaiveDate::from_ymd_opt(2023, 2, 28));
+        assert_eq!(next.recurrence, Some(Recurrence::Monthly));
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 2);
+
+        // completing an already completed todo doesn't spawn another one
+        let updated = store
+            .update_todo(&ctx, updated.todo.id.clone(), complete, None)
+            .await
+            .unwrap();
+        assert!(updated.next_occurrence.is_none());
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 2);
     }
 
     #[tokio::test]
-    async fn test_delete_todo_not_found() {
+    async fn test_update_todo_completed_at() {
         use super::*;
         let store = MemStore::new("test.json".to_string());
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
+            id: Some("00000000-0000-0000-0000-000000000001".to_string()),
             task: "test".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
-        assert_eq!(todos.len(), 1);
-        let ctx2 = UserContext {
-            tenant_id: "tenant".to_string(),
-            user_id: "user2".to_string(),
+        let set_completed = |completed| UpdateTodo {
+            task: None,
+            completed: Some(completed),
+            tags: None,
+            assigned_to: None,
         };
-        let expected_result = store.delete_todo(&ctx2, todos[0].id.clone()).await;
-        assert_eq!(expected_result, Err(Error::NotFound));
-        let todos = store.get_todos(&ctx).await.unwrap();
-        assert_eq!(todos.len(), 1);
+
+        let updated = store
+            .update_todo(
+                &ctx,
+                "00000000-0000-0000-0000-000000000001".to_string(),
+                set_completed(true),
+                None,
+            )
+            .await
+            .unwrap();
+        let completed_at = updated.todo.completed_at.expect("completed_at is set");
+
+        // completing again keeps the original timestamp
+        let updated = store
+            .update_todo(
+                &ctx,
+                "00000000-0000-0000-0000-000000000001".to_string(),
+                set_completed(true),
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.todo.completed_at, Some(completed_at));
+
+        let updated = store
+            .update_todo(
+                &ctx,
+                "00000000-0000-0000-0000-000000000001".to_string(),
+                set_completed(false),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(!updated.todo.completed);
+        assert!(updated.todo.completed_at.is_none());
     }
 
     #[tokio::test]
-    async fn test_update_todo_unauthorized() {
+    async fn test_set_completed() {
         use super::*;
         let store = MemStore::new("test.json".to_string());
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
+        let id = "00000000-0000-0000-0000-000000000001".to_string();
         let new_todo = NewTodo {
+            id: Some(id.clone()),
             task: "test".to_string(),
             completed: false,
+            tags: vec!["home".to_string()],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let todos = store.get_todos(&ctx).await.unwrap();
-        assert_eq!(todos.len(), 1);
-        let ctx2 = UserContext {
+
+        let updated = store.set_completed(&ctx, id.clone(), true).await.unwrap();
+        assert!(updated.todo.completed);
+        assert_eq!(updated.todo.version, 2);
+        assert_eq!(updated.todo.task, "test");
+        assert_eq!(updated.todo.tags, vec!["home".to_string()]);
+        let completed_at = updated.todo.completed_at.expect("completed_at is set");
+
+        // already completed: nothing changes
+        let updated = store.set_completed(&ctx, id.clone(), true).await.unwrap();
+        assert_eq!(updated.todo.version, 2);
+        assert_eq!(updated.todo.completed_at, Some(completed_at));
+
+        let updated = store.set_completed(&ctx, id.clone(), false).await.unwrap();
+        assert!(!updated.todo.completed);
+        assert!(updated.todo.completed_at.is_none());
+        assert_eq!(updated.todo.task, "test");
+
+        let other = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
-        let update_todo = UpdateTodo {
-            task: Some("test2".to_string()),
-            completed: Some(true),
-        };
-        let expected_result = store
-            .update_todo(&ctx2, todos[0].id.clone(), update_todo)
-            .await;
-        assert_eq!(expected_result, Err(Error::Unauthorized));
-        let todos = store.get_todos(&ctx).await.unwrap();
-        assert_eq!(todos.len(), 1);
+        assert_eq!(
+            store.set_completed(&other, id, true).await,
+            Err(Error::NotFound)
+        );
     }
 
     #[tokio::test]
-    async fn test_get_todo_not_found() {
+    async fn test_update_todo_nothing_to_update() {
         use super::*;
         let store = MemStore::new("test.json".to_string());
         let ctx = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
         let new_todo = NewTodo {
+            id: None,
             task: "test".to_string(),
             completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
         };
         store.add_todo(&ctx, new_todo).await.unwrap();
-        let ctx2 = UserContext {
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        let update_todo = UpdateTodo {
+            task: None,
+            completed: None,
+            tags: None,
+            assigned_to: None,
+        };
+        let result = store
+            .update_todo(&ctx, todos[0].id.clone(), update_todo, None)
+            .await;
+        assert_eq!(result, Err(nothing_to_update()));
+        let todo = store.get_todo(&ctx, todos[0].id.clone()).await.unwrap();
+        assert_eq!(todo.as_ref().unwrap().version, 1);
+    }
+
+    #[tokio::test]
+    async fn test_share_todo() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let owner = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let friend = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
-        let expected_result = store.get_todo(&ctx2, "test".to_string()).await;
-        assert_eq!(expected_result, Err(Error::NotFound));
+        let outsider = UserContext {
+            tenant_id: "tenant2".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&owner, new_todo).await.unwrap();
+        let id = store
+            .get_todos(&owner, &TodoFilter::default())
+            .await
+            .unwrap()[0]
+            .id
+            .clone();
+        assert!(store
+            .get_todos(&friend, &TodoFilter::default())
+            .await
+            .unwrap()
+            .is_empty());
+
+        // only the owner can share
+        let result = store
+            .share_todo(&friend, id.clone(), "user2".to_string())
+            .await;
+        assert_eq!(result, Err(Error::NotFound));
+
+        let todo = store
+            .share_todo(&owner, id.clone(), "user2".to_string())
+            .await
+            .unwrap();
+        assert_eq!(todo.shared_with, vec!["user2".to_string()]);
+        let todos = store
+            .get_todos(&friend, &TodoFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(todos.len(), 1);
+        assert!(store.get_todo(&friend, id.clone()).await.is_ok());
+        assert_eq!(store.count_todos(&friend, None).await.unwrap(), 1);
+
+        // sharing never crosses tenants
+        assert!(store
+            .get_todos(&outsider, &TodoFilter::default())
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.get_todo(&outsider, id).await,
+            Err(Error::Unauthorized)
+        );
     }
 
     #[tokio::test]
-    async fn test_get_todos_not_found() {
+    async fn test_get_assigned_todos() {
         use super::*;
         let store = MemStore::new("test.json".to_string());
-        let ctx2 = UserContext {
+        let owner = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let assignee = UserContext {
             tenant_id: "tenant".to_string(),
             user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
         };
-        let todos = store.get_todos(&ctx2).await.unwrap();
-        assert_eq!(todos.len(), 0);
+        for (id, assigned_to) in [
+            ("00000000-0000-0000-0000-000000000001", Some("user2")),
+            ("00000000-0000-0000-0000-000000000002", Some("user3")),
+            ("00000000-0000-0000-0000-000000000003", None),
+        ] {
+            let new_todo = NewTodo {
+                id: Some(id.to_string()),
+                task: "test".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            };
+            store.add_todo(&owner, new_todo).await.unwrap();
+            if let Some(assigned_to) = assigned_to {
+                let update_todo = UpdateTodo {
+                    task: None,
+                    completed: None,
+                    tags: None,
+                    assigned_to: Some(assigned_to.to_string()),
+                };
+                store
+                    .update_todo(&owner, id.to_string(), update_todo, None)
+                    .await
+                    .unwrap();
+            }
+        }
+        let todos = store.get_assigned_todos(&assignee).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].id, "00000000-0000-0000-0000-000000000001");
+        assert_eq!(todos[0].user_id, "user");
+        assert!(store.get_assigned_todos(&owner).await.unwrap().is_empty());
+
+        let other_tenant = UserContext {
+            tenant_id: "tenant2".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        assert!(store
+            .get_assigned_todos(&other_tenant)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subtasks() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: Some("1".to_string()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        for text in ["first", "second"] {
+            let subtask = Subtask {
+                id: text.to_string(),
+                text: text.to_string(),
+                done: false,
+            };
+            store
+                .add_subtask(&ctx, "1".to_string(), subtask)
+                .await
+                .unwrap();
+        }
+
+        let todo = store
+            .toggle_subtask(&ctx, "1".to_string(), "first".to_string(), true)
+            .await
+            .unwrap();
+        assert!(todo.subtasks[0].done);
+        assert!(!todo.completed);
+
+        let todo = store
+            .remove_subtask(&ctx, "1".to_string(), "second".to_string())
+            .await
+            .unwrap();
+        assert_eq!(todo.subtasks.len(), 1);
+        let result = store
+            .remove_subtask(&ctx, "1".to_string(), "second".to_string())
+            .await;
+        assert_eq!(result, Err(Error::NotFound));
+
+        // toggling back and forth with auto-complete finishes the parent
+        store
+            .toggle_subtask(&ctx, "1".to_string(), "first".to_string(), true)
+            .await
+            .unwrap();
+        let todo = store
+            .toggle_subtask(&ctx, "1".to_string(), "first".to_string(), true)
+            .await
+            .unwrap();
+        assert!(todo.subtasks[0].done);
+        assert!(todo.completed);
+
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let result = store
+            .toggle_subtask(&ctx2, "1".to_string(), "first".to_string(), true)
+            .await;
+        assert_eq!(result, Err(Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_archive_todo() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        for id in [
+            "00000000-0000-0000-0000-000000000001",
+            "00000000-0000-0000-0000-000000000002",
+        ] {
+            let new_todo = NewTodo {
+                id: Some(id.to_string()),
+                task: "test".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            };
+            store.add_todo(&ctx, new_todo).await.unwrap();
+        }
+        let todo = store
+            .archive_todo(&ctx, "00000000-0000-0000-0000-000000000001".to_string())
+            .await
+            .unwrap();
+        assert!(todo.archived);
+
+        let active = TodoFilter {
+            archived: Some(false),
+            ..TodoFilter::default()
+        };
+        let archived = TodoFilter {
+            archived: Some(true),
+            ..TodoFilter::default()
+        };
+        let todos = store.get_todos(&ctx, &active).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].id, "00000000-0000-0000-0000-000000000002");
+        let todos = store.get_todos(&ctx, &archived).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].id, "00000000-0000-0000-0000-000000000001");
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 2);
+        // archived todos can still be fetched directly
+        assert!(store
+            .get_todo(&ctx, "00000000-0000-0000-0000-000000000001".to_string())
+            .await
+            .is_ok());
+
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let result = store
+            .unarchive_todo(&ctx2, "00000000-0000-0000-0000-000000000001".to_string())
+            .await;
+        assert_eq!(result, Err(Error::NotFound));
+        let todo = store
+            .unarchive_todo(&ctx, "00000000-0000-0000-0000-000000000001".to_string())
+            .await
+            .unwrap();
+        assert!(!todo.archived);
+        let todos = store.get_todos(&ctx, &active).await.unwrap();
+        assert_eq!(todos.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_todo() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        let todo = store.delete_todo(&ctx, todos[0].id.clone()).await.unwrap();
+        assert_eq!(todo.as_ref().unwrap().task, "test");
+        assert!(!todo.as_ref().unwrap().completed);
+        assert_eq!(todo.as_ref().unwrap().user_id, "user");
+        assert_eq!(todo.as_ref().unwrap().tenant_id, "tenant");
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = |tenant_id: &str| UserContext {
+            tenant_id: tenant_id.to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = |id: &str| NewTodo {
+            id: Some(id.to_string()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        let first = "00000000-0000-0000-0000-000000000001".to_string();
+        let second = "00000000-0000-0000-0000-000000000002".to_string();
+        let third = "00000000-0000-0000-0000-000000000003".to_string();
+        store.add_todo(&ctx("a"), new_todo(&first)).await.unwrap();
+        store.add_todo(&ctx("a"), new_todo(&second)).await.unwrap();
+        store.add_todo(&ctx("b"), new_todo(&third)).await.unwrap();
+        store.delete_todo(&ctx("a"), first.clone()).await.unwrap();
+        store.delete_todo(&ctx("b"), third.clone()).await.unwrap();
+
+        // deleted too recently
+        assert_eq!(
+            store
+                .purge_deleted(&ctx("a"), Duration::days(1))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            store
+                .purge_deleted(&ctx("a"), Duration::zero())
+                .await
+                .unwrap(),
+            1
+        );
+        let data = store.objects.read().await;
+        assert!(!data.contains_key(&first));
+        // live todos and other tenants' tombstones are kept
+        assert!(data.contains_key(&second));
+        assert!(data.contains_key(&third));
+    }
+
+    #[tokio::test]
+    async fn test_changes_since() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = |id: &str| NewTodo {
+            id: Some(id.to_string()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        let first = "00000000-0000-0000-0000-000000000001".to_string();
+        let second = "00000000-0000-0000-0000-000000000002".to_string();
+        store.add_todo(&ctx, new_todo(&first)).await.unwrap();
+        store.add_todo(&ctx, new_todo(&second)).await.unwrap();
+        let changes = store.changes_since(&ctx, None, 10).await.unwrap();
+        assert_eq!(changes.len(), 2);
+        let cursor = changes[1].updated_at.map(|updated_at| ChangeCursor {
+            updated_at,
+            id: None,
+        });
+
+        store.delete_todo(&ctx, first.clone()).await.unwrap();
+        let changes = store.changes_since(&ctx, cursor, 10).await.unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].id, first);
+        assert!(changes[0].deleted_at.is_some());
+        assert_eq!(changes[0].version, 2);
+
+        // the tombstone is hidden everywhere else
+        assert_eq!(
+            store.get_todo(&ctx, first.clone()).await,
+            Err(Error::NotFound)
+        );
+        assert_eq!(
+            store.delete_todo(&ctx, first.clone()).await,
+            Err(Error::NotFound)
+        );
+        assert_eq!(store.count_todos(&ctx, None).await.unwrap(), 1);
+
+        let other = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        assert!(store
+            .changes_since(&other, None, 10)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(store.changes_since(&ctx, None, 1).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_changes_since_same_timestamp() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todos = (0..3)
+            .map(|i| NewTodo {
+                id: None,
+                task: format!("test {}", i),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            })
+            .collect();
+        store.add_todos(&ctx, new_todos).await.unwrap();
+        let now = Utc::now().trunc_subsecs(6);
+        for todo in store.objects.write().await.values_mut() {
+            todo.updated_at = Some(now);
+        }
+
+        let mut seen = vec![];
+        let mut since = None;
+        loop {
+            let page = store.changes_since(&ctx, since.clone(), 2).await.unwrap();
+            let Some(last) = page.last() else { break };
+            since = Some(ChangeCursor {
+                updated_at: last.updated_at.unwrap(),
+                id: Some(last.id.clone()),
+            });
+            seen.extend(page.into_iter().map(|todo| todo.id));
+        }
+        let mut ids: Vec<String> = store.objects.read().await.keys().cloned().collect();
+        ids.sort();
+        assert_eq!(seen, ids);
+    }
+
+    #[tokio::test]
+    async fn test_delete_todo_not_found() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let expected_result = store.delete_todo(&ctx2, todos[0].id.clone()).await;
+        assert_eq!(expected_result, Err(Error::NotFound));
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_todo_unauthorized() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let update_todo = UpdateTodo {
+            task: Some("test2".to_string()),
+            completed: Some(true),
+            tags: None,
+            assigned_to: None,
+        };
+        let expected_result = store
+            .update_todo(&ctx2, todos[0].id.clone(), update_todo, None)
+            .await;
+        assert_eq!(expected_result, Err(Error::Unauthorized));
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_todo_not_found() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let expected_result = store
+            .get_todo(&ctx2, uuid::Uuid::new_v4().to_string())
+            .await;
+        assert_eq!(expected_result, Err(Error::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_id() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let invalid = Error::validation("id", "must be a UUID");
+        for id in ["test", r#"{"$gt": ""}"#, ""] {
+            assert_eq!(
+                store.get_todo(&ctx, id.to_string()).await,
+                Err(invalid.clone())
+            );
+            assert_eq!(
+                store.delete_todo(&ctx, id.to_string()).await,
+                Err(invalid.clone())
+            );
+            let update_todo = UpdateTodo {
+                task: None,
+                completed: Some(true),
+                tags: None,
+                assigned_to: None,
+            };
+            assert_eq!(
+                store
+                    .update_todo(&ctx, id.to_string(), update_todo, None)
+                    .await,
+                Err(invalid.clone())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_not_found() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let todos = store
+            .get_todos(&ctx2, &TodoFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(todos.len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_create_user() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let user = store
+            .create_user(
+                "external".to_string(),
+                "name".to_string(),
+                "email".to_string(),
+            )
+            .await
+            .unwrap();
+        let found = store.get_user("external".to_string()).await.unwrap();
+        assert_eq!(found.unwrap().id, user.id);
+        let expected_result = store
+            .create_user(
+                "external".to_string(),
+                "name".to_string(),
+                "email".to_string(),
+            )
+            .await;
+        assert_eq!(expected_result.map(|u| u.id), Err(Error::Conflict));
+        let expected_result = store.get_user("missing".to_string()).await;
+        assert_eq!(
+            expected_result.map(|u| u.map(|u| u.id)),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_persists_users() {
+        use super::*;
+        let path = std::env::temp_dir().join(format!("todos-{}.json", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap().to_string();
+        let store = MemStore::new(path.clone());
+        let user = store
+            .create_user(
+                "external".to_string(),
+                "name".to_string(),
+                "email".to_string(),
+            )
+            .await
+            .unwrap();
+        let ctx = UserContext {
+            tenant_id: user.tenant_id.clone(),
+            user_id: user.id.clone(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todo = store.objects.read().await.values().next().unwrap().clone();
+        store.shutdown().await.unwrap();
+
+        let restarted = MemStore::new(path.clone());
+        let found = restarted.get_user("external".to_string()).await.unwrap();
+        assert_eq!(found.map(|u| u.id), Some(user.id));
+        let found = restarted.get_todo(&ctx, todo.id.clone()).await.unwrap();
+        assert_eq!(found.map(|t| t.task), Some("test".to_string()));
+
+        // files from before users were persisted still load
+        let todos = HashMap::from([(todo.id.clone(), todo.clone())]);
+        std::fs::write(&path, serde_json::to_string(&todos).unwrap()).unwrap();
+        let legacy = MemStore::new(path.clone());
+        assert_eq!(legacy.objects.read().await.len(), 1);
+        assert!(legacy.users.read().await.is_empty());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_user_by_id() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let user = User::new(
+            "external".to_string(),
+            "name".to_string(),
+            "email".to_string(),
+            "tenant".to_string(),
+        );
+        store
+            .users
+            .write()
+            .await
+            .insert(user.external_id.clone(), user.clone());
+        let found = store.get_user_by_id(user.id.clone()).await.unwrap();
+        assert_eq!(found.unwrap().external_id, "external");
+        let expected_result = store.get_user_by_id("missing".to_string()).await;
+        assert_eq!(
+            expected_result.map(|u| u.map(|u| u.id)),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_todo_conflict() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: Some("id".to_string()),
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo.clone()).await.unwrap();
+        let expected_result = store.add_todo(&ctx, new_todo).await;
+        assert_eq!(expected_result, Err(Error::Conflict));
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].id, "id");
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_by_tag() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        for (task, tags) in [("work task", vec!["work"]), ("home task", vec!["home"])] {
+            let new_todo = NewTodo {
+                id: None,
+                task: task.to_string(),
+                completed: false,
+                tags: tags.into_iter().map(String::from).collect(),
+                due_date: None,
+                recurrence: None,
+            };
+            store.add_todo(&ctx, new_todo).await.unwrap();
+        }
+        let filter = TodoFilter {
+            tags: vec!["work".to_string()],
+            ..TodoFilter::default()
+        };
+        let todos = store.get_todos(&ctx, &filter).await.unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].task, "work task");
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_count_todos() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        for completed in [true, false, false] {
+            let new_todo = NewTodo {
+                id: None,
+                task: "test".to_string(),
+                completed,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            };
+            store.add_todo(&ctx, new_todo).await.unwrap();
+        }
+        let ctx2 = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user2".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        assert_eq!(store.count_todos(&ctx, None).await.unwrap(), 3);
+        assert_eq!(store.count_todos(&ctx, Some(true)).await.unwrap(), 1);
+        assert_eq!(store.count_todos(&ctx, Some(false)).await.unwrap(), 2);
+        assert_eq!(store.count_todos(&ctx2, None).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_todo_version_mismatch() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todo = NewTodo {
+            id: None,
+            task: "test".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        let update_todo = UpdateTodo {
+            task: Some("test2".to_string()),
+            completed: None,
+            tags: None,
+            assigned_to: None,
+        };
+        let todo = store
+            .update_todo(&ctx, todos[0].id.clone(), update_todo.clone(), Some(1))
+            .await
+            .unwrap();
+        assert_eq!(todo.todo.version, 2);
+        let expected_result = store
+            .update_todo(&ctx, todos[0].id.clone(), update_todo, Some(1))
+            .await;
+        assert_eq!(expected_result, Err(Error::PreconditionFailed));
+    }
+
+    #[tokio::test]
+    async fn test_watch() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let other = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "other".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let mut events = store.watch(&ctx).await.unwrap();
+        let new_todo = NewTodo {
+            id: None,
+            task: "theirs".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&other, new_todo).await.unwrap();
+        let new_todo = NewTodo {
+            id: None,
+            task: "mine".to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        };
+        store.add_todo(&ctx, new_todo).await.unwrap();
+        let todo = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap()[0].clone();
+        store.delete_todo(&ctx, todo.id.clone()).await.unwrap();
+
+        match events.next().await.unwrap() {
+            TodoEvent::Added(added) => assert_eq!(added.task, "mine"),
+            event => panic!("unexpected event {:?}", event),
+        }
+        match events.next().await.unwrap() {
+            TodoEvent::Deleted(deleted) => assert_eq!(deleted.id, todo.id),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_todos_after_cursor() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        for id in ["c", "a", "b"] {
+            let new_todo = NewTodo {
+                id: Some(id.to_string()),
+                task: "test".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            };
+            store.add_todo(&ctx, new_todo).await.unwrap();
+        }
+        let filter = TodoFilter {
+            limit: Some(2),
+            ..TodoFilter::default()
+        };
+        let todos = store.get_todos(&ctx, &filter).await.unwrap();
+        let ids: Vec<&str> = todos.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+
+        let filter = TodoFilter {
+            after: Some("b".to_string()),
+            limit: Some(2),
+            ..TodoFilter::default()
+        };
+        let todos = store.get_todos(&ctx, &filter).await.unwrap();
+        let ids: Vec<&str> = todos.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["c"]);
+    }
+
+    #[tokio::test]
+    async fn test_add_todos() {
+        use super::*;
+        let store = MemStore::new("test.json".to_string());
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        let new_todos = vec![
+            NewTodo {
+                id: None,
+                task: "first".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            },
+            NewTodo {
+                id: Some("second".to_string()),
+                task: "second".to_string(),
+                completed: true,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            },
+        ];
+        let todos = store.add_todos(&ctx, new_todos).await.unwrap();
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos[1].id, "second");
+
+        // a conflicting id rejects the whole batch
+        let new_todos = vec![
+            NewTodo {
+                id: None,
+                task: "third".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            },
+            NewTodo {
+                id: Some("second".to_string()),
+                task: "duplicate".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            },
+        ];
+        assert_eq!(
+            store.add_todos(&ctx, new_todos).await.unwrap_err(),
+            Error::Conflict
+        );
+        let todos = store.get_todos(&ctx, &TodoFilter::default()).await.unwrap();
+        assert_eq!(todos.len(), 2);
     }
 }
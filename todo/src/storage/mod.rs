@@ -1,9 +1,13 @@
-#[cfg(test)]
+pub mod auditstore;
+pub mod cachingstore;
 pub mod memstore;
 pub mod mongostore;
 pub mod store;
+pub mod webhookstore;
 
-#[cfg(test)]
+pub use auditstore::*;
+pub use cachingstore::*;
 pub use memstore::*;
 pub use mongostore::*;
 pub use store::*;
+pub use webhookstore::*;
@@ -1,15 +1,31 @@
 use crate::error::Error;
-use crate::model::{NewTodo, Todo, UpdateTodo, User};
-use crate::storage::store::{TodoStore, UserContext};
+use crate::model::{
+    format_timestamp, next_occurrence, nothing_to_update, spawns_occurrence, validate_id, NewTodo,
+    Subtask, Todo, UpdateTodo, User,
+};
+use crate::storage::store::{
+    ChangeCursor, StoreStats, TodoEvent, TodoFilter, TodoStore, UpdatedTodo, UserContext,
+    UserDeletion, UserExport,
+};
 use async_trait::async_trait;
-use futures::stream::TryStreamExt;
-use log::{error, info};
-use mongodb::bson::{doc, Document};
-use mongodb::{Client, Collection};
+use chrono::Utc;
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use log::{error, info, warn};
+use mongodb::bson::{doc, to_bson, Bson, Document};
+use mongodb::change_stream::event::OperationType;
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{
+    ChangeStreamOptions, ClientOptions, FindOneAndUpdateOptions, FindOptions,
+    FullDocumentBeforeChangeType, FullDocumentType, IndexOptions, ReturnDocument,
+};
+use mongodb::{Client, Collection, IndexModel};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::timeout;
 use uuid::Uuid;
 
-const DB_NAME: &str = "todo";
-
 macro_rules! update_todo {
     ($updatetodo:expr) => {{
         let mut doc = Document::new();
@@ -22,90 +38,435 @@ macro_rules! update_todo {
             doc.insert("completed", completed);
         }
 
+        if let Some(ref tags) = $updatetodo.tags {
+            doc.insert("tags", tags);
+        }
+
+        if let Some(ref assigned_to) = $updatetodo.assigned_to {
+            doc.insert("assigned_to", assigned_to);
+        }
+
         doc
     }};
 }
 
-async fn mongo_result<T>(
-    result: Result<Option<T>, mongodb::error::Error>,
-    operation: &str,
-) -> Result<Option<T>, Error> {
+/// For single-document lookups: a missing document is `NotFound`. Queries
+/// returning lists go through `find_all` and come back empty instead.
+fn mongo_result<T>(result: Result<Option<T>, Error>) -> Result<Option<T>, Error> {
     match result {
         Ok(None) => Err(Error::NotFound),
-        Ok(Some(item)) => Ok(Some(item)),
-        Err(e) => {
-            error!("Failed to {}: {:?}", operation, e);
-            Err(Error::DatabaseOperationFailed(format!(
-                "Failed to {}: {:?}",
-                operation, e
-            )))
+        result => result,
+    }
+}
+
+/// A timed out or failed call is tried this many times in total.
+const OPERATION_ATTEMPTS: u32 = 2;
+
+/// Whether repeating a call could apply it twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Access {
+    Read,
+    Write,
+}
+
+/// Errors worth one more attempt. Server selection and a cleared pool fail
+/// before anything reaches the server; a write that hit a network error may
+/// already have been applied, so only reads are retried on those.
+fn is_transient(e: &mongodb::error::Error, access: Access) -> bool {
+    match *e.kind {
+        ErrorKind::ServerSelection { .. } | ErrorKind::ConnectionPoolCleared { .. } => true,
+        ErrorKind::Io(_) => access == Access::Read,
+        _ => false,
+    }
+}
+
+/// Server error code for a write that breaks a unique index.
+const DUPLICATE_KEY: i32 = 11000;
+
+/// Whether a write was refused because a document with the same unique key,
+/// such as a todo id, already exists.
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    match &*e.kind {
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) => {
+            write_error.code == DUPLICATE_KEY
+        }
+        ErrorKind::BulkWrite(failure) => failure
+            .write_errors
+            .iter()
+            .flatten()
+            .any(|write_error| write_error.code == DUPLICATE_KEY),
+        _ => false,
+    }
+}
+
+/// Runs one database call bounded by `operation_timeout`, trying it again
+/// once if it fails transiently. Only reads are retried after a timeout,
+/// since a timed out write may still be applied. A duplicate key is reported
+/// as `Error::Conflict`.
+async fn run<T, F, Fut>(
+    operation: &str,
+    access: Access,
+    operation_timeout: Duration,
+    call: F,
+) -> Result<T, Error>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Result<T, mongodb::error::Error>>,
+{
+    let mut attempt = 1;
+    loop {
+        let (retryable, reason) = match timeout(operation_timeout, call()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) if is_duplicate_key(&e) => {
+                warn!("Failed to {}: duplicate key", operation);
+                return Err(Error::Conflict);
+            }
+            Ok(Err(e)) => (is_transient(&e, access), format!("{:?}", e)),
+            Err(_) => (
+                access == Access::Read,
+                format!("timed out after {:?}", operation_timeout),
+            ),
+        };
+        if retryable && attempt < OPERATION_ATTEMPTS {
+            warn!("Failed to {}: {}; retrying", operation, reason);
+            attempt += 1;
+            continue;
         }
+        let message = match attempt {
+            1 => format!("Failed to {}: {}", operation, reason),
+            _ => format!(
+                "Failed to {}: {} (after {} attempts)",
+                operation, reason, attempt
+            ),
+        };
+        error!("{}", message);
+        return Err(Error::DatabaseOperationFailed(message));
     }
 }
 
+/// Client settings applied on top of the connection string. Fields left as
+/// `None` keep whatever the URI or the driver default specifies.
+#[derive(Debug, Clone)]
+pub struct MongoOptions {
+    pub max_pool_size: Option<u32>,
+    pub connect_timeout: Option<Duration>,
+    pub server_selection_timeout: Option<Duration>,
+    /// Upper bound on each database call made by the store.
+    pub operation_timeout: Duration,
+    pub db_name: String,
+    pub todo_collection: String,
+    pub user_collection: String,
+}
+
+impl Default for MongoOptions {
+    fn default() -> Self {
+        Self {
+            max_pool_size: None,
+            connect_timeout: None,
+            server_selection_timeout: None,
+            operation_timeout: Duration::from_secs(10),
+            db_name: "todo".to_string(),
+            todo_collection: "Todos".to_string(),
+            user_collection: "Users".to_string(),
+        }
+    }
+}
+
+fn bson_value<T: Serialize>(value: &T) -> Result<Bson, Error> {
+    to_bson(value).map_err(|e| {
+        error!("Failed to serialize value: {:?}", e);
+        Error::DatabaseOperationFailed(format!("Failed to serialize value: {:?}", e))
+    })
+}
+
+/// Matches the todos `ctx` can read: its own and those shared with it,
+/// tombstones included.
+fn readable_filter(ctx: &UserContext) -> Document {
+    doc! {
+        "tenant_id": ctx.tenant_id.clone(),
+        "$or": [
+            { "user_id": ctx.user_id.clone() },
+            { "shared_with": ctx.user_id.clone() },
+        ],
+    }
+}
+
+/// Like `readable_filter`, leaving out deleted todos.
+fn visible_filter(ctx: &UserContext) -> Document {
+    let mut filter = readable_filter(ctx);
+    filter.insert("deleted_at", Bson::Null);
+    filter
+}
+
+/// Todos visible to `ctx` that pass the tag and archive filters of
+/// `todo_filter`.
+fn matching_filter(ctx: &UserContext, todo_filter: &TodoFilter) -> Document {
+    let mut filter = visible_filter(ctx);
+    if !todo_filter.tags.is_empty() {
+        filter.insert("tags", doc! { "$in": todo_filter.tags.clone() });
+    }
+    match todo_filter.archived {
+        // todos stored before archiving existed have no `archived` field
+        Some(false) => {
+            filter.insert("archived", doc! { "$ne": true });
+        }
+        Some(true) => {
+            filter.insert("archived", true);
+        }
+        None => {}
+    }
+    filter
+}
+
+/// Matches todo `id` if `ctx` owns it and it is not deleted.
+fn owner_filter(ctx: &UserContext, id: String) -> Document {
+    doc! {
+        "id": id,
+        "tenant_id": ctx.tenant_id.clone(),
+        "user_id": ctx.user_id.clone(),
+        "deleted_at": Bson::Null,
+    }
+}
+
+/// Every document matching `filter`, read in one call so that the store's
+/// timeout covers draining the cursor too.
+async fn find_all<T>(
+    col: &Collection<T>,
+    filter: Document,
+    options: FindOptions,
+) -> Result<Vec<T>, mongodb::error::Error>
+where
+    T: DeserializeOwned + Unpin + Send + Sync,
+{
+    col.find(filter, options).await?.try_collect().await
+}
+
+/// Number of documents in `col` per `tenant_id`.
+async fn count_by_tenant<T>(
+    col: &Collection<T>,
+) -> Result<Vec<(String, u64)>, mongodb::error::Error> {
+    let pipeline = vec![
+        doc! { "$match": { "deleted_at": Bson::Null } },
+        doc! { "$group": { "_id": "$tenant_id", "count": { "$sum": 1 } } },
+    ];
+    let groups: Vec<Document> = col.aggregate(pipeline, None).await?.try_collect().await?;
+    Ok(groups
+        .iter()
+        .map(|group| {
+            let tenant_id = group.get_str("_id").unwrap_or_default().to_string();
+            let count = match group.get("count") {
+                Some(Bson::Int32(count)) => *count as u64,
+                Some(Bson::Int64(count)) => *count as u64,
+                _ => 0,
+            };
+            (tenant_id, count)
+        })
+        .collect())
+}
+
 #[derive(Debug, Clone)]
 pub struct MongoStore {
     todo_col: Collection<Todo>,
     user_col: Collection<User>,
+    operation_timeout: Duration,
 }
 
 impl MongoStore {
-    pub async fn init(mongo_uri: String) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn init(
+        mongo_uri: String,
+        options: MongoOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let operation_timeout = options.operation_timeout;
         let (todo_col, user_col): (Collection<Todo>, Collection<User>) =
-            Self::connect(mongo_uri).await?;
-        Ok(Self { todo_col, user_col })
+            Self::connect(mongo_uri, options).await?;
+        Ok(Self {
+            todo_col,
+            user_col,
+            operation_timeout,
+        })
     }
 
     async fn connect(
         mongo_uri: String,
+        options: MongoOptions,
     ) -> Result<(Collection<Todo>, Collection<User>), Box<dyn std::error::Error>> {
-        let client = Client::with_uri_str(mongo_uri).await?;
-        let db = client.database(DB_NAME);
-        let todo_col: Collection<Todo> = db.collection("Todos");
-        let user_col: Collection<User> = db.collection("Users");
+        let mut client_options = ClientOptions::parse(mongo_uri).await?;
+        if options.max_pool_size.is_some() {
+            client_options.max_pool_size = options.max_pool_size;
+        }
+        if options.connect_timeout.is_some() {
+            client_options.connect_timeout = options.connect_timeout;
+        }
+        if options.server_selection_timeout.is_some() {
+            client_options.server_selection_timeout = options.server_selection_timeout;
+        }
+        let client = Client::with_options(client_options)?;
+        let db = client.database(&options.db_name);
+        // the client connects lazily; ping so an unreachable server fails here
+        db.run_command(doc! { "ping": 1 }, None).await?;
+        let todo_col: Collection<Todo> = db.collection(&options.todo_collection);
+        // ids are unique across tenants; the index makes concurrent creates
+        // with the same client supplied id fail rather than both succeed
+        let unique_id = IndexModel::builder()
+            .keys(doc! { "id": 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        todo_col.create_index(unique_id, None).await?;
+        let user_col: Collection<User> = db.collection(&options.user_collection);
         Ok((todo_col, user_col))
     }
+
+    async fn read<T, F, Fut>(&self, operation: &str, call: F) -> Result<T, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, mongodb::error::Error>>,
+    {
+        run(operation, Access::Read, self.operation_timeout, call).await
+    }
+
+    async fn write<T, F, Fut>(&self, operation: &str, call: F) -> Result<T, Error>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, mongodb::error::Error>>,
+    {
+        run(operation, Access::Write, self.operation_timeout, call).await
+    }
+
+    async fn set_archived(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        archived: bool,
+    ) -> Result<Todo, Error> {
+        let filter = owner_filter(ctx, id);
+        let update = doc! {
+            "$set": { "archived": archived, "updated_at": format_timestamp(&Utc::now()) },
+            "$inc": { "version": 1_i64 },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let result = self
+            .write("archive todo", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        mongo_result(result)?.ok_or(Error::NotFound)
+    }
 }
 
 #[async_trait]
 impl TodoStore for MongoStore {
     async fn add_todo(&self, ctx: &UserContext, new_todo: NewTodo) -> Result<(), Error> {
         let todo = Todo::new(ctx.tenant_id.clone(), ctx.user_id.clone(), new_todo);
-        self.todo_col
-            .insert_one(todo.clone(), None)
-            .await
-            .map_err(|e| {
-                error!("Failed to insert todo: {:?}", e);
-                Error::DatabaseOperationFailed(format!("Failed to insert todo: {:?}", e))
-            })?;
+        self.write("insert todo", || {
+            self.todo_col.insert_one(todo.clone(), None)
+        })
+        .await?;
         info!("Added todo: {:?}", todo);
         Ok(())
     }
 
+    /// Not a transaction, which would need a replica set. The insert is
+    /// ordered and stops at the first existing id, keeping the todos before
+    /// it; the unique index on `id` turns that, or a repeated insert, into
+    /// `Error::Conflict` rather than duplicates.
+    async fn add_todos(
+        &self,
+        ctx: &UserContext,
+        new_todos: Vec<NewTodo>,
+    ) -> Result<Vec<Todo>, Error> {
+        let client_supplied_ids = new_todos
+            .iter()
+            .filter_map(|new_todo| new_todo.id.clone())
+            .collect::<Vec<String>>();
+        let todos = new_todos
+            .into_iter()
+            .map(|new_todo| Todo::new(ctx.tenant_id.clone(), ctx.user_id.clone(), new_todo))
+            .collect::<Vec<Todo>>();
+        if todos.is_empty() {
+            return Ok(todos);
+        }
+        if !client_supplied_ids.is_empty() {
+            let unique = client_supplied_ids
+                .iter()
+                .collect::<std::collections::HashSet<&String>>();
+            if unique.len() != client_supplied_ids.len() {
+                return Err(Error::Conflict);
+            }
+        }
+        self.write("insert todos", || {
+            self.todo_col.insert_many(todos.clone(), None)
+        })
+        .await?;
+        info!("Added {} todos", todos.len());
+        Ok(todos)
+    }
+
     async fn get_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
-        let filter = doc! {
-            "id": id,
-            "tenant_id": ctx.tenant_id.clone(),
-            "user_id": ctx.user_id.clone(),
-        };
-        let result = self.todo_col.find_one(filter, None).await;
-        mongo_result(result, "get todo").await
+        validate_id(&id)?;
+        let mut filter = visible_filter(ctx);
+        filter.insert("id", id);
+        let result = self
+            .read("get todo", || self.todo_col.find_one(filter.clone(), None))
+            .await;
+        mongo_result(result)
+    }
+
+    async fn get_todos(
+        &self,
+        ctx: &UserContext,
+        todo_filter: &TodoFilter,
+    ) -> Result<Vec<Todo>, Error> {
+        let mut filter = matching_filter(ctx, todo_filter);
+        if let Some(after) = &todo_filter.after {
+            filter.insert("id", doc! { "$gt": after.clone() });
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "id": 1 })
+            .limit(todo_filter.limit.map(|limit| limit as i64))
+            .build();
+        self.read("get todos", || {
+            find_all(&self.todo_col, filter.clone(), options.clone())
+        })
+        .await
     }
 
-    async fn get_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
+    async fn get_assigned_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
         let filter = doc! {
             "tenant_id": ctx.tenant_id.clone(),
-            "user_id": ctx.user_id.clone(),
+            "assigned_to": ctx.user_id.clone(),
+            "deleted_at": Bson::Null,
         };
-        let cursor = self.todo_col.find(filter, None).await.map_err(|e| {
-            error!("Failed create cursor to get todos: {:?}", e);
-            Error::DatabaseOperationFailed(format!("Failed create cursor to get todos: {:?}", e))
-        })?;
-        let todos: Vec<Todo> = cursor.try_collect().await.map_err(|e| {
-            error!("Failed to get todos: {:?}", e);
-            Error::DatabaseOperationFailed(format!("Failed to get todos: {:?}", e))
-        })?;
-        Ok(todos)
+        let options = FindOptions::builder().sort(doc! { "id": 1 }).build();
+        self.read("get assigned todos", || {
+            find_all(&self.todo_col, filter.clone(), options.clone())
+        })
+        .await
+    }
+
+    async fn count_todos(&self, ctx: &UserContext, completed: Option<bool>) -> Result<u64, Error> {
+        let mut filter = visible_filter(ctx);
+        if let Some(completed) = completed {
+            filter.insert("completed", completed);
+        }
+        self.read("count todos", || {
+            self.todo_col.count_documents(filter.clone(), None)
+        })
+        .await
+    }
+
+    async fn count_matching(
+        &self,
+        ctx: &UserContext,
+        todo_filter: &TodoFilter,
+    ) -> Result<u64, Error> {
+        let filter = matching_filter(ctx, todo_filter);
+        self.read("count matching todos", || {
+            self.todo_col.count_documents(filter.clone(), None)
+        })
+        .await
     }
 
     async fn update_todo(
@@ -113,30 +474,304 @@ impl TodoStore for MongoStore {
         ctx: &UserContext,
         id: String,
         update_todo: UpdateTodo,
-    ) -> Result<Option<Todo>, Error> {
-        let filter = doc! {
-            "id": id,
-            "tenant_id": ctx.tenant_id.clone(),
-            "user_id": ctx.user_id.clone(),
+        version: Option<u64>,
+    ) -> Result<UpdatedTodo, Error> {
+        validate_id(&id)?;
+        // an empty $set is rejected by MongoDB with an opaque error
+        if update_todo.is_empty() {
+            return Err(nothing_to_update());
+        }
+        let owner_filter = owner_filter(ctx, id);
+        let mut filter = owner_filter.clone();
+        if let Some(version) = version {
+            filter.insert("version", version as i64);
+        }
+        let now = Utc::now();
+        let mut set = update_todo!(update_todo);
+        set.insert("updated_at", format_timestamp(&now));
+        if let Some(completed) = update_todo.completed {
+            // completed_at only moves on a transition, so read the current
+            // state and make the update conditional on it
+            let result = self
+                .read("get todo", || {
+                    self.todo_col.find_one(owner_filter.clone(), None)
+                })
+                .await;
+            let current = mongo_result(result)?.ok_or(Error::NotFound)?;
+            if current.completed != completed {
+                let completed_at = if completed { Some(now) } else { None };
+                set.insert("completed_at", bson_value(&completed_at)?);
+            }
+            filter.insert("completed", current.completed);
+        }
+        let update = doc! {
+            "$set": set,
+            "$inc": { "version": 1_i64 },
+        };
+        // the previous state tells us whether this update completed the todo
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::Before)
+            .build();
+        let result = self
+            .write("update todo", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        let before = match mongo_result(result) {
+            Err(Error::NotFound) if version.is_some() || update_todo.completed.is_some() => {
+                // distinguish a stale version or a concurrent completion from
+                // a missing todo
+                let result = self
+                    .read("get todo", || {
+                        self.todo_col.find_one(owner_filter.clone(), None)
+                    })
+                    .await;
+                mongo_result(result)?;
+                return Err(match version {
+                    Some(_) => Error::PreconditionFailed,
+                    None => Error::Conflict,
+                });
+            }
+            result => result?.ok_or(Error::NotFound)?,
+        };
+        let mut todo = before.clone();
+        update_todo.apply(&mut todo, now);
+        todo.touch(now);
+
+        let next_occurrence = if spawns_occurrence(&before, &todo) {
+            next_occurrence(&todo)
+        } else {
+            None
         };
+        if let Some(next) = &next_occurrence {
+            self.write("insert next occurrence", || {
+                self.todo_col.insert_one(next.clone(), None)
+            })
+            .await?;
+        }
+        Ok(UpdatedTodo {
+            todo,
+            next_occurrence,
+        })
+    }
+
+    async fn set_completed(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        completed: bool,
+    ) -> Result<UpdatedTodo, Error> {
+        validate_id(&id)?;
+        let owner_filter = owner_filter(ctx, id);
+        // only matches a todo in the other state, so completed_at is stamped
+        // exactly once per transition
+        let mut filter = owner_filter.clone();
+        filter.insert("completed", !completed);
+        let now = Utc::now();
+        let completed_at = if completed { Some(now) } else { None };
         let update = doc! {
-            "$set": update_todo!(update_todo),
+            "$set": {
+                "completed": completed,
+                "completed_at": bson_value(&completed_at)?,
+                "updated_at": format_timestamp(&now),
+            },
+            "$inc": { "version": 1_i64 },
         };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
         let result = self
-            .todo_col
-            .find_one_and_update(filter, update, None)
+            .write("set todo completed", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
             .await;
-        mongo_result(result, "update todo").await
+        let todo = match mongo_result(result) {
+            Err(Error::NotFound) => {
+                // already in the requested state, or missing
+                let result = self
+                    .read("get todo", || {
+                        self.todo_col.find_one(owner_filter.clone(), None)
+                    })
+                    .await;
+                let todo = mongo_result(result)?.ok_or(Error::NotFound)?;
+                return Ok(UpdatedTodo {
+                    todo,
+                    next_occurrence: None,
+                });
+            }
+            result => result?.ok_or(Error::NotFound)?,
+        };
+        let next_occurrence = if completed {
+            next_occurrence(&todo)
+        } else {
+            None
+        };
+        if let Some(next) = &next_occurrence {
+            self.write("insert next occurrence", || {
+                self.todo_col.insert_one(next.clone(), None)
+            })
+            .await?;
+        }
+        Ok(UpdatedTodo {
+            todo,
+            next_occurrence,
+        })
     }
 
     async fn delete_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
-        let filter = doc! {
-            "id": id,
-            "tenant_id": ctx.tenant_id.clone(),
-            "user_id": ctx.user_id.clone(),
+        validate_id(&id)?;
+        // kept as a tombstone so change feeds can report the deletion
+        let now = format_timestamp(&Utc::now());
+        let update = doc! {
+            "$set": { "deleted_at": now.clone(), "updated_at": now },
+            "$inc": { "version": 1_i64 },
         };
-        let result = self.todo_col.find_one_and_delete(filter, None).await;
-        mongo_result(result, "delete todo").await
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let filter = owner_filter(ctx, id);
+        let result = self
+            .write("delete todo", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        mongo_result(result)
+    }
+
+    async fn share_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        user_id: String,
+    ) -> Result<Todo, Error> {
+        let filter = owner_filter(ctx, id);
+        let update = doc! {
+            "$addToSet": { "shared_with": user_id },
+            "$set": { "updated_at": format_timestamp(&Utc::now()) },
+            "$inc": { "version": 1_i64 },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let result = self
+            .write("share todo", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        mongo_result(result)?.ok_or(Error::NotFound)
+    }
+
+    async fn archive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        self.set_archived(ctx, id, true).await
+    }
+
+    async fn unarchive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        self.set_archived(ctx, id, false).await
+    }
+
+    async fn add_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask: Subtask,
+    ) -> Result<Todo, Error> {
+        let filter = owner_filter(ctx, id);
+        let subtask = bson_value(&subtask)?;
+        let update = doc! {
+            "$push": { "subtasks": subtask },
+            "$set": { "updated_at": format_timestamp(&Utc::now()) },
+            "$inc": { "version": 1_i64 },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let result = self
+            .write("add subtask", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        mongo_result(result)?.ok_or(Error::NotFound)
+    }
+
+    async fn toggle_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+        auto_complete: bool,
+    ) -> Result<Todo, Error> {
+        let mut filter = owner_filter(ctx, id);
+        filter.insert("subtasks.id", subtask_id.clone());
+        let result = self
+            .read("get todo", || self.todo_col.find_one(filter.clone(), None))
+            .await;
+        let mut todo = mongo_result(result)?.ok_or(Error::NotFound)?;
+        let subtask = todo
+            .subtasks
+            .iter_mut()
+            .find(|subtask| subtask.id == subtask_id)
+            .ok_or(Error::NotFound)?;
+        subtask.done = !subtask.done;
+        let mut set = doc! {
+            "subtasks.$.done": subtask.done,
+            "updated_at": format_timestamp(&Utc::now()),
+        };
+        if auto_complete && todo.complete_if_subtasks_done() {
+            set.insert("completed", true);
+            set.insert("completed_at", bson_value(&todo.completed_at)?);
+        }
+
+        // the version guard makes the read-modify-write safe against a
+        // concurrent toggle
+        let mut filter = filter;
+        filter.insert("version", todo.version as i64);
+        let update = doc! {
+            "$set": set,
+            "$inc": { "version": 1_i64 },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let result = self
+            .write("toggle subtask", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        match mongo_result(result) {
+            Err(Error::NotFound) => Err(Error::Conflict),
+            result => result?.ok_or(Error::NotFound),
+        }
+    }
+
+    async fn remove_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+    ) -> Result<Todo, Error> {
+        let mut filter = owner_filter(ctx, id);
+        filter.insert("subtasks.id", subtask_id.clone());
+        let update = doc! {
+            "$pull": { "subtasks": { "id": subtask_id } },
+            "$set": { "updated_at": format_timestamp(&Utc::now()) },
+            "$inc": { "version": 1_i64 },
+        };
+        let options = FindOneAndUpdateOptions::builder()
+            .return_document(ReturnDocument::After)
+            .build();
+        let result = self
+            .write("remove subtask", || {
+                self.todo_col
+                    .find_one_and_update(filter.clone(), update.clone(), options.clone())
+            })
+            .await;
+        mongo_result(result)?.ok_or(Error::NotFound)
     }
 
     async fn create_user(
@@ -146,13 +781,10 @@ impl TodoStore for MongoStore {
         email: String,
     ) -> Result<User, Error> {
         let user = User::new(external_id, name, email, Uuid::new_v4().to_string());
-        self.user_col
-            .insert_one(user.clone(), None)
-            .await
-            .map_err(|e| {
-                error!("Failed to insert user: {:?}", e);
-                Error::DatabaseOperationFailed(format!("Failed to insert user: {:?}", e))
-            })?;
+        self.write("insert user", || {
+            self.user_col.insert_one(user.clone(), None)
+        })
+        .await?;
         info!("Added user: {:?}", user);
         Ok(user)
     }
@@ -161,7 +793,314 @@ impl TodoStore for MongoStore {
         let filter = doc! {
             "external_id": external_user_id,
         };
-        let result = self.user_col.find_one(filter, None).await;
-        mongo_result(result, "get user").await
+        let result = self
+            .read("get user", || self.user_col.find_one(filter.clone(), None))
+            .await;
+        mongo_result(result)
+    }
+
+    async fn get_user_by_id(&self, id: String) -> Result<Option<User>, Error> {
+        let filter = doc! {
+            "id": id,
+        };
+        let result = self
+            .read("get user by id", || {
+                self.user_col.find_one(filter.clone(), None)
+            })
+            .await;
+        mongo_result(result)
+    }
+
+    async fn list_users(
+        &self,
+        tenant_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<User>, Error> {
+        let filter = doc! {
+            "tenant_id": tenant_id,
+        };
+        let options = FindOptions::builder()
+            .sort(doc! { "id": 1 })
+            .skip(offset)
+            .limit(limit as i64)
+            .build();
+        self.read("list users", || {
+            find_all(&self.user_col, filter.clone(), options.clone())
+        })
+        .await
+    }
+
+    async fn export_user_data(
+        &self,
+        tenant_id: String,
+        user_id: String,
+    ) -> Result<UserExport, Error> {
+        let filter = doc! { "tenant_id": tenant_id, "id": user_id.clone() };
+        let result = self
+            .read("get user", || self.user_col.find_one(filter.clone(), None))
+            .await;
+        let user = mongo_result(result)?.ok_or(Error::NotFound)?;
+        // tombstones included: they hold the user's data until purged
+        let filter = doc! { "tenant_id": user.tenant_id.clone(), "user_id": user_id };
+        let options = FindOptions::builder().sort(doc! { "id": 1 }).build();
+        let todos = self
+            .read("export todos", || {
+                find_all(&self.todo_col, filter.clone(), options.clone())
+            })
+            .await?;
+        Ok(UserExport {
+            exported_at: Utc::now(),
+            user,
+            todos,
+        })
+    }
+
+    /// Not a transaction, which would need a replica set; deleting is
+    /// idempotent, so a failed cascade can simply be retried.
+    async fn delete_user(
+        &self,
+        admin: &UserContext,
+        user_id: String,
+    ) -> Result<UserDeletion, Error> {
+        let tenant_id = admin.tenant_id.clone();
+        let user_filter = doc! { "tenant_id": tenant_id.clone(), "id": user_id.clone() };
+        let result = self
+            .read("get user", || {
+                self.user_col.find_one(user_filter.clone(), None)
+            })
+            .await;
+        mongo_result(result)?;
+        // deleting again is harmless, so these are retried like reads
+        let todo_filter = doc! { "tenant_id": tenant_id, "user_id": user_id.clone() };
+        let todos_deleted = self
+            .read("delete user todos", || {
+                self.todo_col.delete_many(todo_filter.clone(), None)
+            })
+            .await?
+            .deleted_count;
+        self.read("delete user", || {
+            self.user_col.delete_one(user_filter.clone(), None)
+        })
+        .await
+        .map_err(|e| match e {
+            Error::DatabaseOperationFailed(reason) => {
+                let message = format!(
+                    "Deleted {} todos of user {} but not the user, retry to finish: {}",
+                    todos_deleted, user_id, reason
+                );
+                error!("{}", message);
+                Error::DatabaseOperationFailed(message)
+            }
+            e => e,
+        })?;
+        info!("Deleted user {} and {} todos", user_id, todos_deleted);
+        Ok(UserDeletion {
+            user_id,
+            todos_deleted,
+        })
+    }
+
+    async fn changes_since(
+        &self,
+        ctx: &UserContext,
+        since: Option<ChangeCursor>,
+        limit: u64,
+    ) -> Result<Vec<Todo>, Error> {
+        let mut filter = readable_filter(ctx);
+        if let Some(since) = since {
+            // timestamps are stored fixed-width, so they compare as strings
+            let updated_at = format_timestamp(&since.updated_at);
+            let after = match since.id {
+                Some(id) => doc! {
+                    "$or": [
+                        { "updated_at": { "$gt": updated_at.clone() } },
+                        { "updated_at": updated_at, "id": { "$gt": id } },
+                    ],
+                },
+                None => doc! { "updated_at": { "$gt": updated_at } },
+            };
+            // `readable_filter` already has an `$or`
+            filter = doc! { "$and": [filter, after] };
+        }
+        let options = FindOptions::builder()
+            .sort(doc! { "updated_at": 1, "id": 1 })
+            .limit(limit as i64)
+            .build();
+        self.read("get changes", || {
+            find_all(&self.todo_col, filter.clone(), options.clone())
+        })
+        .await
+    }
+
+    async fn purge_deleted(
+        &self,
+        ctx: &UserContext,
+        older_than: chrono::Duration,
+    ) -> Result<u64, Error> {
+        // timestamps are stored fixed-width, so they compare as strings
+        let cutoff = format_timestamp(&(Utc::now() - older_than));
+        let filter = doc! {
+            "tenant_id": &ctx.tenant_id,
+            "deleted_at": { "$ne": Bson::Null, "$lt": cutoff },
+        };
+        // deleting again is harmless, so this is retried like a read
+        let result = self
+            .read("purge deleted todos", || {
+                self.todo_col.delete_many(filter.clone(), None)
+            })
+            .await?;
+        Ok(result.deleted_count)
+    }
+
+    async fn stats(&self) -> Result<StoreStats, Error> {
+        let mut stats = StoreStats::default();
+        let todo_counts = self
+            .read("count todos by tenant", || count_by_tenant(&self.todo_col))
+            .await?;
+        for (tenant_id, todos) in todo_counts {
+            stats.todos += todos;
+            stats.tenants.entry(tenant_id).or_default().todos = todos;
+        }
+        let user_counts = self
+            .read("count users by tenant", || count_by_tenant(&self.user_col))
+            .await?;
+        for (tenant_id, users) in user_counts {
+            stats.users += users;
+            stats.tenants.entry(tenant_id).or_default().users = users;
+        }
+        Ok(stats)
+    }
+
+    /// Change streams need a replica set. Deleting a todo sets `deleted_at`,
+    /// which is reported as a delete; hard deletes are only reported when
+    /// pre-images are enabled on the collection, since the deleted document is
+    /// otherwise unavailable to filter on.
+    async fn watch(&self, ctx: &UserContext) -> Result<BoxStream<'static, TodoEvent>, Error> {
+        let pipeline = vec![doc! {
+            "$match": {
+                "$or": [
+                    {
+                        "fullDocument.tenant_id": ctx.tenant_id.clone(),
+                        "fullDocument.user_id": ctx.user_id.clone(),
+                    },
+                    {
+                        "fullDocumentBeforeChange.tenant_id": ctx.tenant_id.clone(),
+                        "fullDocumentBeforeChange.user_id": ctx.user_id.clone(),
+                    },
+                ]
+            }
+        }];
+        let options = ChangeStreamOptions::builder()
+            .full_document(Some(FullDocumentType::UpdateLookup))
+            .full_document_before_change(Some(FullDocumentBeforeChangeType::WhenAvailable))
+            .build();
+        let changes = self
+            .read("watch todos", || {
+                self.todo_col.watch(pipeline.clone(), options.clone())
+            })
+            .await?;
+        let events = changes.filter_map(|change| async move {
+            match change {
+                Ok(change) => match change.operation_type {
+                    OperationType::Insert => change.full_document.map(TodoEvent::Added),
+                    OperationType::Update | OperationType::Replace => {
+                        change.full_document.map(|todo| match todo.deleted_at {
+                            Some(_) => TodoEvent::Deleted(todo),
+                            None => TodoEvent::Updated(todo),
+                        })
+                    }
+                    OperationType::Delete => {
+                        change.full_document_before_change.map(TodoEvent::Deleted)
+                    }
+                    _ => None,
+                },
+                Err(e) => {
+                    error!("Failed to read todo change: {:?}", e);
+                    None
+                }
+            }
+        });
+        Ok(events.boxed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const TIMEOUT: Duration = Duration::from_millis(20);
+
+    fn network_error() -> mongodb::error::Error {
+        std::io::ErrorKind::ConnectionReset.into()
+    }
+
+    #[tokio::test]
+    async fn test_run_retries_transient_reads() {
+        let calls = AtomicU32::new(0);
+        let result = run("get todo", Access::Read, TIMEOUT, || async {
+            match calls.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(network_error()),
+                _ => Ok(1),
+            }
+        })
+        .await;
+        assert_eq!(result, Ok(1));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<(), Error> = run("get todo", Access::Read, TIMEOUT, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::future::pending().await
+        })
+        .await;
+        match result {
+            Err(Error::DatabaseOperationFailed(message)) => {
+                assert!(message.contains("timed out"), "{}", message);
+                assert!(message.ends_with("(after 2 attempts)"), "{}", message);
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_does_not_repeat_writes() {
+        let calls = AtomicU32::new(0);
+        let result: Result<(), Error> = run("insert todo", Access::Write, TIMEOUT, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            std::future::pending().await
+        })
+        .await;
+        assert!(matches!(result, Err(Error::DatabaseOperationFailed(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: Result<(), Error> = run("insert todo", Access::Write, TIMEOUT, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(network_error())
+        })
+        .await;
+        assert!(matches!(result, Err(Error::DatabaseOperationFailed(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_run_reports_duplicate_key_as_conflict() {
+        let duplicate_key = || -> mongodb::error::Error {
+            let write_error =
+                mongodb::bson::from_document(doc! { "code": DUPLICATE_KEY, "errmsg": "E11000" })
+                    .unwrap();
+            ErrorKind::Write(WriteFailure::WriteError(write_error)).into()
+        };
+        let calls = AtomicU32::new(0);
+        let result: Result<(), Error> = run("insert todo", Access::Write, TIMEOUT, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(duplicate_key())
+        })
+        .await;
+        assert_eq!(result, Err(Error::Conflict));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
     }
 }
@@ -1,25 +1,178 @@
 use crate::error::Error;
-use crate::model::{NewTodo, Todo, UpdateTodo, User};
+use crate::model::{NewTodo, Subtask, Todo, UpdateTodo, User};
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone)]
 pub struct UserContext {
     pub tenant_id: String,
     pub user_id: String,
+    pub roles: Vec<String>,
+    /// OAuth scopes granted to the token, e.g. `read:todos`.
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TodoFilter {
+    /// Only return todos carrying at least one of these tags; empty means no filtering.
+    pub tags: Vec<String>,
+    /// Keyset cursor: only return todos whose id sorts after this one.
+    pub after: Option<String>,
+    /// Maximum number of todos to return, ordered by id.
+    pub limit: Option<u64>,
+    /// Only return todos in this archive state; `None` returns both.
+    pub archived: Option<bool>,
+}
+
+/// Where a `changes_since` page left off. Changes are ordered by
+/// `(updated_at, id)`, so the id is needed to resume inside a group of todos
+/// changed at the same instant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeCursor {
+    pub updated_at: DateTime<Utc>,
+    /// `None` skips every change at `updated_at`.
+    pub id: Option<String>,
+}
+
+/// Result of `update_todo`. Completing a recurring todo also creates its next
+/// occurrence, which is returned alongside the updated todo.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpdatedTodo {
+    #[serde(flatten)]
+    pub todo: Todo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_occurrence: Option<Todo>,
+}
+
+/// Todo and user counts for one tenant.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TenantStats {
+    pub todos: u64,
+    pub users: u64,
+}
+
+/// Storage-wide counts for capacity planning, broken down by tenant.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StoreStats {
+    pub todos: u64,
+    pub users: u64,
+    pub tenants: BTreeMap<String, TenantStats>,
+}
+
+/// Everything stored about one user, for data-subject access requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserExport {
+    pub exported_at: DateTime<Utc>,
+    pub user: User,
+    /// Todos the user owns, ordered by id; deleted ones that have not been
+    /// purged yet carry `deleted_at`.
+    pub todos: Vec<Todo>,
+}
+
+/// What `delete_user` removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserDeletion {
+    pub user_id: String,
+    pub todos_deleted: u64,
+}
+
+/// A change to a todo, published by the store after the mutation succeeds.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", content = "todo", rename_all = "snake_case")]
+pub enum TodoEvent {
+    Added(Todo),
+    Updated(Todo),
+    Deleted(Todo),
+}
+
+impl TodoEvent {
+    pub fn todo(&self) -> &Todo {
+        match self {
+            TodoEvent::Added(todo) | TodoEvent::Updated(todo) | TodoEvent::Deleted(todo) => todo,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TodoEvent::Added(_) => "added",
+            TodoEvent::Updated(_) => "updated",
+            TodoEvent::Deleted(_) => "deleted",
+        }
+    }
 }
 
 #[async_trait]
 pub trait TodoStore: Send + Sync {
     async fn add_todo(&self, ctx: &UserContext, new_todo: NewTodo) -> Result<(), Error>;
+    /// Adds the todos and returns them in order. A duplicate id, within the
+    /// batch or with an existing todo, is `Error::Conflict`. Stores without
+    /// transactions may keep the todos before a failed insert; ids stay
+    /// unique, so a retry cannot duplicate them.
+    async fn add_todos(
+        &self,
+        ctx: &UserContext,
+        new_todos: Vec<NewTodo>,
+    ) -> Result<Vec<Todo>, Error>;
+    /// A missing todo is `Error::NotFound` rather than `Ok(None)`.
     async fn get_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error>;
-    async fn get_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error>;
+    /// Matching nothing is an empty list, never `Error::NotFound`.
+    async fn get_todos(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<Vec<Todo>, Error>;
+    /// Todos in the tenant assigned to `ctx`, whoever owns them.
+    async fn get_assigned_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error>;
+    async fn count_todos(&self, ctx: &UserContext, completed: Option<bool>) -> Result<u64, Error>;
+    /// How many todos `get_todos` matches for `filter` across all pages, i.e.
+    /// ignoring `after` and `limit`.
+    async fn count_matching(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<u64, Error>;
     async fn update_todo(
         &self,
         ctx: &UserContext,
         id: String,
         update_todo: UpdateTodo,
-    ) -> Result<Option<Todo>, Error>;
+        version: Option<u64>,
+    ) -> Result<UpdatedTodo, Error>;
+    /// Sets only `completed` and `completed_at`; a todo already in that state
+    /// is returned unchanged. Completing a recurring todo creates its next
+    /// occurrence like `update_todo` does.
+    async fn set_completed(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        completed: bool,
+    ) -> Result<UpdatedTodo, Error>;
     async fn delete_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error>;
+    /// Lets `user_id` read the todo; only the owner can share it.
+    async fn share_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        user_id: String,
+    ) -> Result<Todo, Error>;
+    async fn archive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error>;
+    async fn unarchive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error>;
+    async fn add_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask: Subtask,
+    ) -> Result<Todo, Error>;
+    /// Flips a subtask's `done` flag. With `auto_complete`, finishing the last
+    /// open subtask also completes the todo.
+    async fn toggle_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+        auto_complete: bool,
+    ) -> Result<Todo, Error>;
+    async fn remove_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+    ) -> Result<Todo, Error>;
     async fn create_user(
         &self,
         external_id: String,
@@ -27,4 +180,44 @@ pub trait TodoStore: Send + Sync {
         email: String,
     ) -> Result<User, Error>;
     async fn get_user(&self, external_user_id: String) -> Result<Option<User>, Error>;
+    async fn get_user_by_id(&self, id: String) -> Result<Option<User>, Error>;
+    /// A page of the users in `tenant_id`, ordered by id.
+    async fn list_users(
+        &self,
+        tenant_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<User>, Error>;
+    /// The user with id `user_id` in `tenant_id` and all of their todos.
+    async fn export_user_data(
+        &self,
+        tenant_id: String,
+        user_id: String,
+    ) -> Result<UserExport, Error>;
+    /// Permanently removes the user with id `user_id` in the tenant of
+    /// `admin`, who made the request, together with every todo they own,
+    /// tombstones included. Todos go first, so a failure part way leaves the
+    /// user in place to retry rather than todos without an owner.
+    async fn delete_user(
+        &self,
+        admin: &UserContext,
+        user_id: String,
+    ) -> Result<UserDeletion, Error>;
+    /// Todos visible to `ctx` changed after `since`, oldest change first and
+    /// then by id, including tombstones of deleted todos. `None` starts from
+    /// the beginning.
+    async fn changes_since(
+        &self,
+        ctx: &UserContext,
+        since: Option<ChangeCursor>,
+        limit: u64,
+    ) -> Result<Vec<Todo>, Error>;
+    /// Permanently removes the tombstones in `ctx`'s tenant deleted more than
+    /// `older_than` ago and returns how many were removed. Clients that last
+    /// synced before then will not see those deletions in `changes_since`.
+    async fn purge_deleted(&self, ctx: &UserContext, older_than: Duration) -> Result<u64, Error>;
+    /// Counts across all tenants; only for admin use.
+    async fn stats(&self) -> Result<StoreStats, Error>;
+    /// Streams changes to the todos owned by `ctx` until the store shuts down.
+    async fn watch(&self, ctx: &UserContext) -> Result<BoxStream<'static, TodoEvent>, Error>;
 }
@@ -0,0 +1,304 @@
+use crate::error::Error;
+use crate::model::{NewTodo, Subtask, Todo, UpdateTodo, User};
+use crate::storage::store::{
+    ChangeCursor, StoreStats, TodoEvent, TodoFilter, TodoStore, UpdatedTodo, UserContext,
+    UserDeletion, UserExport,
+};
+use crate::webhook::{WebhookEvent, WebhookEventType, Webhooks};
+use async_trait::async_trait;
+use chrono::Duration;
+use futures::stream::BoxStream;
+
+/// Wraps a store and queues a webhook event for every todo that is created,
+/// completed or deleted. Delivery happens in the background, so the result of
+/// the wrapped call is returned unchanged.
+pub struct WebhookStore<S> {
+    inner: S,
+    webhooks: Webhooks,
+}
+
+impl<S: TodoStore> WebhookStore<S> {
+    pub fn new(inner: S, webhooks: Webhooks) -> Self {
+        Self { inner, webhooks }
+    }
+
+    fn notify(&self, ctx: &UserContext, event_type: WebhookEventType, todo: &Todo) {
+        self.webhooks
+            .notify(WebhookEvent::new(event_type, ctx, todo.clone()));
+    }
+
+    /// Whether the todo is currently done, read before a change that may
+    /// complete it so reopening or re-completing it is not reported.
+    async fn is_completed(&self, ctx: &UserContext, id: &str) -> bool {
+        match self.inner.get_todo(ctx, id.to_string()).await {
+            Ok(Some(todo)) => todo.completed,
+            _ => true,
+        }
+    }
+
+    fn notify_completed(&self, ctx: &UserContext, was_completed: bool, todo: &Todo) {
+        if !was_completed && todo.completed {
+            self.notify(ctx, WebhookEventType::Completed, todo);
+        }
+    }
+}
+
+#[async_trait]
+impl<S: TodoStore> TodoStore for WebhookStore<S> {
+    async fn add_todo(&self, ctx: &UserContext, new_todo: NewTodo) -> Result<(), Error> {
+        // add_todos hands back the created todo, which add_todo does not
+        let todos = self.inner.add_todos(ctx, vec![new_todo]).await?;
+        todos
+            .iter()
+            .for_each(|todo| self.notify(ctx, WebhookEventType::Created, todo));
+        Ok(())
+    }
+
+    async fn add_todos(
+        &self,
+        ctx: &UserContext,
+        new_todos: Vec<NewTodo>,
+    ) -> Result<Vec<Todo>, Error> {
+        let todos = self.inner.add_todos(ctx, new_todos).await?;
+        todos
+            .iter()
+            .for_each(|todo| self.notify(ctx, WebhookEventType::Created, todo));
+        Ok(todos)
+    }
+
+    async fn get_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        self.inner.get_todo(ctx, id).await
+    }
+
+    async fn get_todos(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<Vec<Todo>, Error> {
+        self.inner.get_todos(ctx, filter).await
+    }
+
+    async fn get_assigned_todos(&self, ctx: &UserContext) -> Result<Vec<Todo>, Error> {
+        self.inner.get_assigned_todos(ctx).await
+    }
+
+    async fn count_todos(&self, ctx: &UserContext, completed: Option<bool>) -> Result<u64, Error> {
+        self.inner.count_todos(ctx, completed).await
+    }
+
+    async fn count_matching(&self, ctx: &UserContext, filter: &TodoFilter) -> Result<u64, Error> {
+        self.inner.count_matching(ctx, filter).await
+    }
+
+    async fn update_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        update_todo: UpdateTodo,
+        version: Option<u64>,
+    ) -> Result<UpdatedTodo, Error> {
+        let was_completed = match update_todo.completed {
+            Some(true) => self.is_completed(ctx, &id).await,
+            _ => true,
+        };
+        let updated = self
+            .inner
+            .update_todo(ctx, id, update_todo, version)
+            .await?;
+        self.notify_completed(ctx, was_completed, &updated.todo);
+        if let Some(next) = &updated.next_occurrence {
+            self.notify(ctx, WebhookEventType::Created, next);
+        }
+        Ok(updated)
+    }
+
+    async fn set_completed(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        completed: bool,
+    ) -> Result<UpdatedTodo, Error> {
+        let was_completed = !completed || self.is_completed(ctx, &id).await;
+        let updated = self.inner.set_completed(ctx, id, completed).await?;
+        self.notify_completed(ctx, was_completed, &updated.todo);
+        if let Some(next) = &updated.next_occurrence {
+            self.notify(ctx, WebhookEventType::Created, next);
+        }
+        Ok(updated)
+    }
+
+    async fn delete_todo(&self, ctx: &UserContext, id: String) -> Result<Option<Todo>, Error> {
+        let deleted = self.inner.delete_todo(ctx, id).await?;
+        if let Some(todo) = &deleted {
+            self.notify(ctx, WebhookEventType::Deleted, todo);
+        }
+        Ok(deleted)
+    }
+
+    async fn share_todo(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        user_id: String,
+    ) -> Result<Todo, Error> {
+        self.inner.share_todo(ctx, id, user_id).await
+    }
+
+    async fn archive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        self.inner.archive_todo(ctx, id).await
+    }
+
+    async fn unarchive_todo(&self, ctx: &UserContext, id: String) -> Result<Todo, Error> {
+        self.inner.unarchive_todo(ctx, id).await
+    }
+
+    async fn add_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask: Subtask,
+    ) -> Result<Todo, Error> {
+        self.inner.add_subtask(ctx, id, subtask).await
+    }
+
+    async fn toggle_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+        auto_complete: bool,
+    ) -> Result<Todo, Error> {
+        let was_completed = !auto_complete || self.is_completed(ctx, &id).await;
+        let todo = self
+            .inner
+            .toggle_subtask(ctx, id, subtask_id, auto_complete)
+            .await?;
+        self.notify_completed(ctx, was_completed, &todo);
+        Ok(todo)
+    }
+
+    async fn remove_subtask(
+        &self,
+        ctx: &UserContext,
+        id: String,
+        subtask_id: String,
+    ) -> Result<Todo, Error> {
+        self.inner.remove_subtask(ctx, id, subtask_id).await
+    }
+
+    async fn create_user(
+        &self,
+        external_id: String,
+        name: String,
+        email: String,
+    ) -> Result<User, Error> {
+        self.inner.create_user(external_id, name, email).await
+    }
+
+    async fn get_user(&self, external_user_id: String) -> Result<Option<User>, Error> {
+        self.inner.get_user(external_user_id).await
+    }
+
+    async fn get_user_by_id(&self, id: String) -> Result<Option<User>, Error> {
+        self.inner.get_user_by_id(id).await
+    }
+
+    async fn list_users(
+        &self,
+        tenant_id: String,
+        limit: u64,
+        offset: u64,
+    ) -> Result<Vec<User>, Error> {
+        self.inner.list_users(tenant_id, limit, offset).await
+    }
+
+    async fn export_user_data(
+        &self,
+        tenant_id: String,
+        user_id: String,
+    ) -> Result<UserExport, Error> {
+        self.inner.export_user_data(tenant_id, user_id).await
+    }
+
+    async fn delete_user(
+        &self,
+        admin: &UserContext,
+        user_id: String,
+    ) -> Result<UserDeletion, Error> {
+        self.inner.delete_user(admin, user_id).await
+    }
+
+    async fn changes_since(
+        &self,
+        ctx: &UserContext,
+        since: Option<ChangeCursor>,
+        limit: u64,
+    ) -> Result<Vec<Todo>, Error> {
+        self.inner.changes_since(ctx, since, limit).await
+    }
+
+    async fn purge_deleted(&self, ctx: &UserContext, older_than: Duration) -> Result<u64, Error> {
+        self.inner.purge_deleted(ctx, older_than).await
+    }
+
+    async fn stats(&self) -> Result<StoreStats, Error> {
+        self.inner.stats().await
+    }
+
+    async fn watch(&self, ctx: &UserContext) -> Result<BoxStream<'static, TodoEvent>, Error> {
+        self.inner.watch(ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemStore;
+
+    fn new_todo(task: &str) -> NewTodo {
+        NewTodo {
+            id: None,
+            task: task.to_string(),
+            completed: false,
+            tags: vec![],
+            due_date: None,
+            recurrence: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let (webhooks, mut events) = Webhooks::channel(10);
+        let store = WebhookStore::new(MemStore::new("test.json".to_string()), webhooks);
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+
+        store.add_todo(&ctx, new_todo("first")).await.unwrap();
+        let todos = store
+            .add_todos(&ctx, vec![new_todo("second")])
+            .await
+            .unwrap();
+        let id = todos[0].id.clone();
+        store.set_completed(&ctx, id.clone(), true).await.unwrap();
+        // completing it again is not reported
+        store.set_completed(&ctx, id.clone(), true).await.unwrap();
+        store.set_completed(&ctx, id.clone(), false).await.unwrap();
+        store.delete_todo(&ctx, id.clone()).await.unwrap();
+
+        let mut received = vec![];
+        while let Ok(event) = events.try_recv() {
+            assert_eq!(event.tenant_id, "tenant");
+            assert_eq!(event.user_id, "user");
+            received.push((event.event_type, event.todo.task));
+        }
+        assert_eq!(
+            received,
+            vec![
+                (WebhookEventType::Created, "first".to_string()),
+                (WebhookEventType::Created, "second".to_string()),
+                (WebhookEventType::Completed, "second".to_string()),
+                (WebhookEventType::Deleted, "second".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use crate::model::Todo;
+use crate::storage::UserContext;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use log::{error, warn};
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>` when a secret is set.
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventType {
+    Created,
+    Completed,
+    Deleted,
+}
+
+/// Body POSTed to the webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookEvent {
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub timestamp: DateTime<Utc>,
+    /// Tenant and user who made the change.
+    pub tenant_id: String,
+    pub user_id: String,
+    pub todo: Todo,
+}
+
+impl WebhookEvent {
+    pub fn new(event_type: WebhookEventType, ctx: &UserContext, todo: Todo) -> Self {
+        Self {
+            event_type,
+            timestamp: Utc::now(),
+            tenant_id: ctx.tenant_id.clone(),
+            user_id: ctx.user_id.clone(),
+            todo,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Payloads are signed when set.
+    pub secret: Option<String>,
+    /// Events waiting to be delivered; more are dropped.
+    pub queue_size: usize,
+    /// Deliveries tried per event, the first one included.
+    pub attempts: u32,
+    /// Wait before the first retry, doubled for each one after.
+    pub backoff: Duration,
+    pub timeout: Duration,
+}
+
+impl WebhookConfig {
+    pub fn new(url: String, secret: Option<String>) -> Self {
+        Self {
+            url,
+            secret,
+            queue_size: 1000,
+            attempts: 3,
+            backoff: Duration::from_secs(1),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Queues events for a background task that delivers them one at a time, so
+/// a slow or failing endpoint never holds up a request.
+#[derive(Debug, Clone)]
+pub struct Webhooks {
+    sender: mpsc::Sender<WebhookEvent>,
+}
+
+impl Webhooks {
+    /// Spawns the delivery task; must be called within a Tokio runtime.
+    pub fn start(config: WebhookConfig) -> Self {
+        let (webhooks, receiver) = Self::channel(config.queue_size);
+        tokio::spawn(dispatch(receiver, config));
+        webhooks
+    }
+
+    pub(crate) fn channel(queue_size: usize) -> (Self, mpsc::Receiver<WebhookEvent>) {
+        let (sender, receiver) = mpsc::channel(queue_size);
+        (Self { sender }, receiver)
+    }
+
+    pub fn notify(&self, event: WebhookEvent) {
+        let (reason, event) = match self.sender.try_send(event) {
+            Ok(()) => return,
+            Err(mpsc::error::TrySendError::Full(event)) => ("queue is full", event),
+            Err(mpsc::error::TrySendError::Closed(event)) => ("dispatcher has stopped", event),
+        };
+        warn!(
+            "Dropping webhook event for todo {}: {}",
+            event.todo.id, reason
+        );
+    }
+}
+
+/// `sha256=` followed by the hex HMAC-SHA256 of `body` under `secret`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+async fn dispatch(mut receiver: mpsc::Receiver<WebhookEvent>, config: WebhookConfig) {
+    let client = reqwest::Client::builder()
+        .timeout(config.timeout)
+        .build()
+        .unwrap_or_default();
+    while let Some(event) = receiver.recv().await {
+        let body = match serde_json::to_vec(&event) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize webhook event: {}", e);
+                continue;
+            }
+        };
+        deliver(&client, &config, &event, body).await;
+    }
+}
+
+async fn deliver(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    event: &WebhookEvent,
+    body: Vec<u8>,
+) {
+    let mut backoff = config.backoff;
+    for attempt in 1..=config.attempts {
+        let mut request = client
+            .post(&config.url)
+            .header("Content-Type", "application/json");
+        if let Some(secret) = &config.secret {
+            request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+        }
+        let result = request
+            .body(body.clone())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return,
+            Err(e) if attempt < config.attempts => {
+                warn!(
+                    "Webhook delivery for todo {} failed (attempt {}/{}): {}; retrying in {:?}",
+                    event.todo.id, attempt, config.attempts, e, backoff
+                );
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(e) => error!(
+                "Giving up on webhook delivery for todo {} after {} attempts: {}",
+                event.todo.id, attempt, e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::NewTodo;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{Arc, Mutex};
+    use warp::Filter;
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231, test case 2
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retried_and_signed() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let received = Arc::new(Mutex::new(None));
+        let endpoint = {
+            let calls = calls.clone();
+            let received = received.clone();
+            warp::post()
+                .and(warp::header::<String>("x-webhook-signature"))
+                .and(warp::body::bytes())
+                .map(move |signature: String, body: warp::hyper::body::Bytes| {
+                    // the first delivery fails
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        return warp::http::StatusCode::SERVICE_UNAVAILABLE;
+                    }
+                    *received.lock().unwrap() = Some((signature, body.to_vec()));
+                    warp::http::StatusCode::OK
+                })
+        };
+        let (addr, server) = warp::serve(endpoint).bind_ephemeral(([127, 0, 0, 1], 0));
+        tokio::spawn(server);
+
+        let config = WebhookConfig {
+            backoff: Duration::from_millis(10),
+            ..WebhookConfig::new(format!("http://{}/", addr), Some("secret".to_string()))
+        };
+        let todo = Todo::new(
+            "tenant".to_string(),
+            "user".to_string(),
+            NewTodo {
+                id: None,
+                task: "test".to_string(),
+                completed: false,
+                tags: vec![],
+                due_date: None,
+                recurrence: None,
+            },
+        );
+        let webhooks = Webhooks::start(config);
+        let ctx = UserContext {
+            tenant_id: "tenant".to_string(),
+            user_id: "user".to_string(),
+            roles: vec![],
+            scopes: vec![],
+        };
+        webhooks.notify(WebhookEvent::new(
+            WebhookEventType::Created,
+            &ctx,
+            todo.clone(),
+        ));
+
+        for _ in 0..100 {
+            if received.lock().unwrap().is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let (signature, body) = received.lock().unwrap().take().expect("delivered");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert_eq!(signature, sign("secret", &body));
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["type"], "created");
+        assert_eq!(body["tenant_id"], "tenant");
+        assert_eq!(body["user_id"], "user");
+        assert_eq!(body["todo"]["id"], todo.id);
+    }
+}